@@ -0,0 +1,194 @@
+use std::io::{self, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use clap::Parser;
+
+use sony_bravia_cli::emulator::{RequestParser, Tv, TvState, WARM_UP};
+use sony_bravia_cli::protocol::decode::hex;
+use sony_bravia_cli::protocol::function_name;
+
+#[derive(Parser, Debug)]
+#[command(name = "sony-bravia-emulator")]
+#[command(about = "Emulated Sony Bravia TV on a pseudo-terminal or TCP port")]
+struct Args {
+    /// Listen on this TCP address (e.g. 127.0.0.1:5000) instead of creating a pty
+    #[arg(long)]
+    listen: Option<String>,
+
+    /// Create a symlink to the pty at this path, e.g. /tmp/ttyBRAVIA
+    #[arg(long, conflicts_with = "listen")]
+    link: Option<String>,
+
+    /// Milliseconds the TV rejects packets after powering on
+    #[arg(long, default_value_t = WARM_UP.as_millis() as u64)]
+    warm_up: u64,
+
+    /// Start in standby instead of powered on
+    #[arg(long)]
+    standby: bool,
+
+    /// Do not log packets to stderr
+    #[arg(long)]
+    quiet: bool,
+}
+
+/// Answers packets read from `port` until it is closed.
+fn serve(tv: &Mutex<Tv>, mut port: impl Read + Write, quiet: bool) -> io::Result<()> {
+    let mut parser = RequestParser::default();
+    let mut buf = [0u8; 256];
+    loop {
+        let n = port.read(&mut buf)?;
+        if n == 0 {
+            return Ok(());
+        }
+        parser.push(&buf[..n]);
+        while let Some(packet) = parser.next_packet() {
+            let frame = tv.lock().unwrap().respond(&packet);
+            if !quiet {
+                let name = function_name(packet[2]).unwrap_or("unknown");
+                eprintln!("{} ({}) -> {}", hex(&packet), name, hex(&frame));
+            }
+            port.write_all(&frame)?;
+            port.flush()?;
+        }
+    }
+}
+
+fn listen(addr: &str, tv: Arc<Mutex<Tv>>, quiet: bool) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    println!("listening on {}", listener.local_addr()?);
+    io::stdout().flush()?;
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let tv = tv.clone();
+        thread::spawn(move || {
+            if let Err(e) = serve(&tv, stream, quiet) {
+                eprintln!("connection error: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+mod pty {
+    use std::ffi::CStr;
+    use std::fs::File;
+    use std::io;
+    use std::os::fd::FromRawFd;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    use std::sync::Mutex;
+
+    /// Opens a pty master in raw mode and returns it with the slave path.
+    pub fn open() -> io::Result<(File, String)> {
+        // SAFETY: plain libc calls on a descriptor we own; the name buffer
+        // is large enough for any /dev/pts path and NUL-terminated on success.
+        unsafe {
+            let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let master = File::from_raw_fd(fd);
+            if libc::grantpt(fd) != 0 || libc::unlockpt(fd) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let path = slave_name(fd)?;
+            let mut termios = std::mem::zeroed::<libc::termios>();
+            if libc::tcgetattr(fd, &mut termios) == 0 {
+                libc::cfmakeraw(&mut termios);
+                libc::tcsetattr(fd, libc::TCSANOW, &termios);
+            }
+            Ok((master, path))
+        }
+    }
+
+    /// Path of the slave side of the pty master `fd`.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    unsafe fn slave_name(fd: libc::c_int) -> io::Result<String> {
+        let mut name = [0 as libc::c_char; 128];
+        // SAFETY: the buffer is large enough for any /dev/pts path and is
+        // NUL-terminated on success.
+        unsafe {
+            if libc::ptsname_r(fd, name.as_mut_ptr(), name.len()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(CStr::from_ptr(name.as_ptr()).to_string_lossy().into_owned())
+        }
+    }
+
+    /// Path of the slave side of the pty master `fd`. Without `ptsname_r`,
+    /// as on macOS, `ptsname` returns a shared buffer, so it is read under a
+    /// lock.
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    unsafe fn slave_name(fd: libc::c_int) -> io::Result<String> {
+        static PTSNAME: Mutex<()> = Mutex::new(());
+        let _guard = PTSNAME.lock().unwrap_or_else(|e| e.into_inner());
+        // SAFETY: the returned string stays valid until the next `ptsname`
+        // call, which the lock holds off until it has been copied.
+        unsafe {
+            let name = libc::ptsname(fd);
+            if name.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(CStr::from_ptr(name).to_string_lossy().into_owned())
+        }
+    }
+
+    /// True for the EIO a pty master returns while no one has the slave open.
+    pub fn is_hangup(e: &io::Error) -> bool {
+        e.raw_os_error() == Some(libc::EIO)
+    }
+}
+
+#[cfg(unix)]
+fn run_pty(link: Option<&str>, tv: Arc<Mutex<Tv>>, quiet: bool) -> io::Result<()> {
+    let (master, path) = pty::open()?;
+    if let Some(link) = link {
+        match std::fs::remove_file(link) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        std::os::unix::fs::symlink(&path, link)?;
+    }
+    println!("pty: {}", link.unwrap_or(&path));
+    io::stdout().flush()?;
+    loop {
+        // Each client opens and closes the slave; until one does, reads fail.
+        match serve(&tv, &master, quiet) {
+            Err(e) if pty::is_hangup(&e) => thread::sleep(Duration::from_millis(50)),
+            Err(e) => return Err(e),
+            Ok(()) => {}
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn run_pty(_link: Option<&str>, _tv: Arc<Mutex<Tv>>, _quiet: bool) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "pseudo-terminals need a Unix system; use --listen",
+    ))
+}
+
+fn main() {
+    let args = Args::parse();
+    let state = TvState {
+        power: !args.standby,
+        ..TvState::default()
+    };
+    let tv = Arc::new(Mutex::new(Tv::new(
+        state,
+        Duration::from_millis(args.warm_up),
+    )));
+    let result = match &args.listen {
+        Some(addr) => listen(addr, tv, args.quiet),
+        None => run_pty(args.link.as_deref(), tv, args.quiet),
+    };
+    if let Err(e) = result {
+        eprintln!("Emulator error: {}", e);
+        std::process::exit(1);
+    }
+}
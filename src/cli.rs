@@ -1,12 +1,81 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use serialport::{DataBits, FlowControl, Parity, StopBits};
+
+use crate::protocol::{InputNameError, InputType};
+use crate::transport::serial::{
+    parse_data_bits, parse_flow_control, parse_parity, parse_stop_bits, parse_switch,
+};
 
 #[derive(Parser, Debug)]
 #[command(name = "sony-bravia")]
 #[command(about = "Sony Bravia TV RS232 control")]
 pub struct Cli {
-    /// Serial device path
-    #[arg(long, required = true)]
-    pub dev: String,
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+
+    /// Serial device path, tcp://host:port (raw serial server), rfc2217://host:port,
+    /// usb:vid=..,pid=..,serial=.. or by-id:<text> to match a USB adapter,
+    /// or auto to use the one serial port where a TV answers
+    #[arg(long)]
+    pub dev: Option<String>,
+
+    /// Probe all serial ports and list the ones where a TV answers
+    #[arg(long, conflicts_with = "replay")]
+    pub discover: bool,
+
+    /// Baud rates to try when discovering, comma separated (default: --baud)
+    #[arg(long, value_delimiter = ',')]
+    pub probe_baud: Vec<u32>,
+
+    /// TOML file with defaults for --dev and the serial settings
+    #[arg(long)]
+    pub config: Option<String>,
+
+    // === Serial Line ===
+    /// Baud rate (default 9600)
+    #[arg(long)]
+    pub baud: Option<u32>,
+
+    /// Data bits: 5, 6, 7, 8 (default 8)
+    #[arg(long, value_parser = parse_data_bits)]
+    pub data_bits: Option<DataBits>,
+
+    /// Parity: none, odd, even (default none)
+    #[arg(long, value_parser = parse_parity)]
+    pub parity: Option<Parity>,
+
+    /// Stop bits: 1, 2 (default 1)
+    #[arg(long, value_parser = parse_stop_bits)]
+    pub stop_bits: Option<StopBits>,
+
+    /// Flow control: none, software, hardware (default none)
+    #[arg(long, value_parser = parse_flow_control)]
+    pub flow_control: Option<FlowControl>,
+
+    /// Set DTR after opening: on, off (default: leave as the driver sets it)
+    #[arg(long, value_parser = parse_switch)]
+    pub dtr: Option<bool>,
+
+    /// Set RTS after opening: on, off (default: leave as the driver sets it)
+    #[arg(long, value_parser = parse_switch)]
+    pub rts: Option<bool>,
+
+    /// Response timeout in milliseconds (default 500, 1000 for tcp:// and rfc2217://)
+    #[arg(long)]
+    pub read_timeout: Option<u64>,
+
+    /// Write every packet sent and received, with timestamps, to this file
+    #[arg(long)]
+    pub trace: Option<String>,
+
+    /// Print the packets each command would send instead of opening a device;
+    /// queries fail since nothing answers them
+    #[arg(long, conflicts_with_all = ["discover", "replay", "trace"])]
+    pub dry_run: bool,
+
+    /// Replay a trace file through a mock transport and print how each response parses
+    #[arg(long, conflicts_with = "dev")]
+    pub replay: Option<String>,
 
     // === Mode Control ===
     /// Power control: on, off
@@ -19,7 +88,7 @@ pub struct Cli {
 
     /// Input selection: hdmi1-5, component1-3, video1-3, pc1, shared1
     #[arg(long, value_parser = parse_input)]
-    pub input: Option<String>,
+    pub input: Option<InputType>,
 
     /// Toggle input
     #[arg(long)]
@@ -201,50 +270,112 @@ pub struct Cli {
     #[arg(long, value_parser = ["query"])]
     pub device_id: Option<String>,
 
+    // === Raw Packets ===
+    /// Send a control packet for any function code: FUNCTION [DATA...], bytes in hex
+    #[arg(long, num_args = 1..=255, value_name = "BYTE", value_parser = parse_byte)]
+    pub raw_control: Option<Vec<u8>>,
+
+    /// Send a query packet for any function code (hex)
+    #[arg(long, value_name = "FUNCTION", value_parser = parse_byte)]
+    pub raw_query: Option<u8>,
+
     // === Status ===
     /// Show TV status
     #[arg(long)]
     pub status: bool,
 
+    // === Retries ===
+    /// Resends after a timeout, garbled response or busy TV
+    #[arg(long, default_value = "2")]
+    pub retries: u32,
+
+    /// Resend control commands too, up to --retries times; a toggle may then run twice
+    #[arg(long)]
+    pub retry_controls: bool,
+
+    /// Resends for control commands; implies --retry-controls
+    #[arg(long)]
+    pub control_retries: Option<u32>,
+
+    /// Delay before the first resend in milliseconds, doubled for each one after
+    #[arg(long, default_value = "100")]
+    pub retry_backoff: u64,
+
+    /// Maximum random delay in milliseconds added to each resend
+    #[arg(long, default_value = "50")]
+    pub retry_jitter: u64,
+
+    /// Delay in milliseconds before resending a command the TV canceled
+    #[arg(long, default_value = "500")]
+    pub canceled_retry_delay: u64,
+
+    /// Longest wait in milliseconds for the TV to accept commands after power-on (0 to not wait)
+    #[arg(long, default_value = "15000")]
+    pub warm_up_timeout: u64,
+
+    /// Interval in milliseconds between power queries while the TV warms up
+    #[arg(long, default_value = "500")]
+    pub warm_up_poll: u64,
+
+    /// Minimum gap in milliseconds between one response and the next packet
+    #[arg(long, default_value = "100")]
+    pub min_gap: u64,
+
+    /// Gap in milliseconds after power, input select and picture mode commands
+    #[arg(long, default_value = "1000")]
+    pub slow_gap: u64,
+
     // === HTTP Server ===
     /// Start HTTP server
-    #[arg(long)]
+    #[cfg(feature = "http")]
+    #[arg(long, conflicts_with_all = ["discover", "replay"])]
     pub http_server: bool,
 
     /// HTTP server port
+    #[cfg(feature = "http")]
     #[arg(long, default_value = "8000")]
     pub http_port: u16,
 
     /// HTTP server host
+    #[cfg(feature = "http")]
     #[arg(long, default_value = "127.0.0.1")]
     pub http_host: String,
+
+    /// Requests allowed to wait for the TV before the server answers 503
+    #[cfg(feature = "http")]
+    #[arg(long, default_value = "16")]
+    pub http_queue_size: usize,
+
+    /// Per-request deadline in milliseconds, including time spent queued; keep it above
+    /// --warm-up-timeout, or requests queued behind a power-on get 504 while the TV warms up
+    #[cfg(feature = "http")]
+    #[arg(long, default_value = "20000")]
+    pub http_request_timeout: u64,
+
+    /// Refuse POST /raw requests
+    #[cfg(feature = "http")]
+    #[arg(long)]
+    pub http_disable_raw: bool,
 }
 
-fn parse_input(s: &str) -> Result<String, String> {
-    let valid_inputs = [
-        "hdmi1",
-        "hdmi2",
-        "hdmi3",
-        "hdmi4",
-        "hdmi5",
-        "component1",
-        "component2",
-        "component3",
-        "video1",
-        "video2",
-        "video3",
-        "pc1",
-        "shared1",
-    ];
-    if valid_inputs.contains(&s) {
-        Ok(s.to_string())
-    } else {
-        Err(format!(
-            "Invalid input: {}. Valid inputs: {}",
-            s,
-            valid_inputs.join(", ")
-        ))
-    }
+#[derive(Subcommand, Debug)]
+pub enum CliCommand {
+    /// Explain packet bytes from a log or logic analyser, e.g. `decode 8c 00 00 02 01 8f`
+    Decode {
+        /// Hex bytes, separated by spaces, commas or colons, with or without 0x
+        #[arg(required = true, num_args = 1.., allow_hyphen_values = true)]
+        bytes: Vec<String>,
+    },
+}
+
+fn parse_byte(s: &str) -> Result<u8, String> {
+    u8::from_str_radix(s.trim_start_matches("0x"), 16)
+        .map_err(|_| format!("Invalid byte: {}. Expected hex, e.g. 05 or 0x05", s))
+}
+
+fn parse_input(s: &str) -> Result<InputType, String> {
+    s.parse()
+        .map_err(|e: InputNameError| format!("Invalid input: {}. {}", s, e))
 }
 
 fn parse_sircs_button(s: &str) -> Result<String, String> {
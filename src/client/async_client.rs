@@ -0,0 +1,200 @@
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::client::{BraviaClient, ProductInfo};
+use crate::protocol::*;
+use crate::transport::async_io::{AsyncTransport, FramedLink};
+use crate::transport::worker::{DEFAULT_DEADLINE, DEFAULT_QUEUE_SIZE, LinkWorker, WorkerError};
+use crate::transport::{self, BraviaLink, DynTransport, SerialSettings, TransportError};
+
+/// Async client; the link runs on its own thread behind a `LinkWorker`.
+///
+/// Clones share the link, and requests from all of them reach the TV one at
+/// a time in arrival order. A request that waits or runs longer than the
+/// deadline fails with `WorkerError::DeadlineExceeded`.
+pub struct AsyncBraviaClient<L> {
+    worker: LinkWorker<BraviaClient<L>>,
+}
+
+impl<L> Clone for AsyncBraviaClient<L> {
+    fn clone(&self) -> Self {
+        Self {
+            worker: self.worker.clone(),
+        }
+    }
+}
+
+impl AsyncBraviaClient<DynTransport> {
+    pub fn open(dev: &str) -> Result<Self, TransportError> {
+        Self::open_with(dev, &SerialSettings::default())
+    }
+
+    pub fn open_with(dev: &str, settings: &SerialSettings) -> Result<Self, TransportError> {
+        Ok(Self::new(transport::open(dev, settings)?))
+    }
+}
+
+impl AsyncBraviaClient<FramedLink> {
+    /// Talks to the TV over an async stream, such as a `TcpStream` to a
+    /// serial bridge, framed by `BraviaCodec` on the runtime. Must be called
+    /// from within a runtime.
+    pub fn from_stream<S>(stream: S) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        Self::new(AsyncTransport::new(stream).spawn())
+    }
+}
+
+impl<L: BraviaLink + Send + 'static> AsyncBraviaClient<L> {
+    pub fn new(link: L) -> Self {
+        Self::with_limits(link, DEFAULT_QUEUE_SIZE, DEFAULT_DEADLINE)
+    }
+
+    /// `queue_size` requests may wait for the TV before new ones fail with
+    /// `WorkerError::Busy`.
+    pub fn with_limits(link: L, queue_size: usize, deadline: Duration) -> Self {
+        Self {
+            worker: LinkWorker::spawn(BraviaClient::new(link), queue_size, deadline),
+        }
+    }
+
+    /// Runs `f` against the blocking client on the link thread, for
+    /// sequences that must not be interleaved with other requests.
+    pub async fn run<R, F>(&self, f: F) -> Result<R, WorkerError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut BraviaClient<L>) -> Result<R, TransportError> + Send + 'static,
+    {
+        self.worker.run(f).await
+    }
+
+    pub async fn ensure_connected(&self) -> Result<(), WorkerError> {
+        self.run(|client| client.ensure_connected()).await
+    }
+
+    pub async fn execute<C>(&self, action: C::Action) -> Result<(), WorkerError>
+    where
+        C: ControlCommand + 'static,
+        C::Action: Send + 'static,
+    {
+        self.run(move |client| client.execute::<C>(&action)).await
+    }
+
+    pub async fn query<C>(&self) -> Result<C::Response, WorkerError>
+    where
+        C: QueryCommand + 'static,
+        C::Response: Send + 'static,
+    {
+        self.run(|client| client.query::<C>()).await
+    }
+
+    pub async fn power_on(&self) -> Result<(), WorkerError> {
+        self.run(|client| client.power_on()).await
+    }
+
+    pub async fn power_off(&self) -> Result<(), WorkerError> {
+        self.run(|client| client.power_off()).await
+    }
+
+    pub async fn power(&self) -> Result<PowerState, WorkerError> {
+        self.run(|client| client.power()).await
+    }
+
+    pub async fn set_input(&self, input: InputType) -> Result<(), WorkerError> {
+        self.run(move |client| client.set_input(input)).await
+    }
+
+    pub async fn toggle_input(&self) -> Result<(), WorkerError> {
+        self.run(|client| client.toggle_input()).await
+    }
+
+    pub async fn input(&self) -> Result<InputType, WorkerError> {
+        self.run(|client| client.input()).await
+    }
+
+    pub async fn volume(&self) -> Result<u8, WorkerError> {
+        self.run(|client| client.volume()).await
+    }
+
+    pub async fn set_volume(&self, level: u8) -> Result<(), WorkerError> {
+        self.run(move |client| client.set_volume(level)).await
+    }
+
+    pub async fn volume_up(&self) -> Result<(), WorkerError> {
+        self.run(|client| client.volume_up()).await
+    }
+
+    pub async fn volume_down(&self) -> Result<(), WorkerError> {
+        self.run(|client| client.volume_down()).await
+    }
+
+    pub async fn mute(&self) -> Result<(), WorkerError> {
+        self.run(|client| client.mute()).await
+    }
+
+    pub async fn unmute(&self) -> Result<(), WorkerError> {
+        self.run(|client| client.unmute()).await
+    }
+
+    pub async fn toggle_mute(&self) -> Result<(), WorkerError> {
+        self.run(|client| client.toggle_mute()).await
+    }
+
+    pub async fn is_muted(&self) -> Result<bool, WorkerError> {
+        self.run(|client| client.is_muted()).await
+    }
+
+    /// Queries all three parts as one request, so no other request can
+    /// slip in between them.
+    pub async fn product_info(&self) -> Result<ProductInfo, WorkerError> {
+        self.run(|client| client.product_info()).await
+    }
+
+    pub async fn device_id(&self) -> Result<String, WorkerError> {
+        self.run(|client| client.device_id()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+
+    #[tokio::test]
+    async fn test_async_client_shares_link() {
+        let mut link = MockTransport::mock();
+        link.port_mut().push_ack(ResponseCode::Success);
+        link.port_mut().push_data(&[0x01]);
+        let tv = AsyncBraviaClient::new(link);
+        let other = tv.clone();
+        tv.mute().await.unwrap();
+        assert!(other.is_muted().await.unwrap());
+        let sent = tv.run(|client| Ok(client.link().port().sent().len())).await;
+        assert_eq!(sent.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stream_client_survives_rejection() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (stream, mut tv) = tokio::io::duplex(64);
+        let tv_task = tokio::spawn(async move {
+            let mut packet = [0u8; 7];
+            tv.read_exact(&mut packet).await.unwrap();
+            tv.write_all(&[0x70, 0x01, 0x71]).await.unwrap();
+            tv.read_exact(&mut packet[..6]).await.unwrap();
+            tv.write_all(&[0x70, 0x00, 0x02, 0x14, 0x86]).await.unwrap();
+        });
+        let tv = AsyncBraviaClient::from_stream(stream);
+        assert!(matches!(
+            tv.volume_up().await,
+            Err(WorkerError::Transport(TransportError::CommandRejected(
+                ResponseCode::LimitOverMax
+            )))
+        ));
+        assert_eq!(tv.volume().await.unwrap(), 20);
+        tv_task.await.unwrap();
+    }
+}
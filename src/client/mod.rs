@@ -0,0 +1,226 @@
+//! High-level client for controlling a TV from Rust code.
+//!
+//! `BraviaClient` wraps any `BraviaLink` and turns the common commands into
+//! plain method calls. `AsyncBraviaClient` offers the same methods as futures,
+//! running the link on a dedicated thread so a slow TV never blocks the
+//! runtime.
+
+use crate::protocol::*;
+use crate::transport::{self, BraviaLink, DynTransport, SerialSettings, TransportError};
+
+#[cfg(feature = "async")]
+mod async_client;
+
+#[cfg(feature = "async")]
+pub use async_client::AsyncBraviaClient;
+
+/// The three product information strings a TV reports, usually the model
+/// name split across them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProductInfo {
+    pub info1: String,
+    pub info2: String,
+    pub info3: String,
+}
+
+impl ProductInfo {
+    fn from_bytes(info1: &[u8], info2: &[u8], info3: &[u8]) -> Self {
+        Self {
+            info1: String::from_utf8_lossy(info1).to_string(),
+            info2: String::from_utf8_lossy(info2).to_string(),
+            info3: String::from_utf8_lossy(info3).to_string(),
+        }
+    }
+}
+
+/// Blocking client; each method sends one command and waits for the TV.
+///
+/// ```no_run
+/// use sony_bravia_cli::BraviaClient;
+/// use sony_bravia_cli::protocol::InputType;
+///
+/// let mut tv = BraviaClient::open("/dev/ttyUSB0")?;
+/// tv.power_on()?;
+/// tv.set_input(InputType::Hdmi(2))?;
+/// tv.set_volume(30)?;
+/// println!("volume is {}", tv.volume()?);
+/// # Ok::<(), sony_bravia_cli::transport::TransportError>(())
+/// ```
+pub struct BraviaClient<L> {
+    link: L,
+}
+
+impl BraviaClient<DynTransport> {
+    /// Opens a `--dev` style device name with the default 9600 8N1 settings.
+    /// See `transport::open` for the accepted forms.
+    pub fn open(dev: &str) -> Result<Self, TransportError> {
+        Self::open_with(dev, &SerialSettings::default())
+    }
+
+    pub fn open_with(dev: &str, settings: &SerialSettings) -> Result<Self, TransportError> {
+        Ok(Self::new(transport::open(dev, settings)?))
+    }
+}
+
+impl<L: BraviaLink> BraviaClient<L> {
+    /// Wraps a link, which may be a decorated stack such as
+    /// `Retrying<WarmUp<...>>`.
+    pub fn new(link: L) -> Self {
+        Self { link }
+    }
+
+    pub fn link(&self) -> &L {
+        &self.link
+    }
+
+    pub fn link_mut(&mut self) -> &mut L {
+        &mut self.link
+    }
+
+    pub fn into_inner(self) -> L {
+        self.link
+    }
+
+    pub fn ensure_connected(&mut self) -> Result<(), TransportError> {
+        self.link.ensure_connected()
+    }
+
+    /// Sends any control command, for those without a method of their own.
+    pub fn execute<C: ControlCommand>(&mut self, action: &C::Action) -> Result<(), TransportError> {
+        self.link.execute::<C>(action)
+    }
+
+    /// Sends any query command, for those without a method of their own.
+    pub fn query<C: QueryCommand>(&mut self) -> Result<C::Response, TransportError> {
+        self.link.query::<C>()
+    }
+
+    pub fn power_on(&mut self) -> Result<(), TransportError> {
+        self.execute::<Power>(&PowerAction::On)
+    }
+
+    pub fn power_off(&mut self) -> Result<(), TransportError> {
+        self.execute::<Power>(&PowerAction::Off)
+    }
+
+    pub fn power(&mut self) -> Result<PowerState, TransportError> {
+        self.query::<Power>()
+    }
+
+    pub fn set_input(&mut self, input: InputType) -> Result<(), TransportError> {
+        self.execute::<InputSelect>(&input)
+    }
+
+    pub fn toggle_input(&mut self) -> Result<(), TransportError> {
+        self.set_input(InputType::Toggle)
+    }
+
+    pub fn input(&mut self) -> Result<InputType, TransportError> {
+        self.query::<InputSelect>()
+    }
+
+    pub fn volume(&mut self) -> Result<u8, TransportError> {
+        self.query::<Volume>()
+    }
+
+    /// Sets an absolute volume; out-of-range levels are refused before
+    /// anything is sent.
+    pub fn set_volume(&mut self, level: u8) -> Result<(), TransportError> {
+        let level = VolumeValue::new(level)?;
+        self.execute::<Volume>(&VolumeAction::Set(level))
+    }
+
+    pub fn volume_up(&mut self) -> Result<(), TransportError> {
+        self.execute::<Volume>(&VolumeAction::Up)
+    }
+
+    pub fn volume_down(&mut self) -> Result<(), TransportError> {
+        self.execute::<Volume>(&VolumeAction::Down)
+    }
+
+    pub fn mute(&mut self) -> Result<(), TransportError> {
+        self.execute::<Muting>(&MuteAction::Mute)
+    }
+
+    pub fn unmute(&mut self) -> Result<(), TransportError> {
+        self.execute::<Muting>(&MuteAction::Unmute)
+    }
+
+    pub fn toggle_mute(&mut self) -> Result<(), TransportError> {
+        self.execute::<Muting>(&MuteAction::Toggle)
+    }
+
+    pub fn is_muted(&mut self) -> Result<bool, TransportError> {
+        Ok(self.query::<Muting>()? == MuteState::Muted)
+    }
+
+    pub fn product_info(&mut self) -> Result<ProductInfo, TransportError> {
+        let info1 = self.query::<ProductInfo1>()?;
+        let info2 = self.query::<ProductInfo2>()?;
+        let info3 = self.query::<ProductInfo3>()?;
+        Ok(ProductInfo::from_bytes(&info1, &info2, &info3))
+    }
+
+    pub fn device_id(&mut self) -> Result<String, TransportError> {
+        let id = self.query::<IdCommand>()?;
+        Ok(String::from_utf8_lossy(&id).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+
+    #[test]
+    fn test_methods_send_commands() {
+        let mut tv = BraviaClient::new(MockTransport::mock());
+        let port = tv.link_mut().port_mut();
+        port.push_ack(ResponseCode::Success);
+        port.push_ack(ResponseCode::Success);
+        port.push_ack(ResponseCode::Success);
+        port.push_data(&[0x1E]);
+        tv.power_on().unwrap();
+        tv.set_input(InputType::Hdmi(2)).unwrap();
+        tv.set_volume(30).unwrap();
+        assert_eq!(tv.volume().unwrap(), 30);
+        assert_eq!(
+            tv.link().port().sent(),
+            &[
+                build_control_packet(0x00, &[0x01]),
+                build_control_packet(0x02, &[0x04, 0x02]),
+                build_control_packet(0x05, &[0x01, 30]),
+                build_query_packet(0x05),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_volume_is_not_sent() {
+        let mut tv = BraviaClient::new(MockTransport::mock());
+        assert!(matches!(
+            tv.set_volume(101),
+            Err(TransportError::Range(RangeError::AboveMax {
+                max: 100,
+                got: 101
+            }))
+        ));
+        assert!(tv.link().port().sent().is_empty());
+    }
+
+    #[test]
+    fn test_product_info_as_text() {
+        let mut tv = BraviaClient::new(MockTransport::mock());
+        let port = tv.link_mut().port_mut();
+        port.push_data(b"KDL");
+        port.push_data(b"-55");
+        port.push_data(b"W");
+        port.push_data(b"ID-1");
+        let info = tv.product_info().unwrap();
+        assert_eq!(
+            (info.info1, info.info2, info.info3),
+            ("KDL".into(), "-55".into(), "W".into())
+        );
+        assert_eq!(tv.device_id().unwrap(), "ID-1");
+    }
+}
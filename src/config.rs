@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::transport::SerialSettings;
+use crate::transport::serial::{
+    parse_data_bits, parse_flow_control, parse_parity, parse_stop_bits,
+};
+
+/// Settings read from a `--config` TOML file. Command-line flags win over
+/// anything set here.
+///
+/// ```toml
+/// dev = "/dev/ttyUSB0"
+///
+/// [serial]
+/// baud = 9600
+/// data-bits = 8
+/// parity = "none"
+/// stop-bits = 1
+/// flow-control = "none"
+/// dtr = true
+/// rts = true
+/// read-timeout = 500
+/// ```
+#[derive(Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub dev: Option<String>,
+    #[serde(default)]
+    pub serial: SerialConfig,
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct SerialConfig {
+    pub baud: Option<u32>,
+    pub data_bits: Option<u8>,
+    pub parity: Option<String>,
+    pub stop_bits: Option<u8>,
+    pub flow_control: Option<String>,
+    pub dtr: Option<bool>,
+    pub rts: Option<bool>,
+    /// Milliseconds.
+    pub read_timeout: Option<u64>,
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+}
+
+impl SerialConfig {
+    /// Overwrites the fields of `settings` that this file sets.
+    pub fn apply(&self, settings: &mut SerialSettings) -> Result<(), String> {
+        let line = &mut settings.line;
+        if let Some(baud) = self.baud {
+            line.baud_rate = baud;
+        }
+        if let Some(bits) = self.data_bits {
+            line.data_bits = parse_data_bits(&bits.to_string())?;
+        }
+        if let Some(parity) = &self.parity {
+            line.parity = parse_parity(parity)?;
+        }
+        if let Some(bits) = self.stop_bits {
+            line.stop_bits = parse_stop_bits(&bits.to_string())?;
+        }
+        if let Some(flow_control) = &self.flow_control {
+            line.flow_control = parse_flow_control(flow_control)?;
+        }
+        line.dtr = self.dtr.or(line.dtr);
+        line.rts = self.rts.or(line.rts);
+        if let Some(ms) = self.read_timeout {
+            settings.read_timeout = Some(Duration::from_millis(ms));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serialport::{FlowControl, Parity};
+
+    #[test]
+    fn test_parse_and_apply() {
+        let config = Config::parse(
+            r#"
+            dev = "tcp://10.0.0.5:4001"
+
+            [serial]
+            baud = 38400
+            parity = "even"
+            flow-control = "hardware"
+            dtr = false
+            read-timeout = 1500
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.dev.as_deref(), Some("tcp://10.0.0.5:4001"));
+
+        let mut settings = SerialSettings::default();
+        config.serial.apply(&mut settings).unwrap();
+        assert_eq!(settings.line.baud_rate, 38400);
+        assert_eq!(settings.line.parity, Parity::Even);
+        assert_eq!(settings.line.flow_control, FlowControl::Hardware);
+        assert_eq!(settings.line.dtr, Some(false));
+        assert_eq!(settings.line.rts, None);
+        assert_eq!(settings.read_timeout, Some(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn test_rejects_bad_keys_and_values() {
+        assert!(Config::parse("[serial]\nbaudrate = 9600").is_err());
+        let config = Config::parse("[serial]\nstop-bits = 3").unwrap();
+        assert!(config.serial.apply(&mut SerialSettings::default()).is_err());
+    }
+}
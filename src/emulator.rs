@@ -0,0 +1,469 @@
+//! A simulated TV that answers packets the way a Bravia does, for testing
+//! without hardware.
+
+use std::time::{Duration, Instant};
+
+use crate::protocol::*;
+
+/// How long the emulated TV rejects packets after powering on.
+pub const WARM_UP: Duration = Duration::from_secs(3);
+
+pub const PRODUCT_INFO_1: &[u8] = b"KDL-EMU";
+pub const PRODUCT_INFO_2: &[u8] = b"EMULATOR";
+pub const PRODUCT_INFO_3: &[u8] = b"0000001";
+pub const ID: &[u8] = b"EMU-0001";
+
+/// Input kinds and the highest input number of each.
+const INPUTS: [(u8, u8); 5] = [(0x02, 3), (0x03, 3), (0x04, 5), (0x05, 1), (0x07, 1)];
+
+const PICTURE_MODES: [u8; 6] = [0x00, 0x01, 0x02, 0x03, 0x08, 0x09];
+const SOUND_MODES: [u8; 5] = [0x01, 0x04, 0x05, 0x06, 0x07];
+const WIDE_MODES: [u8; 7] = [0x00, 0x01, 0x02, 0x03, 0x05, 0x06, 0x07];
+const FOUR_THREE_MODES: [u8; 3] = [0x00, 0x03, 0x04];
+
+/// Everything the emulated TV remembers between packets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TvState {
+    pub power: bool,
+    /// Input kind and number, as sent in Input Select packets.
+    pub input: (u8, u8),
+    pub volume: u8,
+    pub muted: bool,
+    pub picture_off: bool,
+    pub picture_mode: u8,
+    pub contrast: u8,
+    pub brightness: u8,
+    pub color: u8,
+    /// Red and green hue.
+    pub hue: [u8; 2],
+    pub sharpness: u8,
+    pub wide: u8,
+    pub auto_wide: bool,
+    pub four_three: u8,
+    pub sound_mode: u8,
+    pub speaker_off: bool,
+}
+
+impl Default for TvState {
+    fn default() -> Self {
+        Self {
+            power: true,
+            input: (0x04, 1),
+            volume: 20,
+            muted: false,
+            picture_off: false,
+            picture_mode: 0x01,
+            contrast: 90,
+            brightness: 25,
+            color: 50,
+            hue: [50, 50],
+            sharpness: 50,
+            wide: 0x01,
+            auto_wide: false,
+            four_three: 0x00,
+            sound_mode: 0x01,
+            speaker_off: false,
+        }
+    }
+}
+
+/// The emulated TV: answers each request packet with a response frame and
+/// updates its state.
+///
+/// Packets with a bad checksum or data the command does not accept get
+/// `ParseError`; values out of range get `LimitOverMax` or `LimitOverMin`.
+/// While the TV is off, or warming up after power on, everything except
+/// power packets is `Canceled`, and during warm-up power packets are too.
+#[derive(Debug)]
+pub struct Tv {
+    state: TvState,
+    warm_up: Duration,
+    warming_since: Option<Instant>,
+}
+
+impl Tv {
+    pub fn new(state: TvState, warm_up: Duration) -> Self {
+        Self {
+            state,
+            warm_up,
+            warming_since: None,
+        }
+    }
+
+    pub fn state(&self) -> &TvState {
+        &self.state
+    }
+
+    pub fn is_warming_up(&mut self) -> bool {
+        match self.warming_since {
+            Some(since) if since.elapsed() < self.warm_up => true,
+            _ => {
+                self.warming_since = None;
+                false
+            }
+        }
+    }
+
+    /// The response frame for one request packet, as split by
+    /// `RequestParser`.
+    pub fn respond(&mut self, packet: &[u8]) -> Vec<u8> {
+        if !well_formed(packet) {
+            return ack(ResponseCode::ParseError);
+        }
+        let function = packet[2];
+        if self.is_warming_up() || (!self.state.power && function != Power::FUNCTION_CODE) {
+            return ack(ResponseCode::Canceled);
+        }
+        if packet[0] == QUERY_HEADER {
+            match self.query(function) {
+                Some(data) => data_frame(&data),
+                None => ack(ResponseCode::ParseError),
+            }
+        } else {
+            ack(self.control(function, &packet[4..packet.len() - 1]))
+        }
+    }
+
+    fn query(&self, function: u8) -> Option<Vec<u8>> {
+        let state = &self.state;
+        Some(match function {
+            f if f == Power::FUNCTION_CODE => vec![state.power as u8],
+            f if f == InputSelect::FUNCTION_CODE => vec![state.input.0, state.input.1],
+            f if f == Volume::FUNCTION_CODE => vec![state.volume],
+            f if f == Muting::FUNCTION_CODE => vec![state.muted as u8],
+            f if f == ProductInfo1::FUNCTION_CODE => PRODUCT_INFO_1.to_vec(),
+            f if f == ProductInfo2::FUNCTION_CODE => PRODUCT_INFO_2.to_vec(),
+            f if f == ProductInfo3::FUNCTION_CODE => PRODUCT_INFO_3.to_vec(),
+            f if f == IdCommand::FUNCTION_CODE => ID.to_vec(),
+            _ => return None,
+        })
+    }
+
+    fn control(&mut self, function: u8, data: &[u8]) -> ResponseCode {
+        let state = &mut self.state;
+        match function {
+            f if f == Power::FUNCTION_CODE => match data {
+                [0x00] => {
+                    state.power = false;
+                    ResponseCode::Success
+                }
+                [0x01] => {
+                    if !state.power {
+                        state.power = true;
+                        self.warming_since = Some(Instant::now());
+                    }
+                    ResponseCode::Success
+                }
+                _ => ResponseCode::ParseError,
+            },
+            f if f == Standby::FUNCTION_CODE || f == CineMotion::FUNCTION_CODE => match data {
+                [0x00 | 0x01] => ResponseCode::Success,
+                _ => ResponseCode::ParseError,
+            },
+            f if f == InputSelect::FUNCTION_CODE => select_input(&mut state.input, data),
+            f if f == Volume::FUNCTION_CODE => adjust(&mut state.volume, 100, data),
+            f if f == Muting::FUNCTION_CODE => switch(&mut state.muted, data),
+            f if f == OffTimer::FUNCTION_CODE => match data {
+                [0x00] | [0x01, _] => ResponseCode::Success,
+                _ => ResponseCode::ParseError,
+            },
+            f if f == PictureOff::FUNCTION_CODE => switch(&mut state.picture_off, data),
+            f if f == Display::FUNCTION_CODE => match data {
+                [0x00] => ResponseCode::Success,
+                _ => ResponseCode::ParseError,
+            },
+            f if f == PictureMode::FUNCTION_CODE => {
+                select(&mut state.picture_mode, &PICTURE_MODES, data)
+            }
+            f if f == Contrast::FUNCTION_CODE => adjust(&mut state.contrast, 100, data),
+            f if f == Brightness::FUNCTION_CODE => adjust(&mut state.brightness, 50, data),
+            f if f == Color::FUNCTION_CODE => adjust(&mut state.color, 100, data),
+            f if f == Hue::FUNCTION_CODE => match data {
+                [op, channel @ (0x00 | 0x01), rest @ ..] => {
+                    let mut adjusted = vec![*op];
+                    adjusted.extend_from_slice(rest);
+                    adjust(&mut state.hue[*channel as usize], 100, &adjusted)
+                }
+                _ => ResponseCode::ParseError,
+            },
+            f if f == Sharpness::FUNCTION_CODE => adjust(&mut state.sharpness, 100, data),
+            f if f == HShift::FUNCTION_CODE
+                || f == VSize::FUNCTION_CODE
+                || f == VShift::FUNCTION_CODE =>
+            {
+                match data {
+                    [0x00, 0x00 | 0x01] | [0x01, 0x00 | 0x01, _] => ResponseCode::Success,
+                    _ => ResponseCode::ParseError,
+                }
+            }
+            f if f == Wide::FUNCTION_CODE => select(&mut state.wide, &WIDE_MODES, data),
+            f if f == AutoWide::FUNCTION_CODE => switch(&mut state.auto_wide, data),
+            f if f == FourThreeMode::FUNCTION_CODE => {
+                select(&mut state.four_three, &FOUR_THREE_MODES, data)
+            }
+            f if f == SoundMode::FUNCTION_CODE => select(&mut state.sound_mode, &SOUND_MODES, data),
+            f if f == Speaker::FUNCTION_CODE => switch(&mut state.speaker_off, data),
+            f if f == Language::FUNCTION_CODE => match data {
+                [0x00, _, _, _] => ResponseCode::Success,
+                _ => ResponseCode::ParseError,
+            },
+            f if f == Sircs::FUNCTION_CODE => {
+                let known = SircsButton::ALL
+                    .iter()
+                    .any(|button| Sircs::encode(button) == *data);
+                if known {
+                    ResponseCode::Success
+                } else {
+                    ResponseCode::ParseError
+                }
+            }
+            _ => ResponseCode::ParseError,
+        }
+    }
+}
+
+/// Checks the header, category, length and checksum of a request packet.
+fn well_formed(packet: &[u8]) -> bool {
+    let Some((&sum, body)) = packet.split_last() else {
+        return false;
+    };
+    let shape_ok = match packet[0] {
+        QUERY_HEADER => packet.len() == 6,
+        CONTROL_HEADER => packet.len() >= 5 && packet.len() == 4 + packet[3] as usize,
+        _ => false,
+    };
+    shape_ok && packet[1] == CATEGORY && sum == checksum(body)
+}
+
+/// Up (`00 00`), down (`00 01`) or set (`01 v`) on a value from 0 to `max`.
+fn adjust(value: &mut u8, max: u8, data: &[u8]) -> ResponseCode {
+    match data {
+        [0x00, 0x00] if *value >= max => ResponseCode::LimitOverMax,
+        [0x00, 0x00] => {
+            *value += 1;
+            ResponseCode::Success
+        }
+        [0x00, 0x01] if *value == 0 => ResponseCode::LimitOverMin,
+        [0x00, 0x01] => {
+            *value -= 1;
+            ResponseCode::Success
+        }
+        [0x01, v] if *v > max => ResponseCode::LimitOverMax,
+        [0x01, v] => {
+            *value = *v;
+            ResponseCode::Success
+        }
+        _ => ResponseCode::ParseError,
+    }
+}
+
+/// Toggle (`00`) or set (`01 v`) one of `values`.
+fn select(value: &mut u8, values: &[u8], data: &[u8]) -> ResponseCode {
+    match data {
+        [0x00] => {
+            let next = values.iter().position(|v| v == value).map_or(0, |i| i + 1);
+            *value = values[next % values.len()];
+            ResponseCode::Success
+        }
+        [0x01, v] if values.contains(v) => {
+            *value = *v;
+            ResponseCode::Success
+        }
+        _ => ResponseCode::ParseError,
+    }
+}
+
+/// Toggle (`00`), off (`01 00`) or on (`01 01`).
+fn switch(value: &mut bool, data: &[u8]) -> ResponseCode {
+    match data {
+        [0x00] => *value = !*value,
+        [0x01, 0x00] => *value = false,
+        [0x01, 0x01] => *value = true,
+        _ => return ResponseCode::ParseError,
+    }
+    ResponseCode::Success
+}
+
+/// Toggle (`00`) to the next input, or select kind and number (`kind n`).
+fn select_input(input: &mut (u8, u8), data: &[u8]) -> ResponseCode {
+    match data {
+        [0x00] => {
+            let all: Vec<(u8, u8)> = INPUTS
+                .iter()
+                .flat_map(|&(kind, max)| (1..=max).map(move |n| (kind, n)))
+                .collect();
+            let next = all.iter().position(|i| i == input).map_or(0, |i| i + 1);
+            *input = all[next % all.len()];
+            ResponseCode::Success
+        }
+        [kind, n] => match INPUTS.iter().find(|(k, _)| k == kind) {
+            None => ResponseCode::ParseError,
+            Some(_) if *n == 0 => ResponseCode::LimitOverMin,
+            Some((_, max)) if n > max => ResponseCode::LimitOverMax,
+            Some(_) => {
+                *input = (*kind, *n);
+                ResponseCode::Success
+            }
+        },
+        _ => ResponseCode::ParseError,
+    }
+}
+
+/// Splits a byte stream into request packets.
+#[derive(Debug, Default)]
+pub struct RequestParser {
+    buf: Vec<u8>,
+}
+
+impl RequestParser {
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// The next complete packet, skipping bytes that cannot start one.
+    pub fn next_packet(&mut self) -> Option<Vec<u8>> {
+        loop {
+            let len = match *self.buf.first()? {
+                QUERY_HEADER => 6,
+                CONTROL_HEADER => 4 + *self.buf.get(3)? as usize,
+                _ => {
+                    self.buf.remove(0);
+                    continue;
+                }
+            };
+            if self.buf.len() < len {
+                return None;
+            }
+            return Some(self.buf.drain(..len).collect());
+        }
+    }
+}
+
+fn ack(code: ResponseCode) -> Vec<u8> {
+    let mut frame = vec![RESPONSE_HEADER, code as u8];
+    frame.push(checksum(&frame));
+    frame
+}
+
+fn data_frame(data: &[u8]) -> Vec<u8> {
+    let mut frame = vec![
+        RESPONSE_HEADER,
+        ResponseCode::Success as u8,
+        data.len() as u8 + 1,
+    ];
+    frame.extend_from_slice(data);
+    frame.push(checksum(&frame));
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn control<C: ControlCommand>(tv: &mut Tv, action: &C::Action) -> Vec<u8> {
+        tv.respond(&C::control_packet(action))
+    }
+
+    fn query<C: QueryCommand>(tv: &mut Tv) -> Vec<u8> {
+        tv.respond(&C::query_packet())
+    }
+
+    fn tv() -> Tv {
+        Tv::new(TvState::default(), Duration::ZERO)
+    }
+
+    #[test]
+    fn test_keeps_state_between_packets() {
+        let mut tv = tv();
+        let volume = VolumeValue::new(42).unwrap();
+        assert_eq!(
+            control::<Volume>(&mut tv, &VolumeAction::Set(volume)),
+            ack(ResponseCode::Success)
+        );
+        control::<Volume>(&mut tv, &VolumeAction::Up);
+        assert_eq!(query::<Volume>(&mut tv), data_frame(&[43]));
+        control::<InputSelect>(&mut tv, &InputType::Component(2));
+        assert_eq!(query::<InputSelect>(&mut tv), data_frame(&[0x03, 0x02]));
+        control::<Hue>(&mut tv, &HueAction::Down(HueChannel::Green));
+        assert_eq!(tv.state().hue, [50, 49]);
+        control::<SoundMode>(&mut tv, &SoundModeAction::Cinema);
+        assert_eq!(tv.state().sound_mode, 0x04);
+    }
+
+    #[test]
+    fn test_rejects_out_of_range_and_malformed() {
+        let mut tv = tv();
+        let max = VolumeValue::new(100).unwrap();
+        control::<Volume>(&mut tv, &VolumeAction::Set(max));
+        assert_eq!(
+            control::<Volume>(&mut tv, &VolumeAction::Up),
+            ack(ResponseCode::LimitOverMax)
+        );
+        assert_eq!(
+            tv.respond(&build_control_packet(0x05, &[0x01, 101])),
+            ack(ResponseCode::LimitOverMax)
+        );
+        assert_eq!(
+            tv.respond(&build_control_packet(0x02, &[0x04, 0x00])),
+            ack(ResponseCode::LimitOverMin)
+        );
+        let mut bad_checksum = build_control_packet(0x00, &[0x01]);
+        *bad_checksum.last_mut().unwrap() ^= 0xFF;
+        assert_eq!(tv.respond(&bad_checksum), ack(ResponseCode::ParseError));
+        assert_eq!(
+            tv.respond(&build_control_packet(0x06, &[0x07])),
+            ack(ResponseCode::ParseError)
+        );
+        assert_eq!(
+            tv.respond(&build_query_packet(Contrast::FUNCTION_CODE)),
+            ack(ResponseCode::ParseError)
+        );
+    }
+
+    #[test]
+    fn test_standby_and_warm_up() {
+        let state = TvState {
+            power: false,
+            ..TvState::default()
+        };
+        let mut tv = Tv::new(state, Duration::from_millis(50));
+        assert_eq!(
+            control::<Muting>(&mut tv, &MuteAction::Mute),
+            ack(ResponseCode::Canceled)
+        );
+        assert_eq!(query::<Power>(&mut tv), data_frame(&[0x00]));
+        control::<Power>(&mut tv, &PowerAction::On);
+        assert_eq!(query::<Power>(&mut tv), ack(ResponseCode::Canceled));
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(query::<Power>(&mut tv), data_frame(&[0x01]));
+        assert_eq!(
+            control::<Muting>(&mut tv, &MuteAction::Mute),
+            ack(ResponseCode::Success)
+        );
+    }
+
+    #[test]
+    fn test_input_toggle_cycles() {
+        let mut tv = tv();
+        tv.state.input = (0x07, 1);
+        control::<InputSelect>(&mut tv, &InputType::Toggle);
+        assert_eq!(tv.state().input, (0x02, 1));
+        control::<InputSelect>(&mut tv, &InputType::Toggle);
+        assert_eq!(tv.state().input, (0x02, 2));
+    }
+
+    #[test]
+    fn test_request_parser_skips_garbage() {
+        let mut parser = RequestParser::default();
+        let control = build_control_packet(0x00, &[0x01]);
+        let query = build_query_packet(0x05);
+        parser.push(&[0x00, 0x55]);
+        parser.push(&control[..3]);
+        assert_eq!(parser.next_packet(), None);
+        parser.push(&control[3..]);
+        parser.push(&query);
+        assert_eq!(parser.next_packet(), Some(control));
+        assert_eq!(parser.next_packet(), Some(query));
+        assert_eq!(parser.next_packet(), None);
+    }
+}
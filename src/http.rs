@@ -1,4 +1,4 @@
-use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use axum::{
     Router,
@@ -10,14 +10,56 @@ use axum::{
 use serde::{Deserialize, Serialize};
 use tokio::net::TcpListener;
 
+use sony_bravia_cli::client::{AsyncBraviaClient, ProductInfo};
+
 use crate::protocol::*;
-use crate::transport::Transport;
+use crate::transport::raw::RawCommand;
+use crate::transport::reconnect::Health;
+use crate::transport::worker::WorkerError;
+use crate::transport::{BraviaLink, TransportError};
+
+/// How often the server tries to reopen a lost device while idle.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct AppState<L> {
+    pub client: AsyncBraviaClient<L>,
+    pub health: Health,
+    pub raw_enabled: bool,
+}
 
-pub type SharedTransport = Arc<Mutex<Transport>>;
+impl<L> Clone for AppState<L> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            health: self.health.clone(),
+            raw_enabled: self.raw_enabled,
+        }
+    }
+}
 
-#[derive(Clone)]
-pub struct AppState {
-    pub transport: SharedTransport,
+pub struct ServerOptions {
+    pub host: String,
+    pub port: u16,
+    /// Requests allowed to wait for the TV before new ones get 503.
+    pub queue_size: usize,
+    /// How long a request may wait and run before it gets 504.
+    pub request_timeout: Duration,
+    /// Whether `POST /raw` may send arbitrary packets; 403 otherwise.
+    pub raw_enabled: bool,
+}
+
+fn status(e: WorkerError) -> StatusCode {
+    match e {
+        WorkerError::Busy
+        | WorkerError::Stopped
+        | WorkerError::Transport(TransportError::Disconnected(_)) => {
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+        WorkerError::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        WorkerError::Transport(TransportError::Range(_)) => StatusCode::BAD_REQUEST,
+        WorkerError::Transport(TransportError::DryRun) => StatusCode::NOT_IMPLEMENTED,
+        WorkerError::Aborted | WorkerError::Transport(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -26,6 +68,37 @@ pub struct ApiResponse {
     pub message: String,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct HealthResponse {
+    /// "ok" while the device is open, "degraded" while it is being reopened.
+    pub status: String,
+    pub connected: bool,
+    pub last_error: Option<String>,
+    pub reconnects: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RawKind {
+    Control,
+    Query,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RawRequest {
+    pub kind: RawKind,
+    pub function: u8,
+    #[serde(default)]
+    pub data: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RawResponseBody {
+    /// Response code name, e.g. "Success" or "LimitOverMax".
+    pub code: String,
+    pub data: Vec<u8>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct StatusResponse {
     pub power: String,
@@ -43,8 +116,26 @@ pub struct MuteResponse {
 
 #[derive(Serialize, Deserialize)]
 pub struct InputResponse {
-    pub input_type: u8,
-    pub input_num: u8,
+    pub input: String,
+    /// Raw type code and number, only for inputs without a name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num: Option<u8>,
+}
+
+impl From<InputType> for InputResponse {
+    fn from(input: InputType) -> Self {
+        let (kind, num) = match input {
+            InputType::Unknown { kind, num } => (Some(kind), Some(num)),
+            _ => (None, None),
+        };
+        Self {
+            input: input.to_string(),
+            kind,
+            num,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -59,191 +150,246 @@ pub struct DeviceIdResponse {
     pub id: String,
 }
 
-pub async fn start_http_server(
-    device_path: String,
-    host: String,
-    port: u16,
+/// Serves the API for `transport`. `health` reports whether its device is
+/// open; while it is not, the server keeps trying to reopen it in the
+/// background, so it can start before the device is plugged in.
+pub async fn start_http_server<L: BraviaLink + Send + 'static>(
+    transport: L,
+    health: Health,
+    options: ServerOptions,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let transport = Transport::new(&device_path)?;
-    let shared_transport: SharedTransport = Arc::new(Mutex::new(transport));
-
     let state = AppState {
-        transport: shared_transport,
+        client: AsyncBraviaClient::with_limits(
+            transport,
+            options.queue_size,
+            options.request_timeout,
+        ),
+        health,
+        raw_enabled: options.raw_enabled,
     };
+    tokio::spawn(keep_connected(state.clone()));
+    let app = router(state);
+
+    let addr = format!("{}:{}", options.host, options.port);
+    println!("HTTP server listening on {}", addr);
+
+    let listener = TcpListener::bind(&addr).await?;
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+async fn keep_connected<L: BraviaLink + Send + 'static>(state: AppState<L>) {
+    let mut interval = tokio::time::interval(RECONNECT_INTERVAL);
+    loop {
+        interval.tick().await;
+        if !state.health.status().connected {
+            let _ = state.client.ensure_connected().await;
+        }
+    }
+}
 
-    let app = Router::new()
+pub fn router<L: BraviaLink + Send + 'static>(state: AppState<L>) -> Router {
+    Router::new()
+        .route("/health", get(get_health::<L>))
         // Status routes
-        .route("/status", get(get_status))
-        .route("/power", get(get_power))
-        .route("/volume", get(get_volume))
-        .route("/input", get(get_input))
-        .route("/mute", get(get_mute))
-        .route("/product-info", get(get_product_info))
-        .route("/device-id", get(get_device_id))
+        .route("/status", get(get_status::<L>))
+        .route("/power", get(get_power::<L>))
+        .route("/volume", get(get_volume::<L>))
+        .route("/input", get(get_input::<L>))
+        .route("/mute", get(get_mute::<L>))
+        .route("/product-info", get(get_product_info::<L>))
+        .route("/device-id", get(get_device_id::<L>))
         // Power routes
-        .route("/power/{action}", post(power_control))
-        .route("/standby/{action}", post(standby_control))
+        .route("/power/{action}", post(power_control::<L>))
+        .route("/standby/{action}", post(standby_control::<L>))
         // Input routes
-        .route("/input/{source}", post(input_control))
+        .route("/input/{source}", post(input_control::<L>))
         // Volume routes
-        .route("/volume/{action}", post(volume_control))
-        .route("/volume/set/{value}", post(volume_set))
+        .route("/volume/{action}", post(volume_control::<L>))
+        .route("/volume/set/{value}", post(volume_set::<L>))
         // Mute routes
-        .route("/mute/toggle", post(mute_toggle))
-        .route("/mute/{action}", post(mute_control))
+        .route("/mute/toggle", post(mute_toggle::<L>))
+        .route("/mute/{action}", post(mute_control::<L>))
         // Sleep timer
-        .route("/sleep/{minutes}", post(sleep_control))
-        .route("/sleep/toggle", post(sleep_toggle))
+        .route("/sleep/{minutes}", post(sleep_control::<L>))
+        .route("/sleep/toggle", post(sleep_toggle::<L>))
         // Picture mode
-        .route("/picture/mode/{mode}", post(picture_mode_control))
-        .route("/picture/mode/toggle", post(picture_mode_toggle))
-        .route("/picture/brightness/{action}", post(brightness_control))
-        .route("/picture/brightness/set/{value}", post(brightness_set))
-        .route("/picture/contrast/{action}", post(contrast_control))
-        .route("/picture/contrast/set/{value}", post(contrast_set))
-        .route("/picture/color/{action}", post(color_control))
-        .route("/picture/color/set/{value}", post(color_set))
-        .route("/picture/hue/red/{action}", post(hue_red_control))
-        .route("/picture/hue/red/set/{value}", post(hue_red_set))
-        .route("/picture/hue/green/{action}", post(hue_green_control))
-        .route("/picture/hue/green/set/{value}", post(hue_green_set))
-        .route("/picture/sharpness/{action}", post(sharpness_control))
-        .route("/picture/sharpness/set/{value}", post(sharpness_set))
-        .route("/picture/off", post(picture_off))
-        .route("/picture/on", post(picture_on))
-        .route("/picture/toggle", post(picture_toggle))
-        .route("/picture/cine-motion/{action}", post(cine_motion_control))
+        .route("/picture/mode/{mode}", post(picture_mode_control::<L>))
+        .route("/picture/mode/toggle", post(picture_mode_toggle::<L>))
+        .route(
+            "/picture/brightness/{action}",
+            post(brightness_control::<L>),
+        )
+        .route("/picture/brightness/set/{value}", post(brightness_set::<L>))
+        .route("/picture/contrast/{action}", post(contrast_control::<L>))
+        .route("/picture/contrast/set/{value}", post(contrast_set::<L>))
+        .route("/picture/color/{action}", post(color_control::<L>))
+        .route("/picture/color/set/{value}", post(color_set::<L>))
+        .route("/picture/hue/red/{action}", post(hue_red_control::<L>))
+        .route("/picture/hue/red/set/{value}", post(hue_red_set::<L>))
+        .route("/picture/hue/green/{action}", post(hue_green_control::<L>))
+        .route("/picture/hue/green/set/{value}", post(hue_green_set::<L>))
+        .route("/picture/sharpness/{action}", post(sharpness_control::<L>))
+        .route("/picture/sharpness/set/{value}", post(sharpness_set::<L>))
+        .route("/picture/off", post(picture_off::<L>))
+        .route("/picture/on", post(picture_on::<L>))
+        .route("/picture/toggle", post(picture_toggle::<L>))
+        .route(
+            "/picture/cine-motion/{action}",
+            post(cine_motion_control::<L>),
+        )
         // Input toggle
-        .route("/input/toggle", post(input_toggle))
+        .route("/input/toggle", post(input_toggle::<L>))
         // Display toggle
-        .route("/display/toggle", post(display_toggle))
+        .route("/display/toggle", post(display_toggle::<L>))
         // Language
-        .route("/language/{code}", post(language_control))
+        .route("/language/{code}", post(language_control::<L>))
         // Screen routes
-        .route("/screen/wide/{mode}", post(wide_control))
-        .route("/screen/auto-wide/{action}", post(auto_wide_control))
-        .route("/screen/4-3-mode/{mode}", post(four_three_mode_control))
-        .route("/screen/h-shift/{action}", post(h_shift_control))
-        .route("/screen/h-shift/set/{value}", post(h_shift_set))
-        .route("/screen/v-size/{action}", post(v_size_control))
-        .route("/screen/v-size/set/{value}", post(v_size_set))
-        .route("/screen/v-shift/{action}", post(v_shift_control))
-        .route("/screen/v-shift/set/{value}", post(v_shift_set))
+        .route("/screen/wide/{mode}", post(wide_control::<L>))
+        .route("/screen/auto-wide/{action}", post(auto_wide_control::<L>))
+        .route(
+            "/screen/4-3-mode/{mode}",
+            post(four_three_mode_control::<L>),
+        )
+        .route("/screen/h-shift/{action}", post(h_shift_control::<L>))
+        .route("/screen/h-shift/set/{value}", post(h_shift_set::<L>))
+        .route("/screen/v-size/{action}", post(v_size_control::<L>))
+        .route("/screen/v-size/set/{value}", post(v_size_set::<L>))
+        .route("/screen/v-shift/{action}", post(v_shift_control::<L>))
+        .route("/screen/v-shift/set/{value}", post(v_shift_set::<L>))
         // Sound routes
-        .route("/sound/mode/{mode}", post(sound_mode_control))
-        .route("/sound/speaker/{action}", post(speaker_control))
+        .route("/sound/mode/{mode}", post(sound_mode_control::<L>))
+        .route("/sound/speaker/{action}", post(speaker_control::<L>))
         // SIRCS routes
-        .route("/sircs/{button}", post(sircs_control))
-        .with_state(state);
+        .route("/sircs/{button}", post(sircs_control::<L>))
+        // Raw packets
+        .route("/raw", post(raw_packet::<L>))
+        .with_state(state)
+}
 
-    let addr = format!("{}:{}", host, port);
-    println!("HTTP server listening on {}", addr);
+// === Health ===
 
-    let listener = TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
-
-    Ok(())
+async fn get_health<L: BraviaLink + Send + 'static>(
+    State(app_state): State<AppState<L>>,
+) -> (StatusCode, Json<HealthResponse>) {
+    let status = app_state.health.status();
+    let code = if status.connected {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        code,
+        Json(HealthResponse {
+            status: if status.connected { "ok" } else { "degraded" }.to_string(),
+            connected: status.connected,
+            last_error: status.last_error,
+            reconnects: status.reconnects,
+        }),
+    )
 }
 
 // === Status Routes ===
 
-async fn get_status(State(app_state): State<AppState>) -> Result<Json<StatusResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
-    match transport.query::<Power>() {
+async fn get_status<L: BraviaLink + Send + 'static>(
+    State(app_state): State<AppState<L>>,
+) -> Result<Json<StatusResponse>, StatusCode> {
+    match app_state.client.power().await {
         Ok(state) => Ok(Json(StatusResponse {
             power: match state {
                 PowerState::On => "on".to_string(),
                 PowerState::Off => "off".to_string(),
             },
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn get_power(State(app_state): State<AppState>) -> Result<Json<StatusResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
-    match transport.query::<Power>() {
+async fn get_power<L: BraviaLink + Send + 'static>(
+    State(app_state): State<AppState<L>>,
+) -> Result<Json<StatusResponse>, StatusCode> {
+    match app_state.client.power().await {
         Ok(state) => Ok(Json(StatusResponse {
             power: match state {
                 PowerState::On => "on".to_string(),
                 PowerState::Off => "off".to_string(),
             },
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn get_volume(State(app_state): State<AppState>) -> Result<Json<VolumeResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
-    match transport.query::<Volume>() {
+async fn get_volume<L: BraviaLink + Send + 'static>(
+    State(app_state): State<AppState<L>>,
+) -> Result<Json<VolumeResponse>, StatusCode> {
+    match app_state.client.volume().await {
         Ok(level) => Ok(Json(VolumeResponse { level })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn get_input(State(app_state): State<AppState>) -> Result<Json<InputResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
-    match transport.query::<InputSelect>() {
-        Ok(state) => Ok(Json(InputResponse {
-            input_type: state.input_type,
-            input_num: state.input_num,
-        })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+async fn get_input<L: BraviaLink + Send + 'static>(
+    State(app_state): State<AppState<L>>,
+) -> Result<Json<InputResponse>, StatusCode> {
+    match app_state.client.input().await {
+        Ok(input) => Ok(Json(input.into())),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn get_mute(State(app_state): State<AppState>) -> Result<Json<MuteResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
-    match transport.query::<Muting>() {
-        Ok(state) => Ok(Json(MuteResponse {
-            muted: state == MuteState::Muted,
-        })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+async fn get_mute<L: BraviaLink + Send + 'static>(
+    State(app_state): State<AppState<L>>,
+) -> Result<Json<MuteResponse>, StatusCode> {
+    match app_state.client.is_muted().await {
+        Ok(muted) => Ok(Json(MuteResponse { muted })),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn get_product_info(State(app_state): State<AppState>) -> Result<Json<ProductInfoResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
-    let info1 = transport.query::<ProductInfo1>().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let info2 = transport.query::<ProductInfo2>().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let info3 = transport.query::<ProductInfo3>().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+async fn get_product_info<L: BraviaLink + Send + 'static>(
+    State(app_state): State<AppState<L>>,
+) -> Result<Json<ProductInfoResponse>, StatusCode> {
+    let ProductInfo {
+        info1,
+        info2,
+        info3,
+    } = app_state.client.product_info().await.map_err(status)?;
 
     Ok(Json(ProductInfoResponse {
-        info1: String::from_utf8_lossy(&info1).to_string(),
-        info2: String::from_utf8_lossy(&info2).to_string(),
-        info3: String::from_utf8_lossy(&info3).to_string(),
+        info1,
+        info2,
+        info3,
     }))
 }
 
-async fn get_device_id(State(app_state): State<AppState>) -> Result<Json<DeviceIdResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
-    match transport.query::<IdCommand>() {
-        Ok(id) => Ok(Json(DeviceIdResponse {
-            id: String::from_utf8_lossy(&id).to_string(),
-        })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+async fn get_device_id<L: BraviaLink + Send + 'static>(
+    State(app_state): State<AppState<L>>,
+) -> Result<Json<DeviceIdResponse>, StatusCode> {
+    match app_state.client.device_id().await {
+        Ok(id) => Ok(Json(DeviceIdResponse { id })),
+        Err(e) => Err(status(e)),
     }
 }
 
 // === Power Routes ===
 
-async fn power_control(
+async fn power_control<L: BraviaLink + Send + 'static>(
     Path(action): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let action = match action.as_str() {
         "on" => PowerAction::On,
         "off" => PowerAction::Off,
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<Power>(&action) {
+    match app_state.client.execute::<Power>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!("Power {}", action_str(&action)),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
@@ -254,86 +400,66 @@ fn action_str(action: &PowerAction) -> &str {
     }
 }
 
-async fn standby_control(
+async fn standby_control<L: BraviaLink + Send + 'static>(
     Path(action): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let action = match action.as_str() {
         "enable" => StandbyAction::Enable,
         "disable" => StandbyAction::Disable,
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<Standby>(&action) {
+    match app_state.client.execute::<Standby>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
-            message: format!("Standby {}", if matches!(action, StandbyAction::Enable) { "enabled" } else { "disabled" }),
+            message: format!(
+                "Standby {}",
+                if matches!(action, StandbyAction::Enable) {
+                    "enabled"
+                } else {
+                    "disabled"
+                }
+            ),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
 // === Input Routes ===
 
-async fn input_control(
+async fn input_control<L: BraviaLink + Send + 'static>(
     Path(source): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let input = parse_input_source(&source)?;
 
-    match transport.execute::<InputSelect>(&input) {
+    match app_state.client.set_input(input).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!("Input set to {}", source),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
 fn parse_input_source(source: &str) -> Result<InputType, StatusCode> {
-    if let Some(stripped) = source.strip_prefix("hdmi") {
-        let num = stripped
-            .parse::<u8>()
-            .map_err(|_| StatusCode::BAD_REQUEST)?;
-        Ok(InputType::Hdmi(num))
-    } else if let Some(stripped) = source.strip_prefix("component") {
-        let num = stripped
-            .parse::<u8>()
-            .map_err(|_| StatusCode::BAD_REQUEST)?;
-        Ok(InputType::Component(num))
-    } else if let Some(stripped) = source.strip_prefix("video") {
-        let num = stripped
-            .parse::<u8>()
-            .map_err(|_| StatusCode::BAD_REQUEST)?;
-        Ok(InputType::Video(num))
-    } else if let Some(stripped) = source.strip_prefix("pc") {
-        let num = stripped
-            .parse::<u8>()
-            .map_err(|_| StatusCode::BAD_REQUEST)?;
-        Ok(InputType::Pc(num))
-    } else if source == "shared1" {
-        Ok(InputType::SharedInput(1))
-    } else {
-        Err(StatusCode::BAD_REQUEST)
-    }
+    source.parse().map_err(|_| StatusCode::BAD_REQUEST)
 }
 
 // === Volume Routes ===
 
-async fn volume_control(
+async fn volume_control<L: BraviaLink + Send + 'static>(
     Path(action): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let action = match action.as_str() {
         "up" => VolumeAction::Up,
         "down" => VolumeAction::Down,
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<Volume>(&action) {
+    match app_state.client.execute::<Volume>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!(
@@ -345,51 +471,48 @@ async fn volume_control(
                 }
             ),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn volume_set(
+async fn volume_set<L: BraviaLink + Send + 'static>(
     Path(value): Path<u8>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let vol = VolumeValue::new(value).map_err(|_| StatusCode::BAD_REQUEST)?;
-    let mut transport = app_state.transport.lock().unwrap();
-
-    match transport.execute::<Volume>(&VolumeAction::Set(vol)) {
+    match app_state.client.set_volume(value).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!("Volume set to {}", value),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
 // === Mute Routes ===
 
-async fn mute_toggle(State(app_state): State<AppState>) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
-    match transport.execute::<Muting>(&MuteAction::Toggle) {
+async fn mute_toggle<L: BraviaLink + Send + 'static>(
+    State(app_state): State<AppState<L>>,
+) -> Result<Json<ApiResponse>, StatusCode> {
+    match app_state.client.toggle_mute().await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: "Mute toggled".to_string(),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn mute_control(
+async fn mute_control<L: BraviaLink + Send + 'static>(
     Path(action): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let action = match action.as_str() {
         "on" => MuteAction::Mute,
         "off" => MuteAction::Unmute,
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<Muting>(&action) {
+    match app_state.client.execute::<Muting>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!(
@@ -401,46 +524,53 @@ async fn mute_control(
                 }
             ),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
 // === Sleep Timer Routes ===
 
-async fn sleep_control(
+async fn sleep_control<L: BraviaLink + Send + 'static>(
     Path(minutes): Path<u8>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
     let sleep_mins = SleepMinutes::new(minutes).map_err(|_| StatusCode::BAD_REQUEST)?;
-    let mut transport = app_state.transport.lock().unwrap();
 
-    match transport.execute::<OffTimer>(&SleepAction::Set(sleep_mins)) {
+    match app_state
+        .client
+        .execute::<OffTimer>(SleepAction::Set(sleep_mins))
+        .await
+    {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!("Sleep timer set to {} minutes", minutes),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn sleep_toggle(State(app_state): State<AppState>) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
-    match transport.execute::<OffTimer>(&SleepAction::Toggle) {
+async fn sleep_toggle<L: BraviaLink + Send + 'static>(
+    State(app_state): State<AppState<L>>,
+) -> Result<Json<ApiResponse>, StatusCode> {
+    match app_state
+        .client
+        .execute::<OffTimer>(SleepAction::Toggle)
+        .await
+    {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: "Sleep timer toggled".to_string(),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
 // === Picture Mode Routes ===
 
-async fn picture_mode_control(
+async fn picture_mode_control<L: BraviaLink + Send + 'static>(
     Path(mode): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let mode_action = match mode.as_str() {
         "vivid" => PictureModeAction::Vivid,
         "standard" => PictureModeAction::Standard,
@@ -451,38 +581,42 @@ async fn picture_mode_control(
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<PictureMode>(&mode_action) {
+    match app_state.client.execute::<PictureMode>(mode_action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!("Picture mode set to {}", mode),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn picture_mode_toggle(State(app_state): State<AppState>) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
-    match transport.execute::<PictureMode>(&PictureModeAction::Toggle) {
+async fn picture_mode_toggle<L: BraviaLink + Send + 'static>(
+    State(app_state): State<AppState<L>>,
+) -> Result<Json<ApiResponse>, StatusCode> {
+    match app_state
+        .client
+        .execute::<PictureMode>(PictureModeAction::Toggle)
+        .await
+    {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: "Picture mode toggled".to_string(),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn brightness_control(
+async fn brightness_control<L: BraviaLink + Send + 'static>(
     Path(action): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let action = match action.as_str() {
         "up" => BrightnessAction::Up,
         "down" => BrightnessAction::Down,
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<Brightness>(&action) {
+    match app_state.client.execute::<Brightness>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!(
@@ -494,38 +628,40 @@ async fn brightness_control(
                 }
             ),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn brightness_set(
+async fn brightness_set<L: BraviaLink + Send + 'static>(
     Path(value): Path<u8>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
     let brightness = BrightnessValue::new(value).map_err(|_| StatusCode::BAD_REQUEST)?;
-    let mut transport = app_state.transport.lock().unwrap();
 
-    match transport.execute::<Brightness>(&BrightnessAction::Set(brightness)) {
+    match app_state
+        .client
+        .execute::<Brightness>(BrightnessAction::Set(brightness))
+        .await
+    {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!("Brightness set to {}", value),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn contrast_control(
+async fn contrast_control<L: BraviaLink + Send + 'static>(
     Path(action): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let action = match action.as_str() {
         "up" => ContrastAction::Up,
         "down" => ContrastAction::Down,
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<Contrast>(&action) {
+    match app_state.client.execute::<Contrast>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!(
@@ -537,38 +673,40 @@ async fn contrast_control(
                 }
             ),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn contrast_set(
+async fn contrast_set<L: BraviaLink + Send + 'static>(
     Path(value): Path<u8>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
     let contrast = ContrastValue::new(value).map_err(|_| StatusCode::BAD_REQUEST)?;
-    let mut transport = app_state.transport.lock().unwrap();
 
-    match transport.execute::<Contrast>(&ContrastAction::Set(contrast)) {
+    match app_state
+        .client
+        .execute::<Contrast>(ContrastAction::Set(contrast))
+        .await
+    {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!("Contrast set to {}", value),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn color_control(
+async fn color_control<L: BraviaLink + Send + 'static>(
     Path(action): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let action = match action.as_str() {
         "up" => ColorAction::Up,
         "down" => ColorAction::Down,
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<Color>(&action) {
+    match app_state.client.execute::<Color>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!(
@@ -580,38 +718,40 @@ async fn color_control(
                 }
             ),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn color_set(
+async fn color_set<L: BraviaLink + Send + 'static>(
     Path(value): Path<u8>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
     let color = ColorValue::new(value).map_err(|_| StatusCode::BAD_REQUEST)?;
-    let mut transport = app_state.transport.lock().unwrap();
 
-    match transport.execute::<Color>(&ColorAction::Set(color)) {
+    match app_state
+        .client
+        .execute::<Color>(ColorAction::Set(color))
+        .await
+    {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!("Color set to {}", value),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn sharpness_control(
+async fn sharpness_control<L: BraviaLink + Send + 'static>(
     Path(action): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let action = match action.as_str() {
         "up" => SharpnessAction::Up,
         "down" => SharpnessAction::Down,
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<Sharpness>(&action) {
+    match app_state.client.execute::<Sharpness>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!(
@@ -623,136 +763,171 @@ async fn sharpness_control(
                 }
             ),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn sharpness_set(
+async fn sharpness_set<L: BraviaLink + Send + 'static>(
     Path(value): Path<u8>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
     let sharpness = SharpnessValue::new(value).map_err(|_| StatusCode::BAD_REQUEST)?;
-    let mut transport = app_state.transport.lock().unwrap();
 
-    match transport.execute::<Sharpness>(&SharpnessAction::Set(sharpness)) {
+    match app_state
+        .client
+        .execute::<Sharpness>(SharpnessAction::Set(sharpness))
+        .await
+    {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!("Sharpness set to {}", value),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn hue_red_control(
+async fn hue_red_control<L: BraviaLink + Send + 'static>(
     Path(action): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let action = match action.as_str() {
         "up" => HueAction::Up(HueChannel::Red),
         "down" => HueAction::Down(HueChannel::Red),
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<Hue>(&action) {
+    match app_state.client.execute::<Hue>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
-            message: format!("Hue red {}", if matches!(action, HueAction::Up(_)) { "up" } else { "down" }),
+            message: format!(
+                "Hue red {}",
+                if matches!(action, HueAction::Up(_)) {
+                    "up"
+                } else {
+                    "down"
+                }
+            ),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn hue_red_set(
+async fn hue_red_set<L: BraviaLink + Send + 'static>(
     Path(value): Path<u8>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
     let hue = HueValue::new(value).map_err(|_| StatusCode::BAD_REQUEST)?;
-    let mut transport = app_state.transport.lock().unwrap();
 
-    match transport.execute::<Hue>(&HueAction::Set(HueChannel::Red, hue)) {
+    match app_state
+        .client
+        .execute::<Hue>(HueAction::Set(HueChannel::Red, hue))
+        .await
+    {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!("Hue red set to {}", value),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn hue_green_control(
+async fn hue_green_control<L: BraviaLink + Send + 'static>(
     Path(action): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let action = match action.as_str() {
         "up" => HueAction::Up(HueChannel::Green),
         "down" => HueAction::Down(HueChannel::Green),
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<Hue>(&action) {
+    match app_state.client.execute::<Hue>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
-            message: format!("Hue green {}", if matches!(action, HueAction::Up(_)) { "up" } else { "down" }),
+            message: format!(
+                "Hue green {}",
+                if matches!(action, HueAction::Up(_)) {
+                    "up"
+                } else {
+                    "down"
+                }
+            ),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn hue_green_set(
+async fn hue_green_set<L: BraviaLink + Send + 'static>(
     Path(value): Path<u8>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
     let hue = HueValue::new(value).map_err(|_| StatusCode::BAD_REQUEST)?;
-    let mut transport = app_state.transport.lock().unwrap();
 
-    match transport.execute::<Hue>(&HueAction::Set(HueChannel::Green, hue)) {
+    match app_state
+        .client
+        .execute::<Hue>(HueAction::Set(HueChannel::Green, hue))
+        .await
+    {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!("Hue green set to {}", value),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn picture_off(State(app_state): State<AppState>) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
-    match transport.execute::<PictureOff>(&PictureOffAction::Off) {
+async fn picture_off<L: BraviaLink + Send + 'static>(
+    State(app_state): State<AppState<L>>,
+) -> Result<Json<ApiResponse>, StatusCode> {
+    match app_state
+        .client
+        .execute::<PictureOff>(PictureOffAction::Off)
+        .await
+    {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: "Picture off".to_string(),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn picture_on(State(app_state): State<AppState>) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
-    match transport.execute::<PictureOff>(&PictureOffAction::On) {
+async fn picture_on<L: BraviaLink + Send + 'static>(
+    State(app_state): State<AppState<L>>,
+) -> Result<Json<ApiResponse>, StatusCode> {
+    match app_state
+        .client
+        .execute::<PictureOff>(PictureOffAction::On)
+        .await
+    {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: "Picture on".to_string(),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn picture_toggle(State(app_state): State<AppState>) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
-    match transport.execute::<PictureOff>(&PictureOffAction::Toggle) {
+async fn picture_toggle<L: BraviaLink + Send + 'static>(
+    State(app_state): State<AppState<L>>,
+) -> Result<Json<ApiResponse>, StatusCode> {
+    match app_state
+        .client
+        .execute::<PictureOff>(PictureOffAction::Toggle)
+        .await
+    {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: "Picture toggled".to_string(),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn cine_motion_control(
+async fn cine_motion_control<L: BraviaLink + Send + 'static>(
     Path(action): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let action = match action.as_str() {
         "off" => CineMotionAction::Off,
         "on" | "auto" => CineMotionAction::Auto,
@@ -762,66 +937,73 @@ async fn cine_motion_control(
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<CineMotion>(&action) {
+    match app_state.client.execute::<CineMotion>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
-            message: format!("Cine motion {}", if matches!(action, CineMotionAction::Off) { "off" } else { "on" }),
+            message: format!(
+                "Cine motion {}",
+                if matches!(action, CineMotionAction::Off) {
+                    "off"
+                } else {
+                    "on"
+                }
+            ),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
 // === Input Toggle Route ===
 
-async fn input_toggle(State(app_state): State<AppState>) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
-    match transport.execute::<InputSelect>(&InputType::Toggle) {
+async fn input_toggle<L: BraviaLink + Send + 'static>(
+    State(app_state): State<AppState<L>>,
+) -> Result<Json<ApiResponse>, StatusCode> {
+    match app_state.client.toggle_input().await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: "Input toggled".to_string(),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
 // === Display Toggle Route ===
 
-async fn display_toggle(State(app_state): State<AppState>) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
-    match transport.execute::<Display>(&()) {
+async fn display_toggle<L: BraviaLink + Send + 'static>(
+    State(app_state): State<AppState<L>>,
+) -> Result<Json<ApiResponse>, StatusCode> {
+    match app_state.client.execute::<Display>(()).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: "Display toggled".to_string(),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
 // === Language Route ===
 
-async fn language_control(
+async fn language_control<L: BraviaLink + Send + 'static>(
     Path(code): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
     let language_code = LanguageCode::new(&code).map_err(|_| StatusCode::BAD_REQUEST)?;
-    let mut transport = app_state.transport.lock().unwrap();
 
-    match transport.execute::<Language>(&language_code) {
+    match app_state.client.execute::<Language>(language_code).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!("Language set to {}", code),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
 // === Screen Routes ===
 
-async fn wide_control(
+async fn wide_control<L: BraviaLink + Send + 'static>(
     Path(mode): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let action = match mode.as_str() {
         "toggle" => WideAction::Toggle,
         "widezoom" => WideAction::WideZoom,
@@ -834,20 +1016,19 @@ async fn wide_control(
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<Wide>(&action) {
+    match app_state.client.execute::<Wide>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!("Wide mode set to {}", mode),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn auto_wide_control(
+async fn auto_wide_control<L: BraviaLink + Send + 'static>(
     Path(action): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let action = match action.as_str() {
         "toggle" => AutoWideAction::Toggle,
         "on" => AutoWideAction::On,
@@ -855,20 +1036,19 @@ async fn auto_wide_control(
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<AutoWide>(&action) {
+    match app_state.client.execute::<AutoWide>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: "Auto wide control executed".to_string(),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn four_three_mode_control(
+async fn four_three_mode_control<L: BraviaLink + Send + 'static>(
     Path(mode): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let action = match mode.as_str() {
         "toggle" => FourThreeModeAction::Toggle,
         "off" => FourThreeModeAction::Off,
@@ -877,154 +1057,168 @@ async fn four_three_mode_control(
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<FourThreeMode>(&action) {
+    match app_state.client.execute::<FourThreeMode>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!("4:3 mode set to {}", mode),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn h_shift_control(
+async fn h_shift_control<L: BraviaLink + Send + 'static>(
     Path(action): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let action = match action.as_str() {
         "up" => HShiftAction::Up,
         "down" => HShiftAction::Down,
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<HShift>(&action) {
+    match app_state.client.execute::<HShift>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
-            message: format!("H-shift {}", if matches!(action, HShiftAction::Up) { "up" } else { "down" }),
+            message: format!(
+                "H-shift {}",
+                if matches!(action, HShiftAction::Up) {
+                    "up"
+                } else {
+                    "down"
+                }
+            ),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn h_shift_set(
+async fn h_shift_set<L: BraviaLink + Send + 'static>(
     Path(value): Path<u8>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
     if value > 134 {
         return Err(StatusCode::BAD_REQUEST);
     }
 
-    let mut transport = app_state.transport.lock().unwrap();
     let action = if value >= 67 {
         HShiftAction::SetPlus(value - 67)
     } else {
         HShiftAction::SetMinus(67 - value)
     };
 
-    match transport.execute::<HShift>(&action) {
+    match app_state.client.execute::<HShift>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!("H-shift set to {}", value),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn v_size_control(
+async fn v_size_control<L: BraviaLink + Send + 'static>(
     Path(action): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let action = match action.as_str() {
         "up" => VSizeAction::Up,
         "down" => VSizeAction::Down,
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<VSize>(&action) {
+    match app_state.client.execute::<VSize>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
-            message: format!("V-size {}", if matches!(action, VSizeAction::Up) { "up" } else { "down" }),
+            message: format!(
+                "V-size {}",
+                if matches!(action, VSizeAction::Up) {
+                    "up"
+                } else {
+                    "down"
+                }
+            ),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn v_size_set(
+async fn v_size_set<L: BraviaLink + Send + 'static>(
     Path(value): Path<u8>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
     if value > 99 {
         return Err(StatusCode::BAD_REQUEST);
     }
 
-    let mut transport = app_state.transport.lock().unwrap();
     let action = if value >= 50 {
         VSizeAction::SetPlus(value - 50)
     } else {
         VSizeAction::SetMinus(50 - value)
     };
 
-    match transport.execute::<VSize>(&action) {
+    match app_state.client.execute::<VSize>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!("V-size set to {}", value),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn v_shift_control(
+async fn v_shift_control<L: BraviaLink + Send + 'static>(
     Path(action): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let action = match action.as_str() {
         "up" => VShiftAction::Up,
         "down" => VShiftAction::Down,
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<VShift>(&action) {
+    match app_state.client.execute::<VShift>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
-            message: format!("V-shift {}", if matches!(action, VShiftAction::Up) { "up" } else { "down" }),
+            message: format!(
+                "V-shift {}",
+                if matches!(action, VShiftAction::Up) {
+                    "up"
+                } else {
+                    "down"
+                }
+            ),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn v_shift_set(
+async fn v_shift_set<L: BraviaLink + Send + 'static>(
     Path(value): Path<u8>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
     if value > 99 {
         return Err(StatusCode::BAD_REQUEST);
     }
 
-    let mut transport = app_state.transport.lock().unwrap();
     let action = if value >= 50 {
         VShiftAction::SetPlus(value - 50)
     } else {
         VShiftAction::SetMinus(50 - value)
     };
 
-    match transport.execute::<VShift>(&action) {
+    match app_state.client.execute::<VShift>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!("V-shift set to {}", value),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
 // === Sound Routes ===
 
-async fn sound_mode_control(
+async fn sound_mode_control<L: BraviaLink + Send + 'static>(
     Path(mode): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let action = match mode.as_str() {
         "toggle" => SoundModeAction::Toggle,
         "standard" => SoundModeAction::Standard,
@@ -1035,20 +1229,19 @@ async fn sound_mode_control(
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<SoundMode>(&action) {
+    match app_state.client.execute::<SoundMode>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: format!("Sound mode set to {}", mode),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
-async fn speaker_control(
+async fn speaker_control<L: BraviaLink + Send + 'static>(
     Path(action): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let action = match action.as_str() {
         "toggle" => SpeakerAction::Toggle,
         "on" => SpeakerAction::On,
@@ -1056,22 +1249,21 @@ async fn speaker_control(
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<Speaker>(&action) {
+    match app_state.client.execute::<Speaker>(action).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: "Speaker control executed".to_string(),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
     }
 }
 
 // === SIRCS Routes ===
 
-async fn sircs_control(
+async fn sircs_control<L: BraviaLink + Send + 'static>(
     Path(button): Path<String>,
-    State(app_state): State<AppState>,
+    State(app_state): State<AppState<L>>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
-    let mut transport = app_state.transport.lock().unwrap();
     let button = match button.as_str() {
         "input" => SircsButton::Input,
         "power" => SircsButton::Power,
@@ -1106,11 +1298,248 @@ async fn sircs_control(
         _ => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match transport.execute::<Sircs>(&button) {
+    match app_state.client.execute::<Sircs>(button).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
             message: "SIRCS button pressed".to_string(),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(status(e)),
+    }
+}
+
+// === Raw Packets ===
+
+async fn raw_packet<L: BraviaLink + Send + 'static>(
+    State(app_state): State<AppState<L>>,
+    Json(request): Json<RawRequest>,
+) -> Result<Json<RawResponseBody>, StatusCode> {
+    if !app_state.raw_enabled {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let command = match request.kind {
+        RawKind::Control => RawCommand::control(request.function, request.data)
+            .map_err(|_| StatusCode::BAD_REQUEST)?,
+        RawKind::Query => RawCommand::query(request.function),
+    };
+
+    match app_state
+        .client
+        .run(move |client| command.send(client.link_mut()))
+        .await
+    {
+        Ok(response) => Ok(Json(RawResponseBody {
+            code: format!("{:?}", response.code),
+            data: response.data,
+        })),
+        Err(e) => Err(status(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex, mpsc};
+
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    #[cfg(feature = "testing")]
+    use crate::transport::Transport;
+    #[cfg(feature = "testing")]
+    use crate::transport::fault::{FaultConfig, FaultyPort};
+    use crate::transport::mock::{MockPort, MockTransport};
+
+    type Sent = Arc<Mutex<Vec<Vec<u8>>>>;
+
+    /// Link that holds every exchange until the test lets one through, and
+    /// records the packets that got to it.
+    struct BlockingLink {
+        gate: mpsc::Receiver<()>,
+        sent: Sent,
+    }
+
+    impl BraviaLink for BlockingLink {
+        fn send_control(&mut self, packet: &[u8]) -> Result<(), TransportError> {
+            self.send_query(packet).map(|_| ())
+        }
+
+        fn send_query(&mut self, packet: &[u8]) -> Result<Vec<u8>, TransportError> {
+            self.gate.recv().map_err(|_| TransportError::Timeout)?;
+            self.sent.lock().unwrap().push(packet.to_vec());
+            Ok(vec![0x01])
+        }
+    }
+
+    fn blocking_app(queue_size: usize, deadline: Duration) -> (Router, mpsc::Sender<()>, Sent) {
+        let (gate_tx, gate) = mpsc::channel();
+        let sent = Sent::default();
+        let link = BlockingLink {
+            gate,
+            sent: Arc::clone(&sent),
+        };
+        let state = AppState {
+            client: AsyncBraviaClient::with_limits(link, queue_size, deadline),
+            health: Health::always_connected(),
+            raw_enabled: false,
+        };
+        (router(state), gate_tx, sent)
+    }
+
+    async fn post(app: Router, path: &'static str) -> StatusCode {
+        let request = Request::post(path).body(Body::empty()).unwrap();
+        app.oneshot(request).await.unwrap().status()
+    }
+
+    /// Lets the worker pick up what has been sent to it.
+    async fn settle() {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    fn app_state(reply: impl FnOnce(&mut MockPort)) -> AppState<MockTransport> {
+        let mut transport = MockTransport::mock();
+        reply(transport.port_mut());
+        AppState {
+            client: AsyncBraviaClient::with_limits(transport, 4, Duration::from_secs(1)),
+            health: Health::always_connected(),
+            raw_enabled: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_errors_map_to_status_codes() {
+        let ok = app_state(|port| port.push_data(&[0x01]));
+        assert_eq!(get_power(State(ok)).await.unwrap().power, "on");
+
+        // A reply with a bad checksum.
+        let corrupt = app_state(|port| port.push_raw(&[0x70, 0x00, 0x02, 0x01, 0x00]));
+        assert_eq!(
+            get_power(State(corrupt)).await.err(),
+            Some(StatusCode::INTERNAL_SERVER_ERROR)
+        );
+
+        let rejected = app_state(|port| port.push_ack(ResponseCode::LimitOverMax));
+        assert_eq!(
+            get_power(State(rejected)).await.err(),
+            Some(StatusCode::INTERNAL_SERVER_ERROR)
+        );
+    }
+
+    /// Answers one power query through `FaultyPort`, which damages the reply
+    /// the same way on every run for a given seed.
+    #[cfg(feature = "testing")]
+    fn faulty_app_state(
+        config: FaultConfig,
+        deadline: Duration,
+    ) -> AppState<Transport<FaultyPort<MockPort>>> {
+        let mut port = MockPort::new();
+        port.push_data(&[0x01]);
+        let transport = Transport::from_port(FaultyPort::new(port, config));
+        AppState {
+            client: AsyncBraviaClient::with_limits(transport, 4, deadline),
+            health: Health::always_connected(),
+            raw_enabled: true,
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_injected_faults_map_to_status_codes() {
+        let seeded = FaultConfig {
+            seed: 0x5eed,
+            ..FaultConfig::default()
+        };
+        let second = Duration::from_secs(1);
+
+        let ok = faulty_app_state(seeded, second);
+        assert_eq!(get_power(State(ok)).await.unwrap().power, "on");
+
+        let corrupt = FaultConfig {
+            corrupt_checksum: 1.0,
+            ..seeded
+        };
+        let status = get_power(State(faulty_app_state(corrupt, second))).await;
+        assert_eq!(status.err(), Some(StatusCode::INTERNAL_SERVER_ERROR));
+
+        // The parser skips the damaged header and the reply never completes.
+        let wrong_header = FaultConfig {
+            wrong_header: 1.0,
+            ..seeded
+        };
+        let status = get_power(State(faulty_app_state(wrong_header, second))).await;
+        assert_eq!(status.err(), Some(StatusCode::INTERNAL_SERVER_ERROR));
+
+        let slow = FaultConfig {
+            delay: 1.0,
+            delay_by: Duration::from_millis(200),
+            ..seeded
+        };
+        let status = get_power(State(faulty_app_state(slow, Duration::from_millis(50)))).await;
+        assert_eq!(status.err(), Some(StatusCode::GATEWAY_TIMEOUT));
+    }
+
+    #[test]
+    fn test_unknown_input_keeps_its_codes() {
+        let json = |input: InputType| serde_json::to_string(&InputResponse::from(input)).unwrap();
+        assert_eq!(json(InputType::Hdmi(1)), r#"{"input":"hdmi1"}"#);
+        assert_eq!(
+            json(InputType::Unknown { kind: 0x06, num: 1 }),
+            r#"{"input":"unknown(0x06, 1)","kind":6,"num":1}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_is_503() {
+        let (app, gate, _) = blocking_app(1, Duration::from_secs(5));
+        let running = tokio::spawn(post(app.clone(), "/power/on"));
+        settle().await;
+        let queued = tokio::spawn(post(app.clone(), "/mute/toggle"));
+        settle().await;
+
+        assert_eq!(
+            post(app.clone(), "/display/toggle").await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        gate.send(()).unwrap();
+        gate.send(()).unwrap();
+        assert_eq!(running.await.unwrap(), StatusCode::OK);
+        assert_eq!(queued.await.unwrap(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_worker_deadline_is_504() {
+        let (app, _gate, sent) = blocking_app(4, Duration::from_millis(50));
+        assert_eq!(post(app, "/power/on").await, StatusCode::GATEWAY_TIMEOUT);
+        assert!(sent.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_request_is_not_sent() {
+        let (app, gate, sent) = blocking_app(4, Duration::from_secs(5));
+        let running = tokio::spawn(post(app.clone(), "/power/on"));
+        settle().await;
+        // The client goes away while its request waits behind the first one.
+        let dropped = tokio::spawn(post(app.clone(), "/mute/toggle"));
+        settle().await;
+        dropped.abort();
+        assert!(dropped.await.unwrap_err().is_cancelled());
+
+        gate.send(()).unwrap();
+        assert_eq!(running.await.unwrap(), StatusCode::OK);
+        let next = tokio::spawn(post(app.clone(), "/display/toggle"));
+        gate.send(()).unwrap();
+        assert_eq!(next.await.unwrap(), StatusCode::OK);
+
+        let functions: Vec<u8> = sent
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|packet| packet[2])
+            .collect();
+        assert_eq!(
+            functions,
+            vec![Power::FUNCTION_CODE, Display::FUNCTION_CODE]
+        );
     }
 }
@@ -0,0 +1,17 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "serial")]
+pub mod client;
+#[cfg(feature = "testing")]
+pub mod emulator;
+pub mod protocol;
+#[cfg(feature = "serial")]
+pub mod transport;
+
+#[cfg(feature = "async")]
+pub use client::AsyncBraviaClient;
+#[cfg(feature = "serial")]
+pub use client::BraviaClient;
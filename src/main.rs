@@ -1,134 +1,137 @@
+use std::time::{Duration, Instant};
+
 use clap::Parser;
 
 mod cli;
+mod config;
+#[cfg(feature = "http")]
 mod http;
-mod protocol;
-mod transport;
 
-use cli::Cli;
+use sony_bravia_cli::{BraviaClient, protocol, transport};
+
+use cli::{Cli, CliCommand};
+use config::Config;
 use protocol::*;
-use transport::{Transport, TransportError};
+use transport::discover;
+use transport::dry_run::DryRun;
+use transport::pacing::{Paced, PacingPolicy};
+use transport::raw::{RawCommand, RawResponse};
+use transport::reconnect::{Health, Reconnecting};
+use transport::retry::{RetryConfig, RetryPolicy, Retrying};
+use transport::rfc2217::Rfc2217Transport;
+use transport::trace::{self, TracePort};
+use transport::warmup::{WarmUp, WarmUpPolicy};
+use transport::{BraviaLink, DynTransport, Port, SerialSettings, Transport, TransportError};
+
+fn retry_config(cli: &Cli) -> RetryConfig {
+    let query = RetryPolicy {
+        retries: cli.retries,
+        backoff: Duration::from_millis(cli.retry_backoff),
+        jitter: Duration::from_millis(cli.retry_jitter),
+        canceled_delay: Duration::from_millis(cli.canceled_retry_delay),
+        ..RetryPolicy::default()
+    };
+    let control = RetryPolicy {
+        retries: cli
+            .control_retries
+            .unwrap_or(if cli.retry_controls { cli.retries } else { 0 }),
+        ..query
+    };
+    RetryConfig { control, query }
+}
 
-fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
+fn run_cli<L: BraviaLink>(cli: &Cli, client: &mut BraviaClient<L>) -> Result<(), TransportError> {
     // Power
     if let Some(action) = &cli.power {
-        let action = match action.as_str() {
-            "on" => PowerAction::On,
-            "off" => PowerAction::Off,
+        match action.as_str() {
+            "on" => client.power_on()?,
+            "off" => client.power_off()?,
             _ => unreachable!(),
-        };
-        transport.execute::<Power>(&action)?;
+        }
         println!("Power: {}", cli.power.as_ref().unwrap());
     }
 
     if cli.power_query {
-        let state = transport.query::<Power>()?;
+        let state = client.power()?;
         println!("Power: {:?}", state);
     }
 
     // Input
-    if let Some(input) = &cli.input {
-        let action = match input.as_str() {
-            "toggle" => InputType::Toggle,
-            "hdmi1" => InputType::Hdmi(1),
-            "hdmi2" => InputType::Hdmi(2),
-            "hdmi3" => InputType::Hdmi(3),
-            "hdmi4" => InputType::Hdmi(4),
-            "hdmi5" => InputType::Hdmi(5),
-            "component1" => InputType::Component(1),
-            "component2" => InputType::Component(2),
-            "component3" => InputType::Component(3),
-            "video1" => InputType::Video(1),
-            "video2" => InputType::Video(2),
-            "video3" => InputType::Video(3),
-            "pc1" => InputType::Pc(1),
-            "shared1" => InputType::SharedInput(1),
-            _ => {
-                eprintln!("Invalid input: {}", input);
-                return Ok(());
-            }
-        };
-        transport.execute::<InputSelect>(&action)?;
+    if let Some(input) = cli.input {
+        client.set_input(input)?;
         println!("Input: {}", input);
     }
 
     if cli.input_toggle {
-        transport.execute::<InputSelect>(&InputType::Toggle)?;
+        client.toggle_input()?;
         println!("Input toggled");
     }
 
     if cli.input_query {
-        let state = transport.query::<InputSelect>()?;
-        println!(
-            "Input: type={:#04x} num={}",
-            state.input_type, state.input_num
-        );
+        let input = client.input()?;
+        println!("Input: {}", input);
     }
 
     // Volume
     if let Some(action) = &cli.volume {
-        let action = match action.as_str() {
-            "up" => VolumeAction::Up,
-            "down" => VolumeAction::Down,
+        match action.as_str() {
+            "up" => client.volume_up()?,
+            "down" => client.volume_down()?,
             _ => unreachable!(),
-        };
-        transport.execute::<Volume>(&action)?;
+        }
         println!("Volume: {}", cli.volume.as_ref().unwrap());
     }
 
     if let Some(level) = cli.volume_set {
-        let val = VolumeValue::new(level).map_err(|_| TransportError::QueryNotSupported)?;
-        transport.execute::<Volume>(&VolumeAction::Set(val))?;
+        client.set_volume(level)?;
         println!("Volume set: {}", level);
     }
 
     if cli.volume_query {
-        let level = transport.query::<Volume>()?;
+        let level = client.volume()?;
         println!("Volume: {}", level);
     }
 
     // Mute
     if let Some(action) = &cli.mute {
-        let action = match action.as_str() {
-            "toggle" => MuteAction::Toggle,
-            "on" => MuteAction::Mute,
-            "off" => MuteAction::Unmute,
+        match action.as_str() {
+            "toggle" => client.toggle_mute()?,
+            "on" => client.mute()?,
+            "off" => client.unmute()?,
             _ => unreachable!(),
-        };
-        transport.execute::<Muting>(&action)?;
+        }
         println!("Mute: {}", cli.mute.as_ref().unwrap());
     }
 
     if let Some(action) = &cli.mute_set {
-        let action = match action.as_str() {
-            "on" => MuteAction::Mute,
-            "off" => MuteAction::Unmute,
+        match action.as_str() {
+            "on" => client.mute()?,
+            "off" => client.unmute()?,
             _ => unreachable!(),
-        };
-        transport.execute::<Muting>(&action)?;
+        }
         println!("Mute: {}", cli.mute_set.as_ref().unwrap());
     }
 
     if cli.mute_query {
-        let state = transport.query::<Muting>()?;
+        let state = client.query::<Muting>()?;
         println!("Mute: {:?}", state);
     }
 
     // Sleep
     if let Some(sleep) = cli.sleep {
-        let val = SleepMinutes::new(sleep).map_err(|_| TransportError::QueryNotSupported)?;
-        transport.execute::<OffTimer>(&SleepAction::Set(val))?;
+        let val = SleepMinutes::new(sleep)?;
+        client.execute::<OffTimer>(&SleepAction::Set(val))?;
         println!("Sleep: {}", sleep);
     }
 
     if cli.sleep_toggle {
-        transport.execute::<OffTimer>(&SleepAction::Toggle)?;
+        client.execute::<OffTimer>(&SleepAction::Toggle)?;
         println!("Sleep toggled");
     }
 
     // Display
     if cli.display {
-        transport.execute::<Display>(&())?;
+        client.execute::<Display>(&())?;
         println!("Display toggled");
     }
 
@@ -140,7 +143,7 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
             "off" => PictureOffAction::Off,
             _ => unreachable!(),
         };
-        transport.execute::<PictureOff>(&action)?;
+        client.execute::<PictureOff>(&action)?;
         println!("Picture: {}", cli.picture.as_ref().unwrap());
     }
 
@@ -159,12 +162,12 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
                 return Ok(());
             }
         };
-        transport.execute::<PictureMode>(&action)?;
+        client.execute::<PictureMode>(&action)?;
         println!("Picture mode: {}", mode);
     }
 
     if cli.picture_mode_toggle {
-        transport.execute::<PictureMode>(&PictureModeAction::Toggle)?;
+        client.execute::<PictureMode>(&PictureModeAction::Toggle)?;
         println!("Picture mode toggled");
     }
 
@@ -175,13 +178,13 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
             "down" => BrightnessAction::Down,
             _ => unreachable!(),
         };
-        transport.execute::<Brightness>(&action)?;
+        client.execute::<Brightness>(&action)?;
         println!("Brightness: {}", cli.brightness.as_ref().unwrap());
     }
 
     if let Some(level) = cli.brightness_set {
-        let val = BrightnessValue::new(level).map_err(|_| TransportError::QueryNotSupported)?;
-        transport.execute::<Brightness>(&BrightnessAction::Set(val))?;
+        let val = BrightnessValue::new(level)?;
+        client.execute::<Brightness>(&BrightnessAction::Set(val))?;
         println!("Brightness set: {}", level);
     }
 
@@ -192,13 +195,13 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
             "down" => ContrastAction::Down,
             _ => unreachable!(),
         };
-        transport.execute::<Contrast>(&action)?;
+        client.execute::<Contrast>(&action)?;
         println!("Contrast: {}", cli.contrast.as_ref().unwrap());
     }
 
     if let Some(level) = cli.contrast_set {
-        let val = ContrastValue::new(level).map_err(|_| TransportError::QueryNotSupported)?;
-        transport.execute::<Contrast>(&ContrastAction::Set(val))?;
+        let val = ContrastValue::new(level)?;
+        client.execute::<Contrast>(&ContrastAction::Set(val))?;
         println!("Contrast set: {}", level);
     }
 
@@ -209,13 +212,13 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
             "down" => ColorAction::Down,
             _ => unreachable!(),
         };
-        transport.execute::<Color>(&action)?;
+        client.execute::<Color>(&action)?;
         println!("Color: {}", cli.color.as_ref().unwrap());
     }
 
     if let Some(level) = cli.color_set {
-        let val = ColorValue::new(level).map_err(|_| TransportError::QueryNotSupported)?;
-        transport.execute::<Color>(&ColorAction::Set(val))?;
+        let val = ColorValue::new(level)?;
+        client.execute::<Color>(&ColorAction::Set(val))?;
         println!("Color set: {}", level);
     }
 
@@ -226,13 +229,13 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
             "down" => SharpnessAction::Down,
             _ => unreachable!(),
         };
-        transport.execute::<Sharpness>(&action)?;
+        client.execute::<Sharpness>(&action)?;
         println!("Sharpness: {}", cli.sharpness.as_ref().unwrap());
     }
 
     if let Some(level) = cli.sharpness_set {
-        let val = SharpnessValue::new(level).map_err(|_| TransportError::QueryNotSupported)?;
-        transport.execute::<Sharpness>(&SharpnessAction::Set(val))?;
+        let val = SharpnessValue::new(level)?;
+        client.execute::<Sharpness>(&SharpnessAction::Set(val))?;
         println!("Sharpness set: {}", level);
     }
 
@@ -243,7 +246,7 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
             "auto" => CineMotionAction::Auto,
             _ => unreachable!(),
         };
-        transport.execute::<CineMotion>(&action)?;
+        client.execute::<CineMotion>(&action)?;
         println!("Cine motion: {}", cli.cine_motion.as_ref().unwrap());
     }
 
@@ -263,7 +266,7 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
                 return Ok(());
             }
         };
-        transport.execute::<Wide>(&action)?;
+        client.execute::<Wide>(&action)?;
         println!("Wide: {}", mode);
     }
 
@@ -275,7 +278,7 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
             "off" => AutoWideAction::Off,
             _ => unreachable!(),
         };
-        transport.execute::<AutoWide>(&action)?;
+        client.execute::<AutoWide>(&action)?;
         println!("Auto wide: {}", cli.auto_wide.as_ref().unwrap());
     }
 
@@ -291,7 +294,7 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
                 return Ok(());
             }
         };
-        transport.execute::<FourThreeMode>(&action)?;
+        client.execute::<FourThreeMode>(&action)?;
         println!("4:3 mode: {}", mode);
     }
 
@@ -309,7 +312,7 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
                 return Ok(());
             }
         };
-        transport.execute::<SoundMode>(&action)?;
+        client.execute::<SoundMode>(&action)?;
         println!("Sound mode: {}", mode);
     }
 
@@ -321,7 +324,7 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
             "off" => SpeakerAction::Off,
             _ => unreachable!(),
         };
-        transport.execute::<Speaker>(&action)?;
+        client.execute::<Speaker>(&action)?;
         println!("Speaker: {}", cli.speaker.as_ref().unwrap());
     }
 
@@ -363,14 +366,14 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
                 return Ok(());
             }
         };
-        transport.execute::<Sircs>(&button)?;
+        client.execute::<Sircs>(&button)?;
         println!("SIRCS: {}", cli.sircs.as_ref().unwrap());
     }
 
     // Language
     if let Some(code) = &cli.language {
-        let lang = LanguageCode::new(code).map_err(|_| TransportError::QueryNotSupported)?;
-        transport.execute::<Language>(&lang)?;
+        let lang = LanguageCode::new(code)?;
+        client.execute::<Language>(&lang)?;
         println!("Language: {}", code);
     }
 
@@ -381,7 +384,7 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
             "disable" => StandbyAction::Disable,
             _ => unreachable!(),
         };
-        transport.execute::<Standby>(&action)?;
+        client.execute::<Standby>(&action)?;
         println!("Standby: {}", cli.standby.as_ref().unwrap());
     }
 
@@ -392,13 +395,13 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
             "down" => HueAction::Down(HueChannel::Red),
             _ => unreachable!(),
         };
-        transport.execute::<Hue>(&action)?;
+        client.execute::<Hue>(&action)?;
         println!("Hue red: {}", cli.hue_red.as_ref().unwrap());
     }
 
     if let Some(level) = cli.hue_red_set {
-        let val = HueValue::new(level).map_err(|_| TransportError::QueryNotSupported)?;
-        transport.execute::<Hue>(&HueAction::Set(HueChannel::Red, val))?;
+        let val = HueValue::new(level)?;
+        client.execute::<Hue>(&HueAction::Set(HueChannel::Red, val))?;
         println!("Hue red set: {}", level);
     }
 
@@ -409,13 +412,13 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
             "down" => HueAction::Down(HueChannel::Green),
             _ => unreachable!(),
         };
-        transport.execute::<Hue>(&action)?;
+        client.execute::<Hue>(&action)?;
         println!("Hue green: {}", cli.hue_green.as_ref().unwrap());
     }
 
     if let Some(level) = cli.hue_green_set {
-        let val = HueValue::new(level).map_err(|_| TransportError::QueryNotSupported)?;
-        transport.execute::<Hue>(&HueAction::Set(HueChannel::Green, val))?;
+        let val = HueValue::new(level)?;
+        client.execute::<Hue>(&HueAction::Set(HueChannel::Green, val))?;
         println!("Hue green set: {}", level);
     }
 
@@ -426,7 +429,7 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
             "down" => HShiftAction::Down,
             _ => unreachable!(),
         };
-        transport.execute::<HShift>(&action)?;
+        client.execute::<HShift>(&action)?;
         println!("H shift: {}", cli.h_shift.as_ref().unwrap());
     }
 
@@ -436,7 +439,7 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
         } else {
             HShiftAction::SetPlus(value - 67)
         };
-        transport.execute::<HShift>(&action)?;
+        client.execute::<HShift>(&action)?;
         println!("H shift set: {}", value);
     }
 
@@ -447,7 +450,7 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
             "down" => VSizeAction::Down,
             _ => unreachable!(),
         };
-        transport.execute::<VSize>(&action)?;
+        client.execute::<VSize>(&action)?;
         println!("V size: {}", cli.v_size.as_ref().unwrap());
     }
 
@@ -457,7 +460,7 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
         } else {
             VSizeAction::SetPlus(value - 49)
         };
-        transport.execute::<VSize>(&action)?;
+        client.execute::<VSize>(&action)?;
         println!("V size set: {}", value);
     }
 
@@ -468,7 +471,7 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
             "down" => VShiftAction::Down,
             _ => unreachable!(),
         };
-        transport.execute::<VShift>(&action)?;
+        client.execute::<VShift>(&action)?;
         println!("V shift: {}", cli.v_shift.as_ref().unwrap());
     }
 
@@ -478,22 +481,22 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
         } else {
             VShiftAction::SetPlus(value - 49)
         };
-        transport.execute::<VShift>(&action)?;
+        client.execute::<VShift>(&action)?;
         println!("V shift set: {}", value);
     }
 
     // Product info
     if cli.product_info.is_some() {
         println!("=== Product Info ===");
-        match transport.query::<ProductInfo1>() {
+        match client.query::<ProductInfo1>() {
             Ok(data) => println!("Product Info 1: {:02x?}", data),
             Err(e) => println!("Product Info 1: error ({:?})", e),
         }
-        match transport.query::<ProductInfo2>() {
+        match client.query::<ProductInfo2>() {
             Ok(data) => println!("Product Info 2: {:02x?}", data),
             Err(e) => println!("Product Info 2: error ({:?})", e),
         }
-        match transport.query::<ProductInfo3>() {
+        match client.query::<ProductInfo3>() {
             Ok(data) => println!("Product Info 3: {:02x?}", data),
             Err(e) => println!("Product Info 3: error ({:?})", e),
         }
@@ -501,7 +504,7 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
 
     // Device ID
     if cli.device_id.is_some() {
-        match transport.query::<IdCommand>() {
+        match client.query::<IdCommand>() {
             Ok(data) => println!("Device ID: {:02x?}", data),
             Err(e) => println!("Device ID: error ({:?})", e),
         }
@@ -510,54 +513,403 @@ fn run_cli(cli: &Cli, transport: &mut Transport) -> Result<(), TransportError> {
     // Status
     if cli.status {
         println!("=== Status ===");
-        match transport.query::<Power>() {
+        match client.query::<Power>() {
             Ok(state) => println!("Power: {:?}", state),
             Err(e) => println!("Power: error ({:?})", e),
         }
-        match transport.query::<Volume>() {
+        match client.query::<Volume>() {
             Ok(level) => println!("Volume: {}", level),
             Err(e) => println!("Volume: error ({:?})", e),
         }
-        match transport.query::<Muting>() {
+        match client.query::<Muting>() {
             Ok(state) => println!("Mute: {:?}", state),
             Err(e) => println!("Mute: error ({:?})", e),
         }
-        match transport.query::<InputSelect>() {
-            Ok(state) => println!(
-                "Input: type={:#04x} num={}",
-                state.input_type, state.input_num
-            ),
+        match client.query::<InputSelect>() {
+            Ok(input) => println!("Input: {}", input),
             Err(e) => println!("Input: error ({:?})", e),
         }
     }
 
+    // Raw packets, outside the typed commands
+    if let Some(bytes) = &cli.raw_control {
+        let raw = RawCommand::control(bytes[0], bytes[1..].to_vec())?;
+        print_raw(&raw.send(client.link_mut())?);
+    }
+
+    if let Some(function) = cli.raw_query {
+        print_raw(&RawCommand::query(function).send(client.link_mut())?);
+    }
+
+    Ok(())
+}
+
+fn print_raw(response: &RawResponse) {
+    println!("Response: {:?}", response.code);
+    if !response.data.is_empty() {
+        println!("Data: {:02x?}", response.data);
+    }
+}
+
+type Link = Retrying<WarmUp<Paced<Reconnecting<DynTransport>>>>;
+
+/// Serial settings from the config file, overridden by command-line flags.
+fn serial_settings(cli: &Cli, config: &Config) -> Result<SerialSettings, String> {
+    let mut settings = SerialSettings::default();
+    config.serial.apply(&mut settings)?;
+    let line = &mut settings.line;
+    line.baud_rate = cli.baud.unwrap_or(line.baud_rate);
+    line.data_bits = cli.data_bits.unwrap_or(line.data_bits);
+    line.parity = cli.parity.unwrap_or(line.parity);
+    line.stop_bits = cli.stop_bits.unwrap_or(line.stop_bits);
+    line.flow_control = cli.flow_control.unwrap_or(line.flow_control);
+    line.dtr = cli.dtr.or(line.dtr);
+    line.rts = cli.rts.or(line.rts);
+    if let Some(ms) = cli.read_timeout {
+        settings.read_timeout = Some(Duration::from_millis(ms));
+    }
+    Ok(settings)
+}
+
+/// Opens `dev`, resolving `auto` by discovery. Called again each time the
+/// device has to be reopened.
+fn open_device(
+    dev: &str,
+    settings: &SerialSettings,
+    probe_baud: &[u32],
+    trace: Option<(&str, Instant)>,
+) -> Result<DynTransport, TransportError> {
+    let mut settings = *settings;
+    let dev = if dev == "auto" {
+        let found = discover::auto(probe_baud, &settings)?;
+        eprintln!("Using {} at {} baud", found.port, found.baud_rate);
+        settings.line.baud_rate = found.baud_rate;
+        found.port
+    } else {
+        dev.to_string()
+    };
+    let mut t = if let Some(addr) = dev.strip_prefix("rfc2217://") {
+        let t = Rfc2217Transport::connect(addr, &settings)?;
+        for m in t.port().mismatches() {
+            eprintln!(
+                "Warning: RFC 2217 server set {} to {:02x?} instead of {:02x?}",
+                m.setting, m.got, m.asked
+            );
+        }
+        let port: Box<dyn Port> = Box::new(t.into_port());
+        Transport::from_port(port)
+    } else {
+        transport::open(&dev, &settings)?
+    };
+    if let Some((path, started)) = trace {
+        let port: Box<dyn Port> = Box::new(TracePort::append(t.into_port(), path, started)?);
+        t = Transport::from_port(port);
+    }
+    Ok(t)
+}
+
+fn open_link(
+    cli: &Cli,
+    dev: &str,
+    settings: &SerialSettings,
+) -> Result<(Link, Health), TransportError> {
+    let (dev, settings) = (dev.to_string(), *settings);
+    let probe_baud = cli.probe_baud.clone();
+    // One clock for the whole trace file, however often the device reopens.
+    let trace = cli.trace.clone().map(|path| (path, Instant::now()));
+    let open = move || {
+        let trace = trace
+            .as_ref()
+            .map(|(path, started)| (path.as_str(), *started));
+        open_device(&dev, &settings, &probe_baud, trace)
+    };
+    // The server may start before the device is plugged in; the CLI may not.
+    let reconnecting = if server_mode(cli) {
+        Reconnecting::new(open)
+    } else {
+        Reconnecting::connect(open)?
+    };
+    let health = reconnecting.health();
+
+    let warm_up = WarmUpPolicy {
+        max_wait: Duration::from_millis(cli.warm_up_timeout),
+        poll_interval: Duration::from_millis(cli.warm_up_poll),
+    };
+    let pacing = PacingPolicy {
+        min_gap: Duration::from_millis(cli.min_gap),
+        slow_gap: Duration::from_millis(cli.slow_gap),
+        ..PacingPolicy::default()
+    };
+    let paced = Paced::new(reconnecting, pacing);
+    let link = Retrying::new(WarmUp::new(paced, warm_up), retry_config(cli));
+    Ok((link, health))
+}
+
+fn run_decode(bytes: &[String]) -> Result<(), String> {
+    let bytes = decode::parse_hex(&bytes.join(" "))?;
+    for decoded in decode::decode(&bytes) {
+        println!("{}", decoded);
+    }
+    Ok(())
+}
+
+fn run_replay(path: &str) -> std::io::Result<()> {
+    let file = std::io::BufReader::new(std::fs::File::open(path)?);
+    for replayed in trace::replay(&trace::read_trace(file)?) {
+        let outcome = match replayed.result {
+            Ok(data) if data.is_empty() => "ok".to_string(),
+            Ok(data) => format!("data {}", decode::hex(&data)),
+            Err(e) => format!("error: {}", e),
+        };
+        println!(
+            "{} ({}): {}",
+            decode::hex(&replayed.packet),
+            trace::describe_packet(&replayed.packet),
+            outcome
+        );
+    }
     Ok(())
 }
 
-#[tokio::main]
-async fn main() {
+/// Lists the serial ports where a TV answers. Returns false if none did.
+fn run_discover(cli: &Cli, settings: &SerialSettings) -> bool {
+    let ports = match discover::available_ports() {
+        Ok(ports) => ports,
+        Err(e) => {
+            eprintln!("Failed to list serial ports: {}", e);
+            return false;
+        }
+    };
+    let found = discover::discover(&ports, &cli.probe_baud, settings);
+    if found.is_empty() {
+        println!("No TV answered on {} serial port(s)", ports.len());
+        return false;
+    }
+    for tv in &found {
+        let power = match tv.probe.power {
+            Some(state) => format!("{:?}", state),
+            None => "unknown".to_string(),
+        };
+        println!("{} at {} baud: power {}", tv.port, tv.baud_rate, power);
+        if let Some(info) = &tv.probe.product_info {
+            println!("  Product Info 1: {:02x?}", info);
+        }
+        if let Some(id) = &tv.probe.id {
+            println!("  Device ID: {:02x?}", id);
+        }
+    }
+    true
+}
+
+/// Whether to serve HTTP instead of running the command flags.
+#[cfg(feature = "http")]
+fn server_mode(cli: &Cli) -> bool {
+    cli.http_server
+}
+
+#[cfg(not(feature = "http"))]
+fn server_mode(_cli: &Cli) -> bool {
+    false
+}
+
+#[cfg(feature = "http")]
+fn serve<L: BraviaLink + Send + 'static>(cli: &Cli, transport: L, health: Health) {
+    let options = http::ServerOptions {
+        host: cli.http_host.clone(),
+        port: cli.http_port,
+        queue_size: cli.http_queue_size,
+        request_timeout: Duration::from_millis(cli.http_request_timeout),
+        raw_enabled: !cli.http_disable_raw,
+    };
+    let server = http::start_http_server(transport, health, options);
+    let result = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime.block_on(server),
+        Err(e) => Err(e.into()),
+    };
+    if let Err(e) = result {
+        eprintln!("Server error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn main() {
     let cli = Cli::parse();
 
-    if cli.http_server {
-        if let Err(e) =
-            http::start_http_server(cli.dev.clone(), cli.http_host.clone(), cli.http_port).await
-        {
-            eprintln!("Server error: {}", e);
+    if let Some(CliCommand::Decode { bytes }) = &cli.command {
+        if let Err(e) = run_decode(bytes) {
+            eprintln!("Decode error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(path) = &cli.replay {
+        if let Err(e) = run_replay(path) {
+            eprintln!("Replay error: {}", e);
             std::process::exit(1);
         }
         return;
     }
 
-    let mut transport = match Transport::new(&cli.dev) {
+    let config = match &cli.config {
+        Some(path) => match Config::load(path) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Config error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => Config::default(),
+    };
+    let settings = match serial_settings(&cli, &config) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Config error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if cli.discover {
+        if !run_discover(&cli, &settings) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if cli.dry_run {
+        let sink = DryRun::new(std::io::stdout());
+        #[cfg(feature = "http")]
+        if cli.http_server {
+            serve(&cli, sink, Health::always_connected());
+            return;
+        }
+        if let Err(e) = run_cli(&cli, &mut BraviaClient::new(sink)) {
+            eprintln!("Command error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let Some(dev) = cli.dev.as_deref().or(config.dev.as_deref()) else {
+        eprintln!("No device given: pass --dev or set dev in the config file");
+        std::process::exit(1);
+    };
+    if let Some(path) = &cli.trace
+        && let Err(e) = std::fs::File::create(path)
+    {
+        eprintln!("Failed to create trace file {}: {}", path, e);
+        std::process::exit(1);
+    }
+    #[cfg_attr(not(feature = "http"), allow(unused_variables))]
+    let (transport, health) = match open_link(&cli, dev, &settings) {
         Ok(t) => t,
         Err(e) => {
-            eprintln!("Failed to open serial port: {}", e);
+            eprintln!("Failed to open {}: {}", dev, e);
             std::process::exit(1);
         }
     };
 
-    if let Err(e) = run_cli(&cli, &mut transport) {
+    let mut client = BraviaClient::new(transport);
+    #[cfg(feature = "http")]
+    if cli.http_server {
+        if let Err(e) = client.ensure_connected() {
+            eprintln!("{} not available yet, will keep trying: {}", dev, e);
+        }
+        serve(&cli, client.into_inner(), health);
+        return;
+    }
+
+    if let Err(e) = run_cli(&cli, &mut client) {
         eprintln!("Command error: {}", e);
         std::process::exit(1);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use transport::mock::MockTransport;
+
+    fn parse(args: &[&str]) -> Cli {
+        Cli::parse_from(["sony-bravia", "--dev", "mock"].iter().chain(args))
+    }
+
+    #[test]
+    fn test_run_cli_sends_flags_in_order() {
+        let mut client = BraviaClient::new(MockTransport::mock());
+        client.link_mut().port_mut().push_ack(ResponseCode::Success);
+        client.link_mut().port_mut().push_ack(ResponseCode::Success);
+        run_cli(&parse(&["--power", "on", "--input", "hdmi2"]), &mut client).unwrap();
+        assert_eq!(
+            client.link().port().sent(),
+            &[
+                build_control_packet(0x00, &[0x01]),
+                build_control_packet(0x02, &[0x04, 0x02]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_run_cli_stops_on_rejection() {
+        let mut client = BraviaClient::new(MockTransport::mock());
+        client
+            .link_mut()
+            .port_mut()
+            .push_ack(ResponseCode::Canceled);
+        let result = run_cli(&parse(&["--volume-set", "20", "--mute", "on"]), &mut client);
+        assert!(matches!(
+            result,
+            Err(TransportError::CommandRejected(ResponseCode::Canceled))
+        ));
+        assert_eq!(client.link().port().sent().len(), 1);
+    }
+
+    #[test]
+    fn test_retry_config_from_flags() {
+        let config = retry_config(&parse(&["--retries", "4", "--retry-controls"]));
+        assert_eq!(config.query.retries, 4);
+        assert_eq!(config.control.retries, 4);
+        assert_eq!(config.control.backoff, Duration::from_millis(100));
+
+        let config = retry_config(&parse(&["--retries", "1"]));
+        assert_eq!(config.control.retries, 0);
+
+        let config = retry_config(&parse(&["--retries", "1", "--retry-controls"]));
+        assert_eq!(config.control.retries, 1);
+
+        let config = retry_config(&parse(&["--control-retries", "3"]));
+        assert_eq!(config.control.retries, 3);
+    }
+
+    #[test]
+    fn test_flags_override_config_file() {
+        let config = Config::parse("[serial]\nbaud = 19200\nparity = \"odd\"\nrts = true").unwrap();
+        let settings =
+            serial_settings(&parse(&["--parity", "even", "--dtr", "off"]), &config).unwrap();
+        assert_eq!(settings.line.baud_rate, 19200);
+        assert_eq!(settings.line.parity, serialport::Parity::Even);
+        assert_eq!(settings.line.dtr, Some(false));
+        assert_eq!(settings.line.rts, Some(true));
+    }
+
+    #[test]
+    fn test_run_cli_raw_packets() {
+        let mut client = BraviaClient::new(MockTransport::mock());
+        client
+            .link_mut()
+            .port_mut()
+            .push_ack(ResponseCode::LimitOverMax);
+        client.link_mut().port_mut().push_data(&[0x2A]);
+        run_cli(
+            &parse(&["--raw-control", "0x99", "01", "ff", "--raw-query", "98"]),
+            &mut client,
+        )
+        .unwrap();
+        assert_eq!(
+            client.link().port().sent(),
+            &[
+                build_control_packet(0x99, &[0x01, 0xFF]),
+                build_query_packet(0x98),
+            ]
+        );
+    }
+}
@@ -0,0 +1,586 @@
+// src/protocol/decode.rs
+#![allow(dead_code)]
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::protocol::frame::{Parsed, parse_frame};
+use crate::protocol::*;
+
+/// Formats bytes as `8C 00 00 02 01 8F`, the form traces and decodes use.
+pub fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses hex such as `8c 00 00 02 01 8f`, `8C,00,00`, `0x8c 0x00` or
+/// `8c000002018f`.
+pub fn parse_hex(s: &str) -> Result<Vec<u8>, String> {
+    let mut digits = String::new();
+    for token in s.split(|c: char| c.is_whitespace() || c == ',' || c == ':') {
+        let invalid = || format!("Invalid hex: {}", token);
+        let token = match token
+            .strip_prefix("0x")
+            .or_else(|| token.strip_prefix("0X"))
+        {
+            // Empty tokens come from runs of separators; a bare prefix is a typo.
+            Some("") => return Err(invalid()),
+            Some(rest) => rest,
+            None => token,
+        };
+        if !token.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        if token.len() % 2 == 1 {
+            digits.push('0');
+        }
+        digits.push_str(token);
+    }
+    // Only ASCII hex digits are left, so every pair is on a char boundary.
+    digits
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let pair = core::str::from_utf8(pair).expect("ASCII hex digits");
+            Ok(u8::from_str_radix(pair, 16).expect("two hex digits"))
+        })
+        .collect()
+}
+
+/// One packet or frame found in a byte stream, with what each field means.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decoded {
+    Request {
+        bytes: Vec<u8>,
+        query: bool,
+        function: u8,
+        /// Declared length byte; `None` for queries, which have none.
+        length: Option<u8>,
+        data: Vec<u8>,
+        checksum_ok: bool,
+        /// The `Command` action that builds `data`, if one does.
+        meaning: Option<String>,
+    },
+    Response {
+        bytes: Vec<u8>,
+        code: Result<ResponseCode, u8>,
+        data: Vec<u8>,
+        checksum_ok: bool,
+        /// Data parsed by the `Command` of the query it answers, if known.
+        meaning: Option<String>,
+    },
+    /// Bytes that do not start a packet or frame, or a truncated one.
+    Unknown(Vec<u8>),
+}
+
+fn typed<C: QueryCommand>(data: &[u8]) -> Option<String>
+where
+    C::Response: fmt::Debug,
+{
+    C::parse_response(data).ok().map(|r| format!("{:?}", r))
+}
+
+fn action<C: ControlCommand>(
+    data: &[u8],
+    candidates: impl IntoIterator<Item = C::Action>,
+) -> Option<String>
+where
+    C::Action: fmt::Debug,
+{
+    candidates
+        .into_iter()
+        .find(|action| C::build_bytes(action) == data)
+        .map(|action| format!("{:?}", action))
+}
+
+/// Finds the action whose `build_bytes` produces `data` for `function`.
+pub fn describe_control(function: u8, data: &[u8]) -> Option<String> {
+    // Parameterised actions take their value from the second data byte.
+    let n = data.get(1).copied().unwrap_or_default();
+    match function {
+        f if f == Power::FUNCTION_CODE => {
+            action::<Power>(data, [PowerAction::Off, PowerAction::On])
+        }
+        f if f == Standby::FUNCTION_CODE => {
+            action::<Standby>(data, [StandbyAction::Disable, StandbyAction::Enable])
+        }
+        f if f == InputSelect::FUNCTION_CODE => action::<InputSelect>(
+            data,
+            [
+                InputType::Toggle,
+                InputType::Video(n),
+                InputType::Component(n),
+                InputType::Hdmi(n),
+                InputType::Pc(n),
+                InputType::SharedInput(n),
+            ],
+        ),
+        f if f == Volume::FUNCTION_CODE => action::<Volume>(
+            data,
+            [VolumeAction::Up, VolumeAction::Down]
+                .into_iter()
+                .chain(VolumeValue::new(n).ok().map(VolumeAction::Set)),
+        ),
+        f if f == Muting::FUNCTION_CODE => action::<Muting>(
+            data,
+            [MuteAction::Toggle, MuteAction::Unmute, MuteAction::Mute],
+        ),
+        f if f == OffTimer::FUNCTION_CODE => action::<OffTimer>(
+            data,
+            [SleepAction::Toggle]
+                .into_iter()
+                .chain(SleepMinutes::new(n).ok().map(SleepAction::Set)),
+        ),
+        f if f == PictureOff::FUNCTION_CODE => action::<PictureOff>(
+            data,
+            [
+                PictureOffAction::Toggle,
+                PictureOffAction::Off,
+                PictureOffAction::On,
+            ],
+        ),
+        f if f == PictureMode::FUNCTION_CODE => action::<PictureMode>(
+            data,
+            [
+                PictureModeAction::Toggle,
+                PictureModeAction::Vivid,
+                PictureModeAction::Standard,
+                PictureModeAction::Cinema,
+                PictureModeAction::Custom,
+                PictureModeAction::Game,
+                PictureModeAction::Graphics,
+            ],
+        ),
+        f if f == Contrast::FUNCTION_CODE => action::<Contrast>(
+            data,
+            [ContrastAction::Up, ContrastAction::Down]
+                .into_iter()
+                .chain(ContrastValue::new(n).ok().map(ContrastAction::Set)),
+        ),
+        f if f == Brightness::FUNCTION_CODE => action::<Brightness>(
+            data,
+            [BrightnessAction::Up, BrightnessAction::Down]
+                .into_iter()
+                .chain(BrightnessValue::new(n).ok().map(BrightnessAction::Set)),
+        ),
+        f if f == Color::FUNCTION_CODE => action::<Color>(
+            data,
+            [ColorAction::Up, ColorAction::Down]
+                .into_iter()
+                .chain(ColorValue::new(n).ok().map(ColorAction::Set)),
+        ),
+        f if f == Hue::FUNCTION_CODE => {
+            let value = data.get(2).copied().unwrap_or_default();
+            action::<Hue>(
+                data,
+                [HueChannel::Red, HueChannel::Green]
+                    .into_iter()
+                    .flat_map(|channel| {
+                        [HueAction::Up(channel), HueAction::Down(channel)]
+                            .into_iter()
+                            .chain(
+                                HueValue::new(value)
+                                    .ok()
+                                    .map(|v| HueAction::Set(channel, v)),
+                            )
+                    }),
+            )
+        }
+        f if f == Sharpness::FUNCTION_CODE => action::<Sharpness>(
+            data,
+            [SharpnessAction::Up, SharpnessAction::Down]
+                .into_iter()
+                .chain(SharpnessValue::new(n).ok().map(SharpnessAction::Set)),
+        ),
+        f if f == CineMotion::FUNCTION_CODE => {
+            action::<CineMotion>(data, [CineMotionAction::Off, CineMotionAction::Auto])
+        }
+        f if f == HShift::FUNCTION_CODE => action::<HShift>(
+            data,
+            [
+                HShiftAction::Up,
+                HShiftAction::Down,
+                HShiftAction::SetPlus(n),
+                HShiftAction::SetMinus(n),
+            ],
+        ),
+        f if f == VSize::FUNCTION_CODE => action::<VSize>(
+            data,
+            [
+                VSizeAction::Up,
+                VSizeAction::Down,
+                VSizeAction::SetPlus(n),
+                VSizeAction::SetMinus(n),
+            ],
+        ),
+        f if f == VShift::FUNCTION_CODE => action::<VShift>(
+            data,
+            [
+                VShiftAction::Up,
+                VShiftAction::Down,
+                VShiftAction::SetPlus(n),
+                VShiftAction::SetMinus(n),
+            ],
+        ),
+        f if f == Wide::FUNCTION_CODE => action::<Wide>(
+            data,
+            [
+                WideAction::Toggle,
+                WideAction::WideZoom,
+                WideAction::Full,
+                WideAction::Zoom,
+                WideAction::Normal,
+                WideAction::PcNormal,
+                WideAction::PcFull1,
+                WideAction::PcFull2,
+            ],
+        ),
+        f if f == AutoWide::FUNCTION_CODE => action::<AutoWide>(
+            data,
+            [
+                AutoWideAction::Toggle,
+                AutoWideAction::Off,
+                AutoWideAction::On,
+            ],
+        ),
+        f if f == FourThreeMode::FUNCTION_CODE => action::<FourThreeMode>(
+            data,
+            [
+                FourThreeModeAction::Toggle,
+                FourThreeModeAction::Off,
+                FourThreeModeAction::WideZoom,
+                FourThreeModeAction::Normal,
+            ],
+        ),
+        f if f == SoundMode::FUNCTION_CODE => action::<SoundMode>(
+            data,
+            [
+                SoundModeAction::Toggle,
+                SoundModeAction::Standard,
+                SoundModeAction::Cinema,
+                SoundModeAction::Sports,
+                SoundModeAction::Music,
+                SoundModeAction::Game,
+            ],
+        ),
+        f if f == Speaker::FUNCTION_CODE => action::<Speaker>(
+            data,
+            [SpeakerAction::Toggle, SpeakerAction::On, SpeakerAction::Off],
+        ),
+        f if f == Language::FUNCTION_CODE => action::<Language>(
+            data,
+            data.get(1..)
+                .and_then(|code| core::str::from_utf8(code).ok())
+                .and_then(|code| LanguageCode::new(code).ok()),
+        ),
+        f if f == Sircs::FUNCTION_CODE => action::<Sircs>(data, SircsButton::ALL),
+        _ => None,
+    }
+}
+
+/// Parses `data` as the answer to a query for `function`. Product info
+/// and ID answers are shown as text.
+pub fn describe_response(function: u8, data: &[u8]) -> Option<String> {
+    match function {
+        f if f == Power::FUNCTION_CODE => typed::<Power>(data),
+        f if f == InputSelect::FUNCTION_CODE => typed::<InputSelect>(data),
+        f if f == Volume::FUNCTION_CODE => typed::<Volume>(data),
+        f if f == Muting::FUNCTION_CODE => typed::<Muting>(data),
+        f if f == ProductInfo1::FUNCTION_CODE
+            || f == ProductInfo2::FUNCTION_CODE
+            || f == ProductInfo3::FUNCTION_CODE
+            || f == IdCommand::FUNCTION_CODE =>
+        {
+            Some(format!("{:?}", String::from_utf8_lossy(data)))
+        }
+        _ => None,
+    }
+}
+
+/// Splits `bytes` into request packets and response frames and explains
+/// each one.
+///
+/// A response's layout depends on the request it answers, so a frame after
+/// a query is read as a data frame and one after a control packet as an
+/// ack. With no request before it, whichever layout has a valid checksum
+/// is used.
+pub fn decode(bytes: &[u8]) -> Vec<Decoded> {
+    let mut out = Vec::new();
+    let mut last_query: Option<Option<u8>> = None;
+    let mut rest = bytes;
+    let mut unknown = Vec::new();
+
+    while let Some(&first) = rest.first() {
+        let used = match first {
+            CONTROL_HEADER | QUERY_HEADER => decode_request(rest).map(|(decoded, used)| {
+                if let Decoded::Request {
+                    query, function, ..
+                } = &decoded
+                {
+                    last_query = Some(query.then_some(*function));
+                }
+                out_push(&mut out, &mut unknown, decoded);
+                used
+            }),
+            RESPONSE_HEADER => decode_response(rest, last_query).map(|(decoded, used)| {
+                out_push(&mut out, &mut unknown, decoded);
+                used
+            }),
+            _ => None,
+        };
+        match used {
+            Some(used) => rest = &rest[used..],
+            None => {
+                unknown.push(first);
+                rest = &rest[1..];
+            }
+        }
+    }
+    if !unknown.is_empty() {
+        out.push(Decoded::Unknown(unknown));
+    }
+    out
+}
+
+fn out_push(out: &mut Vec<Decoded>, unknown: &mut Vec<u8>, decoded: Decoded) {
+    if !unknown.is_empty() {
+        out.push(Decoded::Unknown(core::mem::take(unknown)));
+    }
+    out.push(decoded);
+}
+
+fn decode_request(bytes: &[u8]) -> Option<(Decoded, usize)> {
+    let query = bytes[0] == QUERY_HEADER;
+    let (total, length, data) = if query {
+        (6, None, Vec::new())
+    } else {
+        let length = *bytes.get(3)?;
+        let total = 4 + length as usize;
+        (total, Some(length), bytes.get(4..total - 1)?.to_vec())
+    };
+    let packet = bytes.get(..total)?;
+    let meaning = if query {
+        None
+    } else {
+        describe_control(packet[2], &data)
+    };
+    Some((
+        Decoded::Request {
+            bytes: packet.to_vec(),
+            query,
+            function: packet[2],
+            length,
+            data,
+            checksum_ok: packet[total - 1] == checksum(&packet[..total - 1]),
+            meaning,
+        },
+        total,
+    ))
+}
+
+fn decode_response(bytes: &[u8], last_query: Option<Option<u8>>) -> Option<(Decoded, usize)> {
+    let kind = match last_query {
+        Some(Some(_)) => ResponseKind::Data,
+        Some(None) => ResponseKind::Ack,
+        None => match parse_frame(bytes, ResponseKind::Data) {
+            Parsed::Frame(..) => ResponseKind::Data,
+            _ => ResponseKind::Ack,
+        },
+    };
+    let (code, data, used, checksum_ok) = match parse_frame(bytes, kind) {
+        Parsed::Frame(frame, used) => (Ok(frame.code), frame.data.to_vec(), used, true),
+        Parsed::Error(FrameError::InvalidChecksum, used) => {
+            let data = match kind {
+                ResponseKind::Data if used > 4 => bytes[3..used - 1].to_vec(),
+                _ => Vec::new(),
+            };
+            let code = ResponseCode::try_from(bytes[1]).map_err(|_| bytes[1]);
+            (code, data, used, false)
+        }
+        Parsed::Error(FrameError::InvalidResponseCode(code), _) if bytes.len() >= 3 => {
+            (Err(code), Vec::new(), 3, bytes[2] == checksum(&bytes[..2]))
+        }
+        _ => return None,
+    };
+    let meaning = match (last_query, &code) {
+        (Some(Some(function)), Ok(ResponseCode::Success)) => describe_response(function, &data),
+        _ => None,
+    };
+    Some((
+        Decoded::Response {
+            bytes: bytes[..used].to_vec(),
+            code,
+            data,
+            checksum_ok,
+            meaning,
+        },
+        used,
+    ))
+}
+
+fn with_meaning(data: &[u8], meaning: &Option<String>) -> String {
+    match meaning {
+        Some(meaning) => format!("{} = {}", hex(data), meaning),
+        None => hex(data),
+    }
+}
+
+fn checksum_line(bytes: &[u8], ok: bool) -> String {
+    let last = bytes.last().copied().unwrap_or_default();
+    if ok {
+        format!("{:#04x} ok", last)
+    } else {
+        let expected = checksum(&bytes[..bytes.len().saturating_sub(1)]);
+        format!("{:#04x} BAD, expected {:#04x}", last, expected)
+    }
+}
+
+impl fmt::Display for Decoded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Decoded::Request {
+                bytes,
+                query,
+                function,
+                length,
+                data,
+                checksum_ok,
+                meaning,
+            } => {
+                writeln!(f, "{}", hex(bytes))?;
+                let kind = if *query { "query" } else { "control" };
+                writeln!(f, "  header:   {:#04x} {}", bytes[0], kind)?;
+                writeln!(f, "  category: {:#04x}", bytes[1])?;
+                let name = function_name(*function).unwrap_or("unknown");
+                writeln!(f, "  function: {:#04x} {}", function, name)?;
+                match length {
+                    Some(length) => {
+                        writeln!(f, "  length:   {} (data + checksum)", length)?;
+                        writeln!(f, "  data:     {}", with_meaning(data, meaning))?;
+                    }
+                    None => writeln!(f, "  padding:  {}", hex(&bytes[3..5]))?,
+                }
+                write!(f, "  checksum: {}", checksum_line(bytes, *checksum_ok))
+            }
+            Decoded::Response {
+                bytes,
+                code,
+                data,
+                checksum_ok,
+                meaning,
+            } => {
+                writeln!(f, "{}", hex(bytes))?;
+                writeln!(f, "  header:   {:#04x} response", bytes[0])?;
+                match code {
+                    Ok(code) => writeln!(f, "  code:     {:#04x} {:?}", *code as u8, code)?,
+                    Err(code) => writeln!(f, "  code:     {:#04x} invalid", code)?,
+                }
+                if bytes.len() > 3 {
+                    writeln!(f, "  length:   {} (data + checksum)", bytes[2])?;
+                    writeln!(f, "  data:     {}", with_meaning(data, meaning))?;
+                }
+                write!(f, "  checksum: {}", checksum_line(bytes, *checksum_ok))
+            }
+            Decoded::Unknown(bytes) => write!(f, "{}\n  not a packet or frame", hex(bytes)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hex() {
+        let expected = vec![0x8C, 0x00, 0x00, 0x02, 0x01, 0x8F];
+        assert_eq!(parse_hex("8c 00 00 02 01 8f").unwrap(), expected);
+        assert_eq!(
+            parse_hex("0x8C,0x00,0x00,0x02,0x01,0x8F").unwrap(),
+            expected
+        );
+        assert_eq!(parse_hex("8c000002018f").unwrap(), expected);
+        assert!(parse_hex("8g").is_err());
+        assert_eq!(parse_hex("ab€"), Err("Invalid hex: ab€".to_string()));
+        assert!(parse_hex("é").is_err());
+        assert_eq!(parse_hex("0x"), Err("Invalid hex: 0x".to_string()));
+        assert!(parse_hex("8c 0X 00").is_err());
+        assert_eq!(parse_hex("8c, 00").unwrap(), [0x8C, 0x00]);
+    }
+
+    #[test]
+    fn test_decode_query_and_answer() {
+        let bytes = parse_hex("83 00 00 ff ff 81 70 00 02 01 73").unwrap();
+        let decoded = decode(&bytes);
+        assert_eq!(decoded.len(), 2);
+        assert!(matches!(
+            decoded[0],
+            Decoded::Request {
+                query: true,
+                function: 0x00,
+                checksum_ok: true,
+                ..
+            }
+        ));
+        let Decoded::Response { meaning, .. } = &decoded[1] else {
+            panic!("expected a response");
+        };
+        assert_eq!(meaning.as_deref(), Some("On"));
+        let text = decoded[1].to_string();
+        assert!(text.contains("data:     01 = On"), "{}", text);
+    }
+
+    #[test]
+    fn test_decode_control_with_bad_checksum() {
+        let decoded = decode(&parse_hex("8c 00 05 03 01 14 00 70 03 73").unwrap());
+        assert_eq!(decoded.len(), 2);
+        let text = decoded[0].to_string();
+        assert!(text.contains("function: 0x05 Volume"), "{}", text);
+        assert!(
+            text.contains("data:     01 14 = Set(VolumeValue(20))"),
+            "{}",
+            text
+        );
+        assert!(text.contains("BAD, expected 0xa9"), "{}", text);
+        assert!(matches!(
+            decoded[1],
+            Decoded::Response {
+                code: Ok(ResponseCode::Canceled),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_describe_control_matches_build_bytes() {
+        assert_eq!(describe_control(0x00, &[0x01]).as_deref(), Some("On"));
+        assert_eq!(
+            describe_control(0x02, &[0x04, 0x02]).as_deref(),
+            Some("Hdmi(2)")
+        );
+        assert_eq!(
+            describe_control(0x67, &[0x01, 0x60]).as_deref(),
+            Some("Home")
+        );
+        assert_eq!(describe_control(0x05, &[0x01, 0xC8]), None);
+        assert_eq!(describe_control(0x00, &[0x07]), None);
+    }
+
+    #[test]
+    fn test_decode_lone_response_and_garbage() {
+        let decoded = decode(&parse_hex("55 70 00 70").unwrap());
+        assert_eq!(decoded[0], Decoded::Unknown(vec![0x55]));
+        assert!(matches!(
+            decoded[1],
+            Decoded::Response {
+                code: Ok(ResponseCode::Success),
+                checksum_ok: true,
+                ..
+            }
+        ));
+    }
+}
@@ -26,12 +26,16 @@ impl TryFrom<u8> for ResponseCode {
     }
 }
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
 pub enum ProtocolError {
     #[error("invalid response code: {0:#04x}")]
     InvalidResponseCode(u8),
     #[error("invalid response data")]
     InvalidResponse,
+    #[error("{0} data bytes do not fit in a packet")]
+    DataTooLong(usize),
+    #[error("{needed} bytes do not fit in a buffer of {capacity}")]
+    BufferTooSmall { needed: usize, capacity: usize },
 }
 
 #[derive(Debug, Error)]
@@ -41,3 +45,7 @@ pub enum RangeError {
     #[error("value {got} below minimum {min}")]
     BelowMin { min: u8, got: u8 },
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("valid inputs: toggle, hdmi1-5, component1-3, video1-3, pc1, shared1")]
+pub struct InputNameError;
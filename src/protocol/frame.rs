@@ -0,0 +1,343 @@
+// src/protocol/frame.rs
+#![allow(dead_code)]
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use thiserror::Error;
+
+use crate::protocol::{RESPONSE_HEADER, ResponseCode, ResponseData, checksum};
+
+/// Non-header bytes tolerated in front of a response before giving up.
+pub const MAX_GARBAGE: usize = 64;
+
+/// Which 0x70 frame the TV sends for the packet that was written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseKind {
+    /// Header, response code, checksum.
+    Ack,
+    /// Header, response code, length, data, checksum. Rejections still use
+    /// the 3-byte form.
+    Data,
+}
+
+impl ResponseKind {
+    /// The response a request packet asks for, judged by its header byte.
+    pub fn for_packet(packet: &[u8]) -> Self {
+        if packet.first() == Some(&crate::protocol::QUERY_HEADER) {
+            ResponseKind::Data
+        } else {
+            ResponseKind::Ack
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseFrame {
+    pub code: ResponseCode,
+    /// Data bytes without the checksum; empty for an ack.
+    pub data: ResponseData,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum FrameError {
+    #[error("no response header after {MAX_GARBAGE} bytes, first was {0:#04x}")]
+    UnexpectedHeader(u8),
+    #[error("invalid response code: {0:#04x}")]
+    InvalidResponseCode(u8),
+    #[error("invalid response length: {0}")]
+    InvalidLength(u8),
+    #[error("invalid checksum in response")]
+    InvalidChecksum,
+}
+
+// Frames stay inline so parsing works without a heap.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, PartialEq, Eq)]
+pub enum Parsed {
+    /// More bytes are needed.
+    Incomplete,
+    /// A frame and the number of bytes it used.
+    Frame(ResponseFrame, usize),
+    /// A malformed frame and the number of bytes to drop.
+    Error(FrameError, usize),
+}
+
+/// Parses one frame from the start of `buf`, which must begin with
+/// `RESPONSE_HEADER`.
+pub fn parse_frame(buf: &[u8], kind: ResponseKind) -> Parsed {
+    debug_assert_eq!(buf.first(), Some(&RESPONSE_HEADER));
+    if buf.len() < 2 {
+        return Parsed::Incomplete;
+    }
+    let code = match ResponseCode::try_from(buf[1]) {
+        Ok(code) => code,
+        // Probably not a real header; drop it and hunt again from the next byte.
+        Err(_) => return Parsed::Error(FrameError::InvalidResponseCode(buf[1]), 1),
+    };
+    if buf.len() < 3 {
+        return Parsed::Incomplete;
+    }
+
+    if kind == ResponseKind::Ack || code != ResponseCode::Success {
+        if buf[2] != checksum(&buf[..2]) {
+            return Parsed::Error(FrameError::InvalidChecksum, 3);
+        }
+        let frame = ResponseFrame {
+            code,
+            data: ResponseData::new(),
+        };
+        return Parsed::Frame(frame, 3);
+    }
+
+    // Length covers the data bytes plus the trailing checksum, so zero can
+    // never be valid.
+    let length = buf[2] as usize;
+    if length == 0 {
+        return Parsed::Error(FrameError::InvalidLength(buf[2]), 3);
+    }
+    let total = 3 + length;
+    if buf.len() < total {
+        return Parsed::Incomplete;
+    }
+    if buf[total - 1] != checksum(&buf[..total - 1]) {
+        return Parsed::Error(FrameError::InvalidChecksum, total);
+    }
+    // The length byte cannot describe more data than `ResponseData` holds.
+    let frame = ResponseFrame {
+        code,
+        data: ResponseData::from_slice(&buf[3..total - 1]).expect("data fits in MAX_DATA_LEN"),
+    };
+    Parsed::Frame(frame, total)
+}
+
+/// Incremental response parser fed with whatever the port returns.
+///
+/// Bytes in front of a `RESPONSE_HEADER` are skipped, so stray bytes on the
+/// line do not shift every later frame. Malformed frames are reported and
+/// dropped, and parsing resumes after them.
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct FrameParser {
+    buf: Vec<u8>,
+    kind: ResponseKind,
+    discarded: usize,
+    first_discarded: Option<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl Default for FrameParser {
+    fn default() -> Self {
+        Self::new(ResponseKind::Ack)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl FrameParser {
+    pub fn new(kind: ResponseKind) -> Self {
+        Self {
+            buf: Vec::new(),
+            kind,
+            discarded: 0,
+            first_discarded: None,
+        }
+    }
+
+    /// Forgets buffered bytes and expects a new response of `kind`.
+    pub fn reset(&mut self, kind: ResponseKind) {
+        self.buf.clear();
+        self.kind = kind;
+        self.discarded = 0;
+        self.first_discarded = None;
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Bytes skipped while hunting for a header since the last frame or reset.
+    pub fn discarded(&self) -> usize {
+        self.discarded
+    }
+
+    /// Bytes buffered but not yet parsed.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn next_frame(&mut self) -> Option<Result<ResponseFrame, FrameError>> {
+        // Never skip past the garbage limit in one go, so the result does not
+        // depend on how the input was split into reads.
+        let skip = self
+            .buf
+            .iter()
+            .position(|&b| b == RESPONSE_HEADER)
+            .unwrap_or(self.buf.len())
+            .min(MAX_GARBAGE + 1 - self.discarded);
+        if skip > 0 {
+            self.first_discarded.get_or_insert(self.buf[0]);
+            self.discarded += skip;
+            self.buf.drain(..skip);
+        }
+        if self.discarded > MAX_GARBAGE {
+            let first = self.first_discarded.unwrap_or_default();
+            self.discarded = 0;
+            self.first_discarded = None;
+            return Some(Err(FrameError::UnexpectedHeader(first)));
+        }
+        if self.buf.is_empty() {
+            return None;
+        }
+
+        match parse_frame(&self.buf, self.kind) {
+            Parsed::Incomplete => None,
+            Parsed::Frame(frame, used) => {
+                self.buf.drain(..used);
+                // The garbage limit is for bytes in a row, not in a stream.
+                self.discarded = 0;
+                self.first_discarded = None;
+                Some(Ok(frame))
+            }
+            Parsed::Error(e, used) => {
+                self.buf.drain(..used);
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn data_frame(data: &[u8]) -> Vec<u8> {
+        let mut frame = vec![RESPONSE_HEADER, 0x00, (data.len() + 1) as u8];
+        frame.extend_from_slice(data);
+        frame.push(checksum(&frame));
+        frame
+    }
+
+    fn drain_all(parser: &mut FrameParser) -> Vec<Result<ResponseFrame, FrameError>> {
+        std::iter::from_fn(|| parser.next_frame()).collect()
+    }
+
+    #[test]
+    fn test_skips_garbage_before_header() {
+        let mut parser = FrameParser::new(ResponseKind::Ack);
+        parser.push(&[0x00, 0x8C]);
+        assert_eq!(parser.next_frame(), None);
+        assert_eq!(parser.discarded(), 2);
+        parser.push(&[0x70, 0x00, 0x70]);
+        assert_eq!(
+            parser.next_frame(),
+            Some(Ok(ResponseFrame {
+                code: ResponseCode::Success,
+                data: ResponseData::new()
+            }))
+        );
+        assert_eq!(parser.discarded(), 0);
+    }
+
+    #[test]
+    fn test_zero_length_is_an_error() {
+        let mut parser = FrameParser::new(ResponseKind::Data);
+        parser.push(&[0x70, 0x00, 0x00, 0x70]);
+        assert_eq!(parser.next_frame(), Some(Err(FrameError::InvalidLength(0))));
+        assert_eq!(parser.next_frame(), None);
+    }
+
+    #[test]
+    fn test_rejection_uses_short_frame_for_query() {
+        let mut parser = FrameParser::new(ResponseKind::Data);
+        parser.push(&[0x70, 0x02, 0x72]);
+        assert_eq!(
+            parser.next_frame(),
+            Some(Ok(ResponseFrame {
+                code: ResponseCode::LimitOverMin,
+                data: ResponseData::new()
+            }))
+        );
+    }
+
+    #[test]
+    fn test_resumes_after_bad_frame() {
+        let mut parser = FrameParser::new(ResponseKind::Data);
+        let mut bytes = vec![0x70, 0x00, 0x02, 0x01, 0x00];
+        bytes.extend(data_frame(&[0x2A]));
+        parser.push(&bytes);
+        assert_eq!(parser.next_frame(), Some(Err(FrameError::InvalidChecksum)));
+        assert_eq!(parser.next_frame().unwrap().unwrap().data, [0x2A]);
+    }
+
+    #[test]
+    fn test_noise_between_frames_does_not_add_up() {
+        let mut parser = FrameParser::new(ResponseKind::Ack);
+        for _ in 0..10 {
+            parser.push(&[0x55; MAX_GARBAGE / 4]);
+            parser.push(&[0x70, 0x00, 0x70]);
+        }
+        let frames = drain_all(&mut parser);
+        assert_eq!(frames.len(), 10);
+        assert!(frames.iter().all(Result::is_ok));
+    }
+
+    #[test]
+    fn test_gives_up_on_endless_garbage() {
+        let mut parser = FrameParser::new(ResponseKind::Ack);
+        parser.push(&[0x55; MAX_GARBAGE + 1]);
+        assert_eq!(
+            parser.next_frame(),
+            Some(Err(FrameError::UnexpectedHeader(0x55)))
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_never_panics_and_always_progresses(
+            bytes in proptest::collection::vec(any::<u8>(), 0..512),
+            data_kind in any::<bool>(),
+        ) {
+            let kind = if data_kind { ResponseKind::Data } else { ResponseKind::Ack };
+            let mut parser = FrameParser::new(kind);
+            parser.push(&bytes);
+            let mut steps = 0;
+            while parser.next_frame().is_some() {
+                steps += 1;
+                prop_assert!(steps <= bytes.len());
+            }
+            prop_assert!(parser.buffered() < 3 + 255);
+        }
+
+        #[test]
+        fn prop_chunking_does_not_change_result(
+            bytes in proptest::collection::vec(any::<u8>(), 0..256),
+            chunk in 1usize..16,
+        ) {
+            let mut whole = FrameParser::new(ResponseKind::Data);
+            whole.push(&bytes);
+            let expected = drain_all(&mut whole);
+
+            let mut pieces = FrameParser::new(ResponseKind::Data);
+            let mut got = Vec::new();
+            for part in bytes.chunks(chunk) {
+                pieces.push(part);
+                got.extend(drain_all(&mut pieces));
+            }
+            prop_assert_eq!(got, expected);
+        }
+
+        #[test]
+        fn prop_finds_frame_after_garbage(
+            garbage in proptest::collection::vec(any::<u8>().prop_filter("not a header", |b| *b != RESPONSE_HEADER), 0..MAX_GARBAGE),
+            data in proptest::collection::vec(any::<u8>(), 1..32),
+        ) {
+            let mut parser = FrameParser::new(ResponseKind::Data);
+            parser.push(&garbage);
+            prop_assert!(parser.next_frame().is_none());
+            prop_assert_eq!(parser.discarded(), garbage.len());
+            parser.push(&data_frame(&data));
+            let frame = parser.next_frame().unwrap().unwrap();
+            prop_assert_eq!(frame.data.to_vec(), data);
+        }
+    }
+}
@@ -1,7 +1,7 @@
 // src/protocol/language.rs
 #![allow(dead_code)]
 
-use crate::protocol::{Command, ProtocolError};
+use crate::protocol::{ActionData, Command, ControlCommand, ProtocolError};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LanguageCode([u8; 3]);
@@ -46,23 +46,15 @@ impl LanguageCode {
 pub struct Language;
 
 impl Command for Language {
-    type Action = LanguageCode;
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x07;
+}
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
-        let mut bytes = vec![0x00];
-        bytes.extend_from_slice(action.as_bytes());
-        bytes
-    }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
+impl ControlCommand for Language {
+    type Action = LanguageCode;
 
-    fn supports_query() -> bool {
-        false
+    fn encode(action: &Self::Action) -> ActionData {
+        let [a, b, c] = *action.as_bytes();
+        [0x00, a, b, c].into()
     }
 }
 
@@ -71,24 +63,15 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_language_build_bytes() {
+    fn test_language_encode() {
         let english = LanguageCode::english();
-        assert_eq!(
-            Language::build_bytes(&english),
-            vec![0x00, b'e', b'n', b'g']
-        );
+        assert_eq!(Language::encode(&english), [0x00, b'e', b'n', b'g']);
 
         let japanese = LanguageCode::japanese();
-        assert_eq!(
-            Language::build_bytes(&japanese),
-            vec![0x00, b'j', b'p', b'n']
-        );
+        assert_eq!(Language::encode(&japanese), [0x00, b'j', b'p', b'n']);
 
         let chinese = LanguageCode::chinese_simplified();
-        assert_eq!(
-            Language::build_bytes(&chinese),
-            vec![0x00, b'C', b'H', b'S']
-        );
+        assert_eq!(Language::encode(&chinese), [0x00, b'C', b'H', b'S']);
     }
 
     #[test]
@@ -1,6 +1,13 @@
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+#[cfg(feature = "alloc")]
+pub mod decode;
 pub mod error;
+pub mod frame;
 pub mod language;
 pub mod mode_control;
+pub mod packet;
 pub mod picture;
 pub mod screen;
 pub mod signage;
@@ -8,9 +15,16 @@ pub mod sircs;
 pub mod sound;
 pub mod values;
 
-pub use error::{ProtocolError, ResponseCode};
+pub use error::{InputNameError, ProtocolError, RangeError, ResponseCode};
+#[cfg(feature = "alloc")]
+pub use frame::FrameParser;
+pub use frame::{FrameError, MAX_GARBAGE, ResponseKind};
 pub use language::*;
 pub use mode_control::*;
+pub use packet::{
+    ActionData, ArrayBuf, MAX_DATA_LEN, Packet, ResponseData, encode_control_packet,
+    encode_query_packet,
+};
 pub use picture::*;
 pub use screen::*;
 pub use signage::*;
@@ -23,36 +37,103 @@ pub const QUERY_HEADER: u8 = 0x83;
 pub const CATEGORY: u8 = 0x00;
 pub const RESPONSE_HEADER: u8 = 0x70;
 
+/// A function of the TV, named by its function code.
 pub trait Command {
+    const FUNCTION_CODE: u8;
+}
+
+/// A command that changes something; the TV answers with an ack only.
+pub trait ControlCommand: Command {
     type Action;
-    type Response;
 
-    const FUNCTION_CODE: u8;
+    /// Data bytes of the control packet for `action`.
+    fn encode(action: &Self::Action) -> ActionData;
+
+    fn control_packet(action: &Self::Action) -> Packet {
+        packet::control_packet(Self::FUNCTION_CODE, &Self::encode(action))
+            .expect("action data fits in MAX_PACKET_LEN")
+    }
+
+    #[cfg(feature = "alloc")]
+    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+        Self::encode(action).to_vec()
+    }
+}
+
+/// A command whose current value the TV reports in answer to a query.
+/// Control-only commands do not implement it, so querying one is a compile
+/// error:
+///
+/// ```compile_fail
+/// use sony_bravia_cli::protocol::{Brightness, QueryCommand};
+///
+/// let packet = Brightness::query_packet();
+/// ```
+pub trait QueryCommand: Command {
+    type Response;
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8>;
     fn parse_response(bytes: &[u8]) -> Result<Self::Response, ProtocolError>;
-    fn supports_query() -> bool;
+
+    fn query_packet() -> Packet {
+        packet::query_packet(Self::FUNCTION_CODE)
+    }
+}
+
+/// Command names by function code, for logs and diagnostics.
+pub const FUNCTIONS: &[(u8, &str)] = &[
+    (Power::FUNCTION_CODE, "Power"),
+    (Standby::FUNCTION_CODE, "Standby"),
+    (InputSelect::FUNCTION_CODE, "InputSelect"),
+    (Volume::FUNCTION_CODE, "Volume"),
+    (Muting::FUNCTION_CODE, "Muting"),
+    (Language::FUNCTION_CODE, "Language"),
+    (OffTimer::FUNCTION_CODE, "OffTimer"),
+    (PictureOff::FUNCTION_CODE, "PictureOff"),
+    (Display::FUNCTION_CODE, "Display"),
+    (PictureMode::FUNCTION_CODE, "PictureMode"),
+    (Contrast::FUNCTION_CODE, "Contrast"),
+    (Brightness::FUNCTION_CODE, "Brightness"),
+    (Color::FUNCTION_CODE, "Color"),
+    (Hue::FUNCTION_CODE, "Hue"),
+    (Sharpness::FUNCTION_CODE, "Sharpness"),
+    (CineMotion::FUNCTION_CODE, "CineMotion"),
+    (SoundMode::FUNCTION_CODE, "SoundMode"),
+    (Speaker::FUNCTION_CODE, "Speaker"),
+    (HShift::FUNCTION_CODE, "HShift"),
+    (VSize::FUNCTION_CODE, "VSize"),
+    (VShift::FUNCTION_CODE, "VShift"),
+    (Wide::FUNCTION_CODE, "Wide"),
+    (AutoWide::FUNCTION_CODE, "AutoWide"),
+    (FourThreeMode::FUNCTION_CODE, "FourThreeMode"),
+    (Sircs::FUNCTION_CODE, "Sircs"),
+    (ProductInfo3::FUNCTION_CODE, "ProductInfo3"),
+    (ProductInfo2::FUNCTION_CODE, "ProductInfo2"),
+    (ProductInfo1::FUNCTION_CODE, "ProductInfo1"),
+    (IdCommand::FUNCTION_CODE, "IdCommand"),
+];
+
+pub fn function_name(function: u8) -> Option<&'static str> {
+    FUNCTIONS
+        .iter()
+        .find(|(code, _)| *code == function)
+        .map(|(_, name)| *name)
 }
 
 pub fn checksum(bytes: &[u8]) -> u8 {
     bytes.iter().fold(0u8, |acc, &b| acc.wrapping_add(b))
 }
 
+/// Panics if `data` is longer than `MAX_DATA_LEN`.
+#[cfg(feature = "alloc")]
 pub fn build_control_packet(function: u8, data: &[u8]) -> Vec<u8> {
-    let length = (data.len() + 1) as u8; // data + checksum
-    let mut packet = vec![CONTROL_HEADER, CATEGORY, function, length];
-    packet.extend_from_slice(data);
-    let cs = checksum(&packet);
-    packet.push(cs);
+    let mut packet = vec![0; packet::control_packet_len(data.len())];
+    encode_control_packet(function, data, &mut packet).expect("control data too long");
     packet
 }
 
+#[cfg(feature = "alloc")]
 pub fn build_query_packet(function: u8) -> Vec<u8> {
-    let packet_without_cs = vec![QUERY_HEADER, CATEGORY, function, 0xFF, 0xFF];
-    let cs = checksum(&packet_without_cs);
-    let mut packet = packet_without_cs;
-    packet.push(cs);
-    packet
+    packet::query_packet(function).to_vec()
 }
 
 #[cfg(test)]
@@ -1,6 +1,12 @@
 #![allow(dead_code)]
 
-use crate::protocol::{Command, ProtocolError, SleepMinutes, VolumeValue};
+use core::fmt;
+use core::str::FromStr;
+
+use crate::protocol::{
+    ActionData, Command, ControlCommand, InputNameError, ProtocolError, QueryCommand, SleepMinutes,
+    VolumeValue,
+};
 
 // === Power (0x00) ===
 
@@ -19,17 +25,22 @@ pub enum PowerState {
 pub struct Power;
 
 impl Command for Power {
-    type Action = PowerAction;
-    type Response = PowerState;
-
     const FUNCTION_CODE: u8 = 0x00;
+}
+
+impl ControlCommand for Power {
+    type Action = PowerAction;
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            PowerAction::Off => vec![0x00],
-            PowerAction::On => vec![0x01],
+            PowerAction::Off => [0x00].into(),
+            PowerAction::On => [0x01].into(),
         }
     }
+}
+
+impl QueryCommand for Power {
+    type Response = PowerState;
 
     fn parse_response(bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
         match bytes.first() {
@@ -38,10 +49,6 @@ impl Command for Power {
             _ => Err(ProtocolError::InvalidResponse),
         }
     }
-
-    fn supports_query() -> bool {
-        true
-    }
 }
 
 // === Standby (0x01) ===
@@ -55,29 +62,24 @@ pub enum StandbyAction {
 pub struct Standby;
 
 impl Command for Standby {
-    type Action = StandbyAction;
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x01;
+}
+
+impl ControlCommand for Standby {
+    type Action = StandbyAction;
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            StandbyAction::Disable => vec![0x00],
-            StandbyAction::Enable => vec![0x01],
+            StandbyAction::Disable => [0x00].into(),
+            StandbyAction::Enable => [0x01].into(),
         }
     }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
-
-    fn supports_query() -> bool {
-        false
-    }
 }
 
 // === Input Select (0x02) ===
 
+/// An input to select, or the one a query reports. Named `hdmi1`,
+/// `component2` and so on by `Display` and `FromStr`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InputType {
     Toggle,
@@ -86,46 +88,94 @@ pub enum InputType {
     Hdmi(u8),        // 1-5
     Pc(u8),          // 1
     SharedInput(u8), // 1
+    /// A type code with no name here, kept as the TV sent it. It has no
+    /// name `FromStr` accepts, and selecting it sends `kind` and `num`
+    /// unchecked, so only pass one back that came from a query.
+    Unknown {
+        kind: u8,
+        num: u8,
+    },
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct InputState {
-    pub input_type: u8,
-    pub input_num: u8,
+impl fmt::Display for InputType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputType::Toggle => f.write_str("toggle"),
+            InputType::Video(n) => write!(f, "video{}", n),
+            InputType::Component(n) => write!(f, "component{}", n),
+            InputType::Hdmi(n) => write!(f, "hdmi{}", n),
+            InputType::Pc(n) => write!(f, "pc{}", n),
+            InputType::SharedInput(n) => write!(f, "shared{}", n),
+            InputType::Unknown { kind, num } => write!(f, "unknown({:#04x}, {})", kind, num),
+        }
+    }
+}
+
+/// Accepts the names `Display` gives, for the inputs a TV can have; the
+/// form shown for `Unknown` does not parse back.
+impl FromStr for InputType {
+    type Err = InputNameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "toggle" {
+            return Ok(InputType::Toggle);
+        }
+        let digits = s.find(|c: char| c.is_ascii_digit()).ok_or(InputNameError)?;
+        let (name, num) = s.split_at(digits);
+        let num: u8 = num.parse().map_err(|_| InputNameError)?;
+        let (input, max) = match name {
+            "video" => (InputType::Video(num), 3),
+            "component" => (InputType::Component(num), 3),
+            "hdmi" => (InputType::Hdmi(num), 5),
+            "pc" => (InputType::Pc(num), 1),
+            "shared" => (InputType::SharedInput(num), 1),
+            _ => return Err(InputNameError),
+        };
+        if (1..=max).contains(&num) {
+            Ok(input)
+        } else {
+            Err(InputNameError)
+        }
+    }
 }
 
 pub struct InputSelect;
 
 impl Command for InputSelect {
-    type Action = InputType;
-    type Response = InputState;
-
     const FUNCTION_CODE: u8 = 0x02;
+}
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+impl ControlCommand for InputSelect {
+    type Action = InputType;
+
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            InputType::Toggle => vec![0x00],
-            InputType::Video(n) => vec![0x02, *n],
-            InputType::Component(n) => vec![0x03, *n],
-            InputType::Hdmi(n) => vec![0x04, *n],
-            InputType::Pc(n) => vec![0x05, *n],
-            InputType::SharedInput(n) => vec![0x07, *n],
+            InputType::Toggle => [0x00].into(),
+            InputType::Video(n) => [0x02, *n].into(),
+            InputType::Component(n) => [0x03, *n].into(),
+            InputType::Hdmi(n) => [0x04, *n].into(),
+            InputType::Pc(n) => [0x05, *n].into(),
+            InputType::SharedInput(n) => [0x07, *n].into(),
+            InputType::Unknown { kind, num } => [*kind, *num].into(),
         }
     }
+}
 
-    fn parse_response(bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        if bytes.len() >= 2 {
-            Ok(InputState {
-                input_type: bytes[0],
-                input_num: bytes[1],
-            })
-        } else {
-            Err(ProtocolError::InvalidResponse)
-        }
-    }
+impl QueryCommand for InputSelect {
+    type Response = InputType;
 
-    fn supports_query() -> bool {
-        true
+    fn parse_response(bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
+        let [kind, num, ..] = *bytes else {
+            return Err(ProtocolError::InvalidResponse);
+        };
+        Ok(match kind {
+            0x02 => InputType::Video(num),
+            0x03 => InputType::Component(num),
+            0x04 => InputType::Hdmi(num),
+            0x05 => InputType::Pc(num),
+            0x07 => InputType::SharedInput(num),
+            _ => InputType::Unknown { kind, num },
+        })
     }
 }
 
@@ -141,26 +191,27 @@ pub enum VolumeAction {
 pub struct Volume;
 
 impl Command for Volume {
-    type Action = VolumeAction;
-    type Response = u8;
-
     const FUNCTION_CODE: u8 = 0x05;
+}
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+impl ControlCommand for Volume {
+    type Action = VolumeAction;
+
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            VolumeAction::Up => vec![0x00, 0x00],
-            VolumeAction::Down => vec![0x00, 0x01],
-            VolumeAction::Set(v) => vec![0x01, v.get()],
+            VolumeAction::Up => [0x00, 0x00].into(),
+            VolumeAction::Down => [0x00, 0x01].into(),
+            VolumeAction::Set(v) => [0x01, v.get()].into(),
         }
     }
+}
+
+impl QueryCommand for Volume {
+    type Response = u8;
 
     fn parse_response(bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
         bytes.first().copied().ok_or(ProtocolError::InvalidResponse)
     }
-
-    fn supports_query() -> bool {
-        true
-    }
 }
 
 // === Muting (0x06) ===
@@ -181,18 +232,23 @@ pub enum MuteState {
 pub struct Muting;
 
 impl Command for Muting {
-    type Action = MuteAction;
-    type Response = MuteState;
-
     const FUNCTION_CODE: u8 = 0x06;
+}
+
+impl ControlCommand for Muting {
+    type Action = MuteAction;
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            MuteAction::Toggle => vec![0x00],
-            MuteAction::Unmute => vec![0x01, 0x00],
-            MuteAction::Mute => vec![0x01, 0x01],
+            MuteAction::Toggle => [0x00].into(),
+            MuteAction::Unmute => [0x01, 0x00].into(),
+            MuteAction::Mute => [0x01, 0x01].into(),
         }
     }
+}
+
+impl QueryCommand for Muting {
+    type Response = MuteState;
 
     fn parse_response(bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
         match bytes.first() {
@@ -201,10 +257,6 @@ impl Command for Muting {
             _ => Err(ProtocolError::InvalidResponse),
         }
     }
-
-    fn supports_query() -> bool {
-        true
-    }
 }
 
 // === Off Timer / Sleep (0x0C) ===
@@ -218,25 +270,18 @@ pub enum SleepAction {
 pub struct OffTimer;
 
 impl Command for OffTimer {
-    type Action = SleepAction;
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x0C;
+}
+
+impl ControlCommand for OffTimer {
+    type Action = SleepAction;
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            SleepAction::Toggle => vec![0x00],
-            SleepAction::Set(m) => vec![0x01, m.get()],
+            SleepAction::Toggle => [0x00].into(),
+            SleepAction::Set(m) => [0x01, m.get()].into(),
         }
     }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
-
-    fn supports_query() -> bool {
-        false
-    }
 }
 
 // === Picture OFF (0x0D) ===
@@ -251,26 +296,19 @@ pub enum PictureOffAction {
 pub struct PictureOff;
 
 impl Command for PictureOff {
-    type Action = PictureOffAction;
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x0D;
+}
+
+impl ControlCommand for PictureOff {
+    type Action = PictureOffAction;
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            PictureOffAction::Toggle => vec![0x00],
-            PictureOffAction::Off => vec![0x01, 0x00],
-            PictureOffAction::On => vec![0x01, 0x01],
+            PictureOffAction::Toggle => [0x00].into(),
+            PictureOffAction::Off => [0x01, 0x00].into(),
+            PictureOffAction::On => [0x01, 0x01].into(),
         }
     }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
-
-    fn supports_query() -> bool {
-        false
-    }
 }
 
 // === Display (0x0F) ===
@@ -278,21 +316,14 @@ impl Command for PictureOff {
 pub struct Display;
 
 impl Command for Display {
-    type Action = ();
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x0F;
+}
 
-    fn build_bytes(_action: &Self::Action) -> Vec<u8> {
-        vec![0x00]
-    }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
+impl ControlCommand for Display {
+    type Action = ();
 
-    fn supports_query() -> bool {
-        false
+    fn encode(_action: &Self::Action) -> ActionData {
+        [0x00].into()
     }
 }
 
@@ -301,9 +332,9 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_power_build_bytes() {
-        assert_eq!(Power::build_bytes(&PowerAction::Off), vec![0x00]);
-        assert_eq!(Power::build_bytes(&PowerAction::On), vec![0x01]);
+    fn test_power_encode() {
+        assert_eq!(Power::encode(&PowerAction::Off), [0x00]);
+        assert_eq!(Power::encode(&PowerAction::On), [0x01]);
     }
 
     #[test]
@@ -314,30 +345,61 @@ mod tests {
     }
 
     #[test]
-    fn test_input_select_build_bytes() {
-        assert_eq!(InputSelect::build_bytes(&InputType::Toggle), vec![0x00]);
+    fn test_input_select_encode() {
+        assert_eq!(InputSelect::encode(&InputType::Toggle), [0x00]);
+        assert_eq!(InputSelect::encode(&InputType::Hdmi(1)), [0x04, 0x01]);
+        assert_eq!(InputSelect::encode(&InputType::Hdmi(3)), [0x04, 0x03]);
+    }
+
+    #[test]
+    fn test_input_select_parse_response() {
         assert_eq!(
-            InputSelect::build_bytes(&InputType::Hdmi(1)),
-            vec![0x04, 0x01]
+            InputSelect::parse_response(&[0x04, 0x01]).unwrap(),
+            InputType::Hdmi(1)
         );
         assert_eq!(
-            InputSelect::build_bytes(&InputType::Hdmi(3)),
-            vec![0x04, 0x03]
+            InputSelect::parse_response(&[0x03, 0x02]).unwrap(),
+            InputType::Component(2)
+        );
+        let unknown = InputSelect::parse_response(&[0x06, 0x01]).unwrap();
+        assert_eq!(unknown, InputType::Unknown { kind: 0x06, num: 1 });
+        assert_eq!(InputSelect::encode(&unknown), [0x06, 0x01]);
+        assert!(InputSelect::parse_response(&[0x04]).is_err());
+    }
+
+    #[test]
+    fn test_input_names_round_trip() {
+        for input in [
+            InputType::Toggle,
+            InputType::Hdmi(5),
+            InputType::Component(2),
+            InputType::Video(1),
+            InputType::Pc(1),
+            InputType::SharedInput(1),
+        ] {
+            assert_eq!(input.to_string().parse::<InputType>(), Ok(input));
+        }
+        assert_eq!(
+            InputType::Unknown { kind: 0x06, num: 1 }.to_string(),
+            "unknown(0x06, 1)"
         );
+        assert!("hdmi6".parse::<InputType>().is_err());
+        assert!("hdmi".parse::<InputType>().is_err());
+        assert!("dvi1".parse::<InputType>().is_err());
     }
 
     #[test]
-    fn test_volume_build_bytes() {
-        assert_eq!(Volume::build_bytes(&VolumeAction::Up), vec![0x00, 0x00]);
-        assert_eq!(Volume::build_bytes(&VolumeAction::Down), vec![0x00, 0x01]);
+    fn test_volume_encode() {
+        assert_eq!(Volume::encode(&VolumeAction::Up), [0x00, 0x00]);
+        assert_eq!(Volume::encode(&VolumeAction::Down), [0x00, 0x01]);
         let vol = VolumeValue::new(50).unwrap();
-        assert_eq!(Volume::build_bytes(&VolumeAction::Set(vol)), vec![0x01, 50]);
+        assert_eq!(Volume::encode(&VolumeAction::Set(vol)), [0x01, 50]);
     }
 
     #[test]
-    fn test_mute_build_bytes() {
-        assert_eq!(Muting::build_bytes(&MuteAction::Toggle), vec![0x00]);
-        assert_eq!(Muting::build_bytes(&MuteAction::Mute), vec![0x01, 0x01]);
-        assert_eq!(Muting::build_bytes(&MuteAction::Unmute), vec![0x01, 0x00]);
+    fn test_mute_encode() {
+        assert_eq!(Muting::encode(&MuteAction::Toggle), [0x00]);
+        assert_eq!(Muting::encode(&MuteAction::Mute), [0x01, 0x01]);
+        assert_eq!(Muting::encode(&MuteAction::Unmute), [0x01, 0x00]);
     }
 }
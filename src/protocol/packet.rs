@@ -0,0 +1,222 @@
+//! Packet encoding without allocation, for targets with no heap.
+//!
+//! Packets are written into caller-provided buffers or returned as
+//! fixed-capacity `ArrayBuf`s; `build_control_packet` and friends layer the
+//! `Vec` API on top of these.
+
+use core::fmt;
+use core::ops::Deref;
+
+use crate::protocol::{CATEGORY, CONTROL_HEADER, ProtocolError, QUERY_HEADER, checksum};
+
+/// Largest data payload; the length byte also counts the checksum.
+pub const MAX_DATA_LEN: usize = 254;
+
+/// Largest data payload of any typed command's action (`Language`).
+pub const MAX_ACTION_LEN: usize = 4;
+
+pub const QUERY_PACKET_LEN: usize = 6;
+
+/// Largest packet any typed command sends.
+pub const MAX_PACKET_LEN: usize = control_packet_len(MAX_ACTION_LEN);
+
+/// Data bytes of a typed command's control packet.
+pub type ActionData = ArrayBuf<MAX_ACTION_LEN>;
+
+/// A complete packet for a typed command.
+pub type Packet = ArrayBuf<MAX_PACKET_LEN>;
+
+/// Data bytes of a response frame, without the checksum.
+pub type ResponseData = ArrayBuf<MAX_DATA_LEN>;
+
+/// Size of a control packet carrying `data_len` data bytes.
+pub const fn control_packet_len(data_len: usize) -> usize {
+    // Header, category, function, length, data, checksum.
+    data_len + 5
+}
+
+/// Writes a control packet into the start of `out` and returns its length.
+pub fn encode_control_packet(
+    function: u8,
+    data: &[u8],
+    out: &mut [u8],
+) -> Result<usize, ProtocolError> {
+    if data.len() > MAX_DATA_LEN {
+        return Err(ProtocolError::DataTooLong(data.len()));
+    }
+    let len = control_packet_len(data.len());
+    if out.len() < len {
+        return Err(ProtocolError::BufferTooSmall {
+            needed: len,
+            capacity: out.len(),
+        });
+    }
+    let length = (data.len() + 1) as u8; // data + checksum
+    out[..4].copy_from_slice(&[CONTROL_HEADER, CATEGORY, function, length]);
+    out[4..len - 1].copy_from_slice(data);
+    out[len - 1] = checksum(&out[..len - 1]);
+    Ok(len)
+}
+
+/// Writes a query packet into the start of `out` and returns its length.
+pub fn encode_query_packet(function: u8, out: &mut [u8]) -> Result<usize, ProtocolError> {
+    if out.len() < QUERY_PACKET_LEN {
+        return Err(ProtocolError::BufferTooSmall {
+            needed: QUERY_PACKET_LEN,
+            capacity: out.len(),
+        });
+    }
+    out[..5].copy_from_slice(&[QUERY_HEADER, CATEGORY, function, 0xFF, 0xFF]);
+    out[5] = checksum(&out[..5]);
+    Ok(QUERY_PACKET_LEN)
+}
+
+/// A control packet for up to `MAX_ACTION_LEN` data bytes.
+pub fn control_packet(function: u8, data: &[u8]) -> Result<Packet, ProtocolError> {
+    let mut packet = Packet::new();
+    packet.len = encode_control_packet(function, data, &mut packet.buf)?;
+    Ok(packet)
+}
+
+pub fn query_packet(function: u8) -> Packet {
+    let mut packet = Packet::new();
+    packet.len = encode_query_packet(function, &mut packet.buf)
+        .expect("a query packet fits in MAX_PACKET_LEN");
+    packet
+}
+
+/// Up to `N` bytes stored inline, used where the `Vec` API would allocate.
+/// Derefs to the bytes it holds.
+#[derive(Clone)]
+pub struct ArrayBuf<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> ArrayBuf<N> {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            len: 0,
+        }
+    }
+
+    /// Copies a fixed-size array; one that does not fit fails to compile.
+    pub const fn from_array<const M: usize>(bytes: [u8; M]) -> Self {
+        const { assert!(M <= N, "array larger than ArrayBuf capacity") };
+        let mut buf = [0; N];
+        let mut i = 0;
+        while i < M {
+            buf[i] = bytes[i];
+            i += 1;
+        }
+        Self { buf, len: M }
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, ProtocolError> {
+        if bytes.len() > N {
+            return Err(ProtocolError::BufferTooSmall {
+                needed: bytes.len(),
+                capacity: N,
+            });
+        }
+        let mut array = Self::new();
+        array.buf[..bytes.len()].copy_from_slice(bytes);
+        array.len = bytes.len();
+        Ok(array)
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+impl<const N: usize> Default for ArrayBuf<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Deref for ArrayBuf<N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for ArrayBuf<N> {
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl<const N: usize, const M: usize> From<[u8; M]> for ArrayBuf<N> {
+    fn from(bytes: [u8; M]) -> Self {
+        Self::from_array(bytes)
+    }
+}
+
+impl<const N: usize> fmt::Debug for ArrayBuf<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
+impl<const N: usize> PartialEq for ArrayBuf<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<const N: usize> Eq for ArrayBuf<N> {}
+
+impl<const N: usize> PartialEq<[u8]> for ArrayBuf<N> {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.as_slice() == other
+    }
+}
+
+impl<const N: usize, const M: usize> PartialEq<[u8; M]> for ArrayBuf<N> {
+    fn eq(&self, other: &[u8; M]) -> bool {
+        self.as_slice() == other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_into_buffer() {
+        let mut buf = [0u8; 16];
+        let len = encode_control_packet(0x00, &[0x01], &mut buf).unwrap();
+        assert_eq!(&buf[..len], &[0x8C, 0x00, 0x00, 0x02, 0x01, 0x8F]);
+        let len = encode_query_packet(0x00, &mut buf).unwrap();
+        assert_eq!(&buf[..len], &[0x83, 0x00, 0x00, 0xFF, 0xFF, 0x81]);
+    }
+
+    #[test]
+    fn test_encode_checks_sizes() {
+        assert_eq!(
+            encode_control_packet(0x07, &[0x00, b'e', b'n', b'g'], &mut [0u8; 8]),
+            Err(ProtocolError::BufferTooSmall {
+                needed: 9,
+                capacity: 8
+            })
+        );
+        assert_eq!(
+            encode_control_packet(0x99, &[0u8; 255], &mut [0u8; 300]),
+            Err(ProtocolError::DataTooLong(255))
+        );
+        assert!(encode_query_packet(0x00, &mut [0u8; 5]).is_err());
+    }
+
+    #[test]
+    fn test_fixed_capacity_packets() {
+        let packet = control_packet(0x02, &[0x04, 0x02]).unwrap();
+        assert_eq!(packet, [0x8C, 0x00, 0x02, 0x03, 0x04, 0x02, 0x97]);
+        assert_eq!(query_packet(0x05).len(), QUERY_PACKET_LEN);
+        assert!(control_packet(0x99, &[0u8; MAX_ACTION_LEN + 1]).is_err());
+    }
+}
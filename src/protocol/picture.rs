@@ -1,7 +1,8 @@
 #![allow(dead_code)]
 
 use crate::protocol::{
-    BrightnessValue, ColorValue, Command, ContrastValue, HueValue, ProtocolError, SharpnessValue,
+    ActionData, BrightnessValue, ColorValue, Command, ContrastValue, ControlCommand, HueValue,
+    SharpnessValue,
 };
 
 // === Picture Mode (0x20) ===
@@ -20,30 +21,23 @@ pub enum PictureModeAction {
 pub struct PictureMode;
 
 impl Command for PictureMode {
-    type Action = PictureModeAction;
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x20;
+}
+
+impl ControlCommand for PictureMode {
+    type Action = PictureModeAction;
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            PictureModeAction::Toggle => vec![0x00],
-            PictureModeAction::Vivid => vec![0x01, 0x00],
-            PictureModeAction::Standard => vec![0x01, 0x01],
-            PictureModeAction::Cinema => vec![0x01, 0x02],
-            PictureModeAction::Custom => vec![0x01, 0x03],
-            PictureModeAction::Game => vec![0x01, 0x08],
-            PictureModeAction::Graphics => vec![0x01, 0x09],
+            PictureModeAction::Toggle => [0x00].into(),
+            PictureModeAction::Vivid => [0x01, 0x00].into(),
+            PictureModeAction::Standard => [0x01, 0x01].into(),
+            PictureModeAction::Cinema => [0x01, 0x02].into(),
+            PictureModeAction::Custom => [0x01, 0x03].into(),
+            PictureModeAction::Game => [0x01, 0x08].into(),
+            PictureModeAction::Graphics => [0x01, 0x09].into(),
         }
     }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
-
-    fn supports_query() -> bool {
-        false
-    }
 }
 
 // === Picture/Contrast (0x23) ===
@@ -58,26 +52,19 @@ pub enum ContrastAction {
 pub struct Contrast;
 
 impl Command for Contrast {
-    type Action = ContrastAction;
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x23;
+}
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+impl ControlCommand for Contrast {
+    type Action = ContrastAction;
+
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            ContrastAction::Up => vec![0x00, 0x00],
-            ContrastAction::Down => vec![0x00, 0x01],
-            ContrastAction::Set(v) => vec![0x01, v.get()],
+            ContrastAction::Up => [0x00, 0x00].into(),
+            ContrastAction::Down => [0x00, 0x01].into(),
+            ContrastAction::Set(v) => [0x01, v.get()].into(),
         }
     }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
-
-    fn supports_query() -> bool {
-        false
-    }
 }
 
 // === Brightness (0x24) ===
@@ -92,26 +79,19 @@ pub enum BrightnessAction {
 pub struct Brightness;
 
 impl Command for Brightness {
-    type Action = BrightnessAction;
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x24;
+}
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+impl ControlCommand for Brightness {
+    type Action = BrightnessAction;
+
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            BrightnessAction::Up => vec![0x00, 0x00],
-            BrightnessAction::Down => vec![0x00, 0x01],
-            BrightnessAction::Set(v) => vec![0x01, v.get()],
+            BrightnessAction::Up => [0x00, 0x00].into(),
+            BrightnessAction::Down => [0x00, 0x01].into(),
+            BrightnessAction::Set(v) => [0x01, v.get()].into(),
         }
     }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
-
-    fn supports_query() -> bool {
-        false
-    }
 }
 
 // === Color (0x25) ===
@@ -126,26 +106,19 @@ pub enum ColorAction {
 pub struct Color;
 
 impl Command for Color {
-    type Action = ColorAction;
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x25;
+}
+
+impl ControlCommand for Color {
+    type Action = ColorAction;
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            ColorAction::Up => vec![0x00, 0x00],
-            ColorAction::Down => vec![0x00, 0x01],
-            ColorAction::Set(v) => vec![0x01, v.get()],
+            ColorAction::Up => [0x00, 0x00].into(),
+            ColorAction::Down => [0x00, 0x01].into(),
+            ColorAction::Set(v) => [0x01, v.get()].into(),
         }
     }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
-
-    fn supports_query() -> bool {
-        false
-    }
 }
 
 // === Hue (0x26) ===
@@ -166,29 +139,22 @@ pub enum HueAction {
 pub struct Hue;
 
 impl Command for Hue {
-    type Action = HueAction;
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x26;
+}
+
+impl ControlCommand for Hue {
+    type Action = HueAction;
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            HueAction::Up(HueChannel::Red) => vec![0x00, 0x00, 0x00],
-            HueAction::Down(HueChannel::Red) => vec![0x00, 0x00, 0x01],
-            HueAction::Up(HueChannel::Green) => vec![0x00, 0x01, 0x00],
-            HueAction::Down(HueChannel::Green) => vec![0x00, 0x01, 0x01],
-            HueAction::Set(HueChannel::Red, v) => vec![0x01, 0x00, v.get()],
-            HueAction::Set(HueChannel::Green, v) => vec![0x01, 0x01, v.get()],
+            HueAction::Up(HueChannel::Red) => [0x00, 0x00, 0x00].into(),
+            HueAction::Down(HueChannel::Red) => [0x00, 0x00, 0x01].into(),
+            HueAction::Up(HueChannel::Green) => [0x00, 0x01, 0x00].into(),
+            HueAction::Down(HueChannel::Green) => [0x00, 0x01, 0x01].into(),
+            HueAction::Set(HueChannel::Red, v) => [0x01, 0x00, v.get()].into(),
+            HueAction::Set(HueChannel::Green, v) => [0x01, 0x01, v.get()].into(),
         }
     }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
-
-    fn supports_query() -> bool {
-        false
-    }
 }
 
 // === Sharpness (0x28) ===
@@ -203,26 +169,19 @@ pub enum SharpnessAction {
 pub struct Sharpness;
 
 impl Command for Sharpness {
-    type Action = SharpnessAction;
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x28;
+}
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+impl ControlCommand for Sharpness {
+    type Action = SharpnessAction;
+
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            SharpnessAction::Up => vec![0x00, 0x00],
-            SharpnessAction::Down => vec![0x00, 0x01],
-            SharpnessAction::Set(v) => vec![0x01, v.get()],
+            SharpnessAction::Up => [0x00, 0x00].into(),
+            SharpnessAction::Down => [0x00, 0x01].into(),
+            SharpnessAction::Set(v) => [0x01, v.get()].into(),
         }
     }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
-
-    fn supports_query() -> bool {
-        false
-    }
 }
 
 // === Cine Motion (0x2A) ===
@@ -236,25 +195,18 @@ pub enum CineMotionAction {
 pub struct CineMotion;
 
 impl Command for CineMotion {
-    type Action = CineMotionAction;
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x2A;
+}
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+impl ControlCommand for CineMotion {
+    type Action = CineMotionAction;
+
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            CineMotionAction::Off => vec![0x00],
-            CineMotionAction::Auto => vec![0x01],
+            CineMotionAction::Off => [0x00].into(),
+            CineMotionAction::Auto => [0x01].into(),
         }
     }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
-
-    fn supports_query() -> bool {
-        false
-    }
 }
 
 #[cfg(test)]
@@ -262,56 +214,38 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_picture_mode_build_bytes() {
-        assert_eq!(
-            PictureMode::build_bytes(&PictureModeAction::Toggle),
-            vec![0x00]
-        );
-        assert_eq!(
-            PictureMode::build_bytes(&PictureModeAction::Vivid),
-            vec![0x01, 0x00]
-        );
-        assert_eq!(
-            PictureMode::build_bytes(&PictureModeAction::Cinema),
-            vec![0x01, 0x02]
-        );
+    fn test_picture_mode_encode() {
+        assert_eq!(PictureMode::encode(&PictureModeAction::Toggle), [0x00]);
+        assert_eq!(PictureMode::encode(&PictureModeAction::Vivid), [0x01, 0x00]);
         assert_eq!(
-            PictureMode::build_bytes(&PictureModeAction::Game),
-            vec![0x01, 0x08]
+            PictureMode::encode(&PictureModeAction::Cinema),
+            [0x01, 0x02]
         );
+        assert_eq!(PictureMode::encode(&PictureModeAction::Game), [0x01, 0x08]);
     }
 
     #[test]
-    fn test_brightness_build_bytes() {
-        assert_eq!(
-            Brightness::build_bytes(&BrightnessAction::Up),
-            vec![0x00, 0x00]
-        );
-        assert_eq!(
-            Brightness::build_bytes(&BrightnessAction::Down),
-            vec![0x00, 0x01]
-        );
+    fn test_brightness_encode() {
+        assert_eq!(Brightness::encode(&BrightnessAction::Up), [0x00, 0x00]);
+        assert_eq!(Brightness::encode(&BrightnessAction::Down), [0x00, 0x01]);
         let val = BrightnessValue::new(25).unwrap();
-        assert_eq!(
-            Brightness::build_bytes(&BrightnessAction::Set(val)),
-            vec![0x01, 25]
-        );
+        assert_eq!(Brightness::encode(&BrightnessAction::Set(val)), [0x01, 25]);
     }
 
     #[test]
-    fn test_hue_build_bytes() {
+    fn test_hue_encode() {
         assert_eq!(
-            Hue::build_bytes(&HueAction::Up(HueChannel::Red)),
-            vec![0x00, 0x00, 0x00]
+            Hue::encode(&HueAction::Up(HueChannel::Red)),
+            [0x00, 0x00, 0x00]
         );
         assert_eq!(
-            Hue::build_bytes(&HueAction::Down(HueChannel::Green)),
-            vec![0x00, 0x01, 0x01]
+            Hue::encode(&HueAction::Down(HueChannel::Green)),
+            [0x00, 0x01, 0x01]
         );
         let val = HueValue::new(50).unwrap();
         assert_eq!(
-            Hue::build_bytes(&HueAction::Set(HueChannel::Red, val)),
-            vec![0x01, 0x00, 50]
+            Hue::encode(&HueAction::Set(HueChannel::Red, val)),
+            [0x01, 0x00, 50]
         );
     }
 }
@@ -1,7 +1,7 @@
 // src/protocol/screen.rs
 #![allow(dead_code)]
 
-use crate::protocol::{Command, ProtocolError};
+use crate::protocol::{ActionData, Command, ControlCommand};
 
 // === H Shift (0x41) ===
 
@@ -16,27 +16,20 @@ pub enum HShiftAction {
 pub struct HShift;
 
 impl Command for HShift {
-    type Action = HShiftAction;
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x41;
+}
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+impl ControlCommand for HShift {
+    type Action = HShiftAction;
+
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            HShiftAction::Up => vec![0x00, 0x00],
-            HShiftAction::Down => vec![0x00, 0x01],
-            HShiftAction::SetPlus(v) => vec![0x01, 0x00, *v],
-            HShiftAction::SetMinus(v) => vec![0x01, 0x01, *v],
+            HShiftAction::Up => [0x00, 0x00].into(),
+            HShiftAction::Down => [0x00, 0x01].into(),
+            HShiftAction::SetPlus(v) => [0x01, 0x00, *v].into(),
+            HShiftAction::SetMinus(v) => [0x01, 0x01, *v].into(),
         }
     }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
-
-    fn supports_query() -> bool {
-        false
-    }
 }
 
 // === V Size (0x42) ===
@@ -52,27 +45,20 @@ pub enum VSizeAction {
 pub struct VSize;
 
 impl Command for VSize {
-    type Action = VSizeAction;
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x42;
+}
+
+impl ControlCommand for VSize {
+    type Action = VSizeAction;
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            VSizeAction::Up => vec![0x00, 0x00],
-            VSizeAction::Down => vec![0x00, 0x01],
-            VSizeAction::SetPlus(v) => vec![0x01, 0x00, *v],
-            VSizeAction::SetMinus(v) => vec![0x01, 0x01, *v],
+            VSizeAction::Up => [0x00, 0x00].into(),
+            VSizeAction::Down => [0x00, 0x01].into(),
+            VSizeAction::SetPlus(v) => [0x01, 0x00, *v].into(),
+            VSizeAction::SetMinus(v) => [0x01, 0x01, *v].into(),
         }
     }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
-
-    fn supports_query() -> bool {
-        false
-    }
 }
 
 // === V Shift (0x43) ===
@@ -88,27 +74,20 @@ pub enum VShiftAction {
 pub struct VShift;
 
 impl Command for VShift {
-    type Action = VShiftAction;
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x43;
+}
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+impl ControlCommand for VShift {
+    type Action = VShiftAction;
+
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            VShiftAction::Up => vec![0x00, 0x00],
-            VShiftAction::Down => vec![0x00, 0x01],
-            VShiftAction::SetPlus(v) => vec![0x01, 0x00, *v],
-            VShiftAction::SetMinus(v) => vec![0x01, 0x01, *v],
+            VShiftAction::Up => [0x00, 0x00].into(),
+            VShiftAction::Down => [0x00, 0x01].into(),
+            VShiftAction::SetPlus(v) => [0x01, 0x00, *v].into(),
+            VShiftAction::SetMinus(v) => [0x01, 0x01, *v].into(),
         }
     }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
-
-    fn supports_query() -> bool {
-        false
-    }
 }
 
 // === Wide (0x44) ===
@@ -128,31 +107,24 @@ pub enum WideAction {
 pub struct Wide;
 
 impl Command for Wide {
-    type Action = WideAction;
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x44;
+}
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+impl ControlCommand for Wide {
+    type Action = WideAction;
+
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            WideAction::Toggle => vec![0x00],
-            WideAction::WideZoom => vec![0x01, 0x00],
-            WideAction::Full => vec![0x01, 0x01],
-            WideAction::Zoom => vec![0x01, 0x02],
-            WideAction::Normal => vec![0x01, 0x03],
-            WideAction::PcNormal => vec![0x01, 0x05],
-            WideAction::PcFull1 => vec![0x01, 0x06],
-            WideAction::PcFull2 => vec![0x01, 0x07],
+            WideAction::Toggle => [0x00].into(),
+            WideAction::WideZoom => [0x01, 0x00].into(),
+            WideAction::Full => [0x01, 0x01].into(),
+            WideAction::Zoom => [0x01, 0x02].into(),
+            WideAction::Normal => [0x01, 0x03].into(),
+            WideAction::PcNormal => [0x01, 0x05].into(),
+            WideAction::PcFull1 => [0x01, 0x06].into(),
+            WideAction::PcFull2 => [0x01, 0x07].into(),
         }
     }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
-
-    fn supports_query() -> bool {
-        false
-    }
 }
 
 // === Auto Wide (0x45) ===
@@ -167,26 +139,19 @@ pub enum AutoWideAction {
 pub struct AutoWide;
 
 impl Command for AutoWide {
-    type Action = AutoWideAction;
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x45;
+}
+
+impl ControlCommand for AutoWide {
+    type Action = AutoWideAction;
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            AutoWideAction::Toggle => vec![0x00],
-            AutoWideAction::Off => vec![0x01, 0x00],
-            AutoWideAction::On => vec![0x01, 0x01],
+            AutoWideAction::Toggle => [0x00].into(),
+            AutoWideAction::Off => [0x01, 0x00].into(),
+            AutoWideAction::On => [0x01, 0x01].into(),
         }
     }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
-
-    fn supports_query() -> bool {
-        false
-    }
 }
 
 // === 4:3 Mode (0x46) ===
@@ -202,27 +167,20 @@ pub enum FourThreeModeAction {
 pub struct FourThreeMode;
 
 impl Command for FourThreeMode {
-    type Action = FourThreeModeAction;
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x46;
+}
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+impl ControlCommand for FourThreeMode {
+    type Action = FourThreeModeAction;
+
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            FourThreeModeAction::Toggle => vec![0x00],
-            FourThreeModeAction::Off => vec![0x01, 0x00],
-            FourThreeModeAction::WideZoom => vec![0x01, 0x03],
-            FourThreeModeAction::Normal => vec![0x01, 0x04],
+            FourThreeModeAction::Toggle => [0x00].into(),
+            FourThreeModeAction::Off => [0x01, 0x00].into(),
+            FourThreeModeAction::WideZoom => [0x01, 0x03].into(),
+            FourThreeModeAction::Normal => [0x01, 0x04].into(),
         }
     }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
-
-    fn supports_query() -> bool {
-        false
-    }
 }
 
 #[cfg(test)]
@@ -230,24 +188,21 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_wide_build_bytes() {
-        assert_eq!(Wide::build_bytes(&WideAction::Toggle), vec![0x00]);
-        assert_eq!(Wide::build_bytes(&WideAction::Full), vec![0x01, 0x01]);
-        assert_eq!(Wide::build_bytes(&WideAction::Normal), vec![0x01, 0x03]);
+    fn test_wide_encode() {
+        assert_eq!(Wide::encode(&WideAction::Toggle), [0x00]);
+        assert_eq!(Wide::encode(&WideAction::Full), [0x01, 0x01]);
+        assert_eq!(Wide::encode(&WideAction::Normal), [0x01, 0x03]);
     }
 
     #[test]
-    fn test_auto_wide_build_bytes() {
-        assert_eq!(AutoWide::build_bytes(&AutoWideAction::Toggle), vec![0x00]);
-        assert_eq!(AutoWide::build_bytes(&AutoWideAction::On), vec![0x01, 0x01]);
+    fn test_auto_wide_encode() {
+        assert_eq!(AutoWide::encode(&AutoWideAction::Toggle), [0x00]);
+        assert_eq!(AutoWide::encode(&AutoWideAction::On), [0x01, 0x01]);
     }
 
     #[test]
-    fn test_h_shift_build_bytes() {
-        assert_eq!(HShift::build_bytes(&HShiftAction::Up), vec![0x00, 0x00]);
-        assert_eq!(
-            HShift::build_bytes(&HShiftAction::SetPlus(10)),
-            vec![0x01, 0x00, 10]
-        );
+    fn test_h_shift_encode() {
+        assert_eq!(HShift::encode(&HShiftAction::Up), [0x00, 0x00]);
+        assert_eq!(HShift::encode(&HShiftAction::SetPlus(10)), [0x01, 0x00, 10]);
     }
 }
@@ -1,28 +1,21 @@
 // src/protocol/signage.rs
 #![allow(dead_code)]
 
-use crate::protocol::{Command, ProtocolError};
+use crate::protocol::{Command, ProtocolError, QueryCommand, ResponseData};
 
 // === Product Info 1 (0x6E) ===
 
 pub struct ProductInfo1;
 
 impl Command for ProductInfo1 {
-    type Action = ();
-    type Response = Vec<u8>;
-
     const FUNCTION_CODE: u8 = 0x6E;
+}
 
-    fn build_bytes(_action: &Self::Action) -> Vec<u8> {
-        vec![]
-    }
+impl QueryCommand for ProductInfo1 {
+    type Response = ResponseData;
 
     fn parse_response(bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(bytes.to_vec())
-    }
-
-    fn supports_query() -> bool {
-        true
+        ResponseData::from_slice(bytes)
     }
 }
 
@@ -31,21 +24,14 @@ impl Command for ProductInfo1 {
 pub struct ProductInfo2;
 
 impl Command for ProductInfo2 {
-    type Action = ();
-    type Response = Vec<u8>;
-
     const FUNCTION_CODE: u8 = 0x6D;
+}
 
-    fn build_bytes(_action: &Self::Action) -> Vec<u8> {
-        vec![]
-    }
+impl QueryCommand for ProductInfo2 {
+    type Response = ResponseData;
 
     fn parse_response(bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(bytes.to_vec())
-    }
-
-    fn supports_query() -> bool {
-        true
+        ResponseData::from_slice(bytes)
     }
 }
 
@@ -54,21 +40,14 @@ impl Command for ProductInfo2 {
 pub struct ProductInfo3;
 
 impl Command for ProductInfo3 {
-    type Action = ();
-    type Response = Vec<u8>;
-
     const FUNCTION_CODE: u8 = 0x6C;
+}
 
-    fn build_bytes(_action: &Self::Action) -> Vec<u8> {
-        vec![]
-    }
+impl QueryCommand for ProductInfo3 {
+    type Response = ResponseData;
 
     fn parse_response(bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(bytes.to_vec())
-    }
-
-    fn supports_query() -> bool {
-        true
+        ResponseData::from_slice(bytes)
     }
 }
 
@@ -77,20 +56,13 @@ impl Command for ProductInfo3 {
 pub struct IdCommand;
 
 impl Command for IdCommand {
-    type Action = ();
-    type Response = Vec<u8>;
-
     const FUNCTION_CODE: u8 = 0x6F;
+}
 
-    fn build_bytes(_action: &Self::Action) -> Vec<u8> {
-        vec![]
-    }
+impl QueryCommand for IdCommand {
+    type Response = ResponseData;
 
     fn parse_response(bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(bytes.to_vec())
-    }
-
-    fn supports_query() -> bool {
-        true
+        ResponseData::from_slice(bytes)
     }
 }
@@ -1,5 +1,5 @@
 // src/protocol/sircs.rs
-use crate::protocol::{Command, ProtocolError};
+use crate::protocol::{ActionData, Command, ControlCommand};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SircsButton {
@@ -36,6 +36,39 @@ pub enum SircsButton {
 }
 
 impl SircsButton {
+    pub const ALL: [SircsButton; 30] = [
+        SircsButton::Input,
+        SircsButton::Power,
+        SircsButton::WideMode,
+        SircsButton::Dot,
+        SircsButton::Display,
+        SircsButton::Return,
+        SircsButton::Options,
+        SircsButton::Home,
+        SircsButton::CursorUp,
+        SircsButton::CursorDown,
+        SircsButton::CursorLeft,
+        SircsButton::CursorRight,
+        SircsButton::Select,
+        SircsButton::Num1,
+        SircsButton::Num2,
+        SircsButton::Num3,
+        SircsButton::Num4,
+        SircsButton::Num5,
+        SircsButton::Num6,
+        SircsButton::Num7,
+        SircsButton::Num8,
+        SircsButton::Num9,
+        SircsButton::Num0,
+        SircsButton::ClosedCaption,
+        SircsButton::VolumeUp,
+        SircsButton::VolumeDown,
+        SircsButton::Muting,
+        SircsButton::ChannelUp,
+        SircsButton::ChannelDown,
+        SircsButton::Jump,
+    ];
+
     pub fn codes(&self) -> (u8, u8) {
         match self {
             SircsButton::Input => (0x01, 0x25),
@@ -75,22 +108,15 @@ impl SircsButton {
 pub struct Sircs;
 
 impl Command for Sircs {
-    type Action = SircsButton;
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x67;
+}
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
-        let (category, data) = action.codes();
-        vec![category, data]
-    }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
+impl ControlCommand for Sircs {
+    type Action = SircsButton;
 
-    fn supports_query() -> bool {
-        false
+    fn encode(action: &Self::Action) -> ActionData {
+        let (category, data) = action.codes();
+        [category, data].into()
     }
 }
 
@@ -99,11 +125,11 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_sircs_build_bytes() {
-        assert_eq!(Sircs::build_bytes(&SircsButton::Power), vec![0x01, 0x15]);
-        assert_eq!(Sircs::build_bytes(&SircsButton::Home), vec![0x01, 0x60]);
-        assert_eq!(Sircs::build_bytes(&SircsButton::Select), vec![0x01, 0x65]);
-        assert_eq!(Sircs::build_bytes(&SircsButton::Num1), vec![0x01, 0x00]);
-        assert_eq!(Sircs::build_bytes(&SircsButton::WideMode), vec![0xA4, 0x3D]);
+    fn test_sircs_encode() {
+        assert_eq!(Sircs::encode(&SircsButton::Power), [0x01, 0x15]);
+        assert_eq!(Sircs::encode(&SircsButton::Home), [0x01, 0x60]);
+        assert_eq!(Sircs::encode(&SircsButton::Select), [0x01, 0x65]);
+        assert_eq!(Sircs::encode(&SircsButton::Num1), [0x01, 0x00]);
+        assert_eq!(Sircs::encode(&SircsButton::WideMode), [0xA4, 0x3D]);
     }
 }
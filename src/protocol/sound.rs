@@ -1,5 +1,5 @@
 // src/protocol/sound.rs
-use crate::protocol::{Command, ProtocolError};
+use crate::protocol::{ActionData, Command, ControlCommand};
 
 // === Sound Mode (0x30) ===
 
@@ -16,29 +16,22 @@ pub enum SoundModeAction {
 pub struct SoundMode;
 
 impl Command for SoundMode {
-    type Action = SoundModeAction;
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x30;
+}
+
+impl ControlCommand for SoundMode {
+    type Action = SoundModeAction;
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            SoundModeAction::Toggle => vec![0x00],
-            SoundModeAction::Standard => vec![0x01, 0x01],
-            SoundModeAction::Cinema => vec![0x01, 0x04],
-            SoundModeAction::Sports => vec![0x01, 0x05],
-            SoundModeAction::Music => vec![0x01, 0x06],
-            SoundModeAction::Game => vec![0x01, 0x07],
+            SoundModeAction::Toggle => [0x00].into(),
+            SoundModeAction::Standard => [0x01, 0x01].into(),
+            SoundModeAction::Cinema => [0x01, 0x04].into(),
+            SoundModeAction::Sports => [0x01, 0x05].into(),
+            SoundModeAction::Music => [0x01, 0x06].into(),
+            SoundModeAction::Game => [0x01, 0x07].into(),
         }
     }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
-
-    fn supports_query() -> bool {
-        false
-    }
 }
 
 // === Speaker Off (0x36) ===
@@ -53,26 +46,19 @@ pub enum SpeakerAction {
 pub struct Speaker;
 
 impl Command for Speaker {
-    type Action = SpeakerAction;
-    type Response = ();
-
     const FUNCTION_CODE: u8 = 0x36;
+}
+
+impl ControlCommand for Speaker {
+    type Action = SpeakerAction;
 
-    fn build_bytes(action: &Self::Action) -> Vec<u8> {
+    fn encode(action: &Self::Action) -> ActionData {
         match action {
-            SpeakerAction::Toggle => vec![0x00],
-            SpeakerAction::On => vec![0x01, 0x00], // Speaker OFF = Off means speakers ON
-            SpeakerAction::Off => vec![0x01, 0x01], // Speaker OFF = On means speakers OFF
+            SpeakerAction::Toggle => [0x00].into(),
+            SpeakerAction::On => [0x01, 0x00].into(), // Speaker OFF = Off means speakers ON
+            SpeakerAction::Off => [0x01, 0x01].into(), // Speaker OFF = On means speakers OFF
         }
     }
-
-    fn parse_response(_bytes: &[u8]) -> Result<Self::Response, ProtocolError> {
-        Ok(())
-    }
-
-    fn supports_query() -> bool {
-        false
-    }
 }
 
 #[cfg(test)]
@@ -80,26 +66,17 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_sound_mode_build_bytes() {
-        assert_eq!(SoundMode::build_bytes(&SoundModeAction::Toggle), vec![0x00]);
-        assert_eq!(
-            SoundMode::build_bytes(&SoundModeAction::Standard),
-            vec![0x01, 0x01]
-        );
-        assert_eq!(
-            SoundMode::build_bytes(&SoundModeAction::Cinema),
-            vec![0x01, 0x04]
-        );
-        assert_eq!(
-            SoundMode::build_bytes(&SoundModeAction::Game),
-            vec![0x01, 0x07]
-        );
+    fn test_sound_mode_encode() {
+        assert_eq!(SoundMode::encode(&SoundModeAction::Toggle), [0x00]);
+        assert_eq!(SoundMode::encode(&SoundModeAction::Standard), [0x01, 0x01]);
+        assert_eq!(SoundMode::encode(&SoundModeAction::Cinema), [0x01, 0x04]);
+        assert_eq!(SoundMode::encode(&SoundModeAction::Game), [0x01, 0x07]);
     }
 
     #[test]
-    fn test_speaker_build_bytes() {
-        assert_eq!(Speaker::build_bytes(&SpeakerAction::Toggle), vec![0x00]);
-        assert_eq!(Speaker::build_bytes(&SpeakerAction::On), vec![0x01, 0x00]);
-        assert_eq!(Speaker::build_bytes(&SpeakerAction::Off), vec![0x01, 0x01]);
+    fn test_speaker_encode() {
+        assert_eq!(Speaker::encode(&SpeakerAction::Toggle), [0x00]);
+        assert_eq!(Speaker::encode(&SpeakerAction::On), [0x01, 0x00]);
+        assert_eq!(Speaker::encode(&SpeakerAction::Off), [0x01, 0x01]);
     }
 }
@@ -12,6 +12,7 @@ fn test_checksum_wraps() {
     assert_eq!(checksum(&[0xFF, 0xFF]), 0xFE);
 }
 
+#[cfg(feature = "alloc")]
 #[test]
 fn test_build_control_packet_power_on() {
     let packet = build_control_packet(0x00, &[0x01]);
@@ -24,6 +25,7 @@ fn test_build_control_packet_power_on() {
     assert_eq!(packet[5], checksum(&packet[0..5]));
 }
 
+#[cfg(feature = "alloc")]
 #[test]
 fn test_build_query_packet_power() {
     let packet = build_query_packet(0x00);
@@ -35,6 +37,24 @@ fn test_build_query_packet_power() {
     assert_eq!(packet[5], checksum(&packet[0..5]));
 }
 
+#[cfg(feature = "alloc")]
+#[test]
+fn test_vec_api_matches_fixed_capacity_packets() {
+    let action = VolumeAction::Set(VolumeValue::new(30).unwrap());
+    assert_eq!(
+        Volume::build_bytes(&action),
+        Volume::encode(&action).to_vec()
+    );
+    assert_eq!(
+        build_control_packet(Volume::FUNCTION_CODE, &Volume::build_bytes(&action)),
+        Volume::control_packet(&action).to_vec()
+    );
+    assert_eq!(
+        build_query_packet(Volume::FUNCTION_CODE),
+        Volume::query_packet().to_vec()
+    );
+}
+
 #[test]
 fn test_response_code_try_from() {
     assert_eq!(ResponseCode::try_from(0x00).unwrap(), ResponseCode::Success);
@@ -56,3 +76,13 @@ fn test_response_code_try_from() {
     );
     assert!(ResponseCode::try_from(0x05).is_err());
 }
+
+#[test]
+fn test_function_names_are_unique() {
+    assert_eq!(function_name(0x00), Some("Power"));
+    assert_eq!(function_name(0x67), Some("Sircs"));
+    assert_eq!(function_name(0xFE), None);
+    for (i, (code, _)) in FUNCTIONS.iter().enumerate() {
+        assert!(FUNCTIONS[i + 1..].iter().all(|(other, _)| other != code));
+    }
+}
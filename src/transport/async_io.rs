@@ -0,0 +1,186 @@
+use std::io;
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::codec::Framed;
+
+use crate::protocol::{ControlCommand, QueryCommand, ResponseCode};
+use crate::transport::codec::BraviaCodec;
+use crate::transport::{BraviaLink, TransportError};
+
+pub const RESPONSE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Async counterpart of `Transport`, framing responses with `BraviaCodec`.
+///
+/// Waiting for the TV yields to the runtime instead of blocking a worker. A
+/// rejected or malformed reply fails only its own exchange.
+pub struct AsyncTransport<S> {
+    framed: Framed<S, BraviaCodec>,
+    timeout: Duration,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncTransport<S> {
+    pub fn new(stream: S) -> Self {
+        Self {
+            framed: Framed::new(stream, BraviaCodec::new()),
+            timeout: RESPONSE_TIMEOUT,
+        }
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn get_ref(&self) -> &S {
+        self.framed.get_ref()
+    }
+
+    pub async fn send_control(&mut self, packet: &[u8]) -> Result<(), TransportError> {
+        self.exchange(packet).await?;
+        Ok(())
+    }
+
+    pub async fn send_query(&mut self, packet: &[u8]) -> Result<Vec<u8>, TransportError> {
+        self.exchange(packet).await
+    }
+
+    pub async fn execute<C: ControlCommand>(
+        &mut self,
+        action: &C::Action,
+    ) -> Result<(), TransportError> {
+        let packet = C::control_packet(action);
+        self.send_control(&packet).await
+    }
+
+    pub async fn query<C: QueryCommand>(&mut self) -> Result<C::Response, TransportError> {
+        let packet = C::query_packet();
+        let response_data = self.send_query(&packet).await?;
+        let response = C::parse_response(&response_data)?;
+        Ok(response)
+    }
+
+    /// Sends one packet and waits for its response frame.
+    async fn exchange(&mut self, packet: &[u8]) -> Result<Vec<u8>, TransportError> {
+        // Bytes left over from an earlier timed-out exchange would otherwise be
+        // taken as the answer to this packet.
+        self.framed.read_buffer_mut().clear();
+        self.framed.send(packet).await?;
+        let frame = match tokio::time::timeout(self.timeout, self.framed.next()).await {
+            Ok(Some(result)) => result??,
+            Ok(None) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+            Err(_) => return Err(TransportError::Timeout),
+        };
+        if frame.code != ResponseCode::Success {
+            return Err(TransportError::CommandRejected(frame.code));
+        }
+        Ok(frame.data.to_vec())
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send + 'static> AsyncTransport<S> {
+    /// Moves the transport onto a task of the current runtime and returns a
+    /// blocking `BraviaLink` handle to it. Must be called from within a
+    /// runtime.
+    pub fn spawn(mut self) -> FramedLink {
+        let (tx, mut rx) = mpsc::channel::<Exchange>(1);
+        tokio::spawn(async move {
+            while let Some((packet, reply)) = rx.recv().await {
+                let _ = reply.send(self.exchange(&packet).await);
+            }
+        });
+        FramedLink { tx }
+    }
+}
+
+type Exchange = (Vec<u8>, oneshot::Sender<Result<Vec<u8>, TransportError>>);
+
+/// Blocking handle to an `AsyncTransport` running on a runtime task.
+///
+/// The I/O itself happens on the runtime; only the calling thread waits. Use
+/// it from a thread outside the runtime, as `AsyncBraviaClient` does, where
+/// it can sit under `Retrying` and the other link wrappers like any link.
+pub struct FramedLink {
+    tx: mpsc::Sender<Exchange>,
+}
+
+impl FramedLink {
+    fn exchange(&mut self, packet: &[u8]) -> Result<Vec<u8>, TransportError> {
+        let stopped = || io::Error::new(io::ErrorKind::BrokenPipe, "async transport task stopped");
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.tx
+            .blocking_send((packet.to_vec(), reply_tx))
+            .map_err(|_| stopped())?;
+        reply_rx.blocking_recv().map_err(|_| stopped())?
+    }
+}
+
+impl BraviaLink for FramedLink {
+    fn send_control(&mut self, packet: &[u8]) -> Result<(), TransportError> {
+        self.exchange(packet)?;
+        Ok(())
+    }
+
+    fn send_query(&mut self, packet: &[u8]) -> Result<Vec<u8>, TransportError> {
+        self.exchange(packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, duplex};
+
+    #[tokio::test]
+    async fn test_async_query_round_trip() {
+        let (client, mut tv) = duplex(64);
+        let mut transport = AsyncTransport::new(client);
+        let tv_task = tokio::spawn(async move {
+            let mut packet = [0u8; 6];
+            tv.read_exact(&mut packet).await.unwrap();
+            tv.write_all(&[0x70, 0x00, 0x02, 0x01, 0x73]).await.unwrap();
+            packet
+        });
+        assert_eq!(transport.query::<Power>().await.unwrap(), PowerState::On);
+        assert_eq!(tv_task.await.unwrap().to_vec(), build_query_packet(0x00));
+    }
+
+    #[tokio::test]
+    async fn test_async_execute_timeout() {
+        let (client, _tv) = duplex(64);
+        let mut transport = AsyncTransport::new(client).timeout(Duration::from_millis(20));
+        assert!(matches!(
+            transport.execute::<Power>(&PowerAction::Off).await,
+            Err(TransportError::Timeout)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_next_request_after_rejection() {
+        let (client, mut tv) = duplex(64);
+        let mut transport = AsyncTransport::new(client);
+        tokio::spawn(async move {
+            let mut packet = [0u8; 6];
+            for reply in [
+                &[0x70, 0x01, 0x71][..],
+                &[0x70, 0x00, 0x71],
+                &[0x70, 0x00, 0x70],
+            ] {
+                tv.read_exact(&mut packet).await.unwrap();
+                tv.write_all(reply).await.unwrap();
+            }
+        });
+        assert!(matches!(
+            transport.execute::<Power>(&PowerAction::On).await,
+            Err(TransportError::CommandRejected(ResponseCode::LimitOverMax))
+        ));
+        assert!(matches!(
+            transport.execute::<Power>(&PowerAction::On).await,
+            Err(TransportError::InvalidChecksum)
+        ));
+        transport.execute::<Power>(&PowerAction::On).await.unwrap();
+    }
+}
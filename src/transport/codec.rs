@@ -0,0 +1,140 @@
+use tokio_util::bytes::{BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::protocol::frame::ResponseFrame;
+use crate::protocol::{
+    CONTROL_HEADER, FrameError, FrameParser, ProtocolError, QUERY_HEADER, ResponseKind,
+};
+use crate::transport::TransportError;
+
+/// Frames Bravia RS-232C traffic for `tokio_util::codec::Framed`.
+///
+/// The wire format does not say whether a response is an acknowledgement or
+/// carries data, so the encoder remembers what was last sent: control packets
+/// expect a 3-byte ack, query packets a length-prefixed data frame.
+///
+/// Every response is an item, rejections included, and so is a malformed
+/// frame; decoding resumes at the next header as in `FrameParser`. Only I/O
+/// errors end the stream.
+#[derive(Debug, Default)]
+pub struct BraviaCodec {
+    parser: FrameParser,
+}
+
+impl BraviaCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Encoder<&[u8]> for BraviaCodec {
+    type Error = TransportError;
+
+    fn encode(&mut self, packet: &[u8], dst: &mut BytesMut) -> Result<(), Self::Error> {
+        if !matches!(packet.first(), Some(&CONTROL_HEADER | &QUERY_HEADER)) {
+            return Err(ProtocolError::InvalidResponse.into());
+        }
+        self.parser.reset(ResponseKind::for_packet(packet));
+        dst.reserve(packet.len());
+        dst.put_slice(packet);
+        Ok(())
+    }
+}
+
+impl Decoder for BraviaCodec {
+    type Item = Result<ResponseFrame, FrameError>;
+    type Error = TransportError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.parser.push(src);
+        src.clear();
+        Ok(self.parser.next_frame())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{ResponseCode, ResponseData, build_control_packet, build_query_packet};
+
+    fn decode_after(packet: &[u8], input: &[u8]) -> (BraviaCodec, BytesMut) {
+        let mut codec = BraviaCodec::new();
+        let mut out = BytesMut::new();
+        codec.encode(packet, &mut out).unwrap();
+        assert_eq!(&out[..], packet);
+        (codec, BytesMut::from(input))
+    }
+
+    fn frame(code: ResponseCode, data: &[u8]) -> Option<Result<ResponseFrame, FrameError>> {
+        let data = ResponseData::from_slice(data).unwrap();
+        Some(Ok(ResponseFrame { code, data }))
+    }
+
+    #[test]
+    fn test_decode_ack() {
+        let (mut codec, mut src) =
+            decode_after(&build_control_packet(0x00, &[0x01]), &[0x70, 0x00, 0x70]);
+        assert_eq!(
+            codec.decode(&mut src).unwrap(),
+            frame(ResponseCode::Success, &[])
+        );
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn test_decode_data_in_pieces() {
+        let (mut codec, mut src) = decode_after(&build_query_packet(0x05), &[0x70, 0x00]);
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        src.extend_from_slice(&[0x02, 0x1E]);
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        src.extend_from_slice(&[0x90]);
+        assert_eq!(
+            codec.decode(&mut src).unwrap(),
+            frame(ResponseCode::Success, &[0x1E])
+        );
+    }
+
+    #[test]
+    fn test_decode_query_rejection_is_a_short_frame() {
+        let (mut codec, mut src) = decode_after(&build_query_packet(0x24), &[0x70, 0x03, 0x73]);
+        assert_eq!(
+            codec.decode(&mut src).unwrap(),
+            frame(ResponseCode::Canceled, &[])
+        );
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn test_decode_skips_garbage() {
+        let (mut codec, mut src) =
+            decode_after(&build_query_packet(0x00), &[0x8C, 0x00, 0x70, 0x00, 0x02]);
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        src.extend_from_slice(&[0x01, 0x73]);
+        assert_eq!(
+            codec.decode(&mut src).unwrap(),
+            frame(ResponseCode::Success, &[0x01])
+        );
+    }
+
+    #[test]
+    fn test_decode_resumes_after_bad_frame() {
+        let (mut codec, mut src) = decode_after(
+            &build_control_packet(0x00, &[0x01]),
+            &[0x70, 0x00, 0x71, 0x70, 0x00, 0x70],
+        );
+        assert_eq!(
+            codec.decode(&mut src).unwrap(),
+            Some(Err(FrameError::InvalidChecksum))
+        );
+        assert_eq!(
+            codec.decode(&mut src).unwrap(),
+            frame(ResponseCode::Success, &[])
+        );
+
+        let (mut codec, mut src) = decode_after(&build_query_packet(0x00), &[0x70, 0x00, 0x00]);
+        assert_eq!(
+            codec.decode(&mut src).unwrap(),
+            Some(Err(FrameError::InvalidLength(0)))
+        );
+    }
+}
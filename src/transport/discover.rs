@@ -0,0 +1,196 @@
+use std::time::Duration;
+
+use crate::protocol::{IdCommand, Power, PowerState, ProductInfo1};
+use crate::transport::{BraviaLink, Port, SerialSettings, Transport, TransportError, serial};
+
+/// Read timeout used while probing, kept short so silent ports are skipped
+/// quickly.
+pub const PROBE_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// What a responding TV said when probed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Probe {
+    /// `None` if the TV answered the power query with a rejection frame.
+    pub power: Option<PowerState>,
+    pub product_info: Option<Vec<u8>>,
+    pub id: Option<Vec<u8>>,
+}
+
+/// A serial port and baud rate at which a TV answered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Found {
+    pub port: String,
+    pub baud_rate: u32,
+    pub probe: Probe,
+}
+
+/// Queries `Power` and, if the link answers with any response frame, the
+/// product info and ID. Returns `None` if nothing recognizable came back.
+pub fn probe<L: BraviaLink>(link: &mut L) -> Option<Probe> {
+    let power = match link.query::<Power>() {
+        Ok(state) => Some(state),
+        // A rejection is still a well-formed frame from a TV.
+        Err(TransportError::CommandRejected(_)) => None,
+        Err(_) => return None,
+    };
+    Some(Probe {
+        power,
+        product_info: link.query::<ProductInfo1>().ok().map(|d| d.to_vec()),
+        id: link.query::<IdCommand>().ok().map(|d| d.to_vec()),
+    })
+}
+
+/// Names of the serial ports the OS reports.
+pub fn available_ports() -> Result<Vec<String>, TransportError> {
+    Ok(serialport::available_ports()?
+        .into_iter()
+        .map(|p| p.port_name)
+        .collect())
+}
+
+/// Probes every port at each of `baud_rates` (or the baud rate in `settings`
+/// if empty) and returns those where a TV answered, stopping at the first
+/// rate that works for a port. Ports that cannot be opened are skipped, and
+/// so are rates a port refuses.
+pub fn discover(ports: &[String], baud_rates: &[u32], settings: &SerialSettings) -> Vec<Found> {
+    discover_with(ports, baud_rates, settings, serial::open_port)
+}
+
+fn discover_with<P: Port>(
+    ports: &[String],
+    baud_rates: &[u32],
+    settings: &SerialSettings,
+    mut open: impl FnMut(&str, &SerialSettings) -> Result<P, TransportError>,
+) -> Vec<Found> {
+    let rates = if baud_rates.is_empty() {
+        vec![settings.line.baud_rate]
+    } else {
+        baud_rates.to_vec()
+    };
+    let mut found = Vec::new();
+    for port in ports {
+        for &baud_rate in &rates {
+            let mut settings = *settings;
+            settings.line.baud_rate = baud_rate;
+            settings.read_timeout = settings.read_timeout.or(Some(PROBE_TIMEOUT));
+            let serial = match open(port, &settings) {
+                Ok(serial) => serial,
+                // The driver refused this rate; the next one may still work.
+                Err(TransportError::Setting { .. }) => continue,
+                Err(_) => break,
+            };
+            if let Some(probe) = probe(&mut Transport::from_port(serial)) {
+                found.push(Found {
+                    port: port.clone(),
+                    baud_rate,
+                    probe,
+                });
+                break;
+            }
+        }
+    }
+    found
+}
+
+/// Resolves `--dev auto`: the one port where a TV answered.
+pub fn auto(baud_rates: &[u32], settings: &SerialSettings) -> Result<Found, TransportError> {
+    let mut found = discover(&available_ports()?, baud_rates, settings);
+    match found.len() {
+        1 => Ok(found.remove(0)),
+        0 => Err(TransportError::Discovery(
+            "no TV answered on any serial port".to_string(),
+        )),
+        _ => Err(TransportError::Discovery(format!(
+            "TVs answered on several ports ({}); pass --dev",
+            found
+                .iter()
+                .map(|f| f.port.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::ResponseCode;
+    use crate::transport::mock::{MockPort, MockTransport};
+    use std::io;
+
+    #[test]
+    fn test_probe_collects_info() {
+        let mut link = MockTransport::mock();
+        link.port_mut().push_data(&[0x01]);
+        link.port_mut().push_data(b"KD-55");
+        link.port_mut().push_ack(ResponseCode::Canceled);
+        assert_eq!(
+            probe(&mut link),
+            Some(Probe {
+                power: Some(PowerState::On),
+                product_info: Some(b"KD-55".to_vec()),
+                id: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_probe_accepts_rejection_frame() {
+        let mut link = MockTransport::mock();
+        link.port_mut().push_ack(ResponseCode::Canceled);
+        let probe = probe(&mut link).unwrap();
+        assert_eq!(probe.power, None);
+    }
+
+    #[test]
+    fn test_probe_ignores_silence_and_noise() {
+        let mut link = MockTransport::mock();
+        assert_eq!(probe(&mut link), None);
+
+        link.port_mut().push_raw(&[0x70, 0x00, 0x02, 0x01, 0x00]);
+        assert_eq!(probe(&mut link), None);
+        assert_eq!(link.port().sent().len(), 2);
+    }
+
+    #[test]
+    fn test_refused_rate_moves_on_to_the_next() {
+        let mut opened = Vec::new();
+        let found = discover_with(
+            &["/dev/ttyUSB0".to_string()],
+            &[19200, 9600],
+            &SerialSettings::default(),
+            |_, settings| {
+                opened.push(settings.line.baud_rate);
+                if settings.line.baud_rate != 9600 {
+                    return Err(TransportError::Setting {
+                        setting: "baud",
+                        reason: "not supported".to_string(),
+                    });
+                }
+                let mut port = MockPort::new();
+                port.push_data(&[0x01]);
+                Ok(port)
+            },
+        );
+        assert_eq!(opened, [19200, 9600]);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].baud_rate, 9600);
+        assert_eq!(found[0].probe.power, Some(PowerState::On));
+    }
+
+    #[test]
+    fn test_missing_port_is_not_retried() {
+        let mut calls = 0;
+        let found = discover_with(
+            &["/dev/ttyUSB0".to_string()],
+            &[19200, 9600],
+            &SerialSettings::default(),
+            |_, _| -> Result<MockPort, _> {
+                calls += 1;
+                Err(io::Error::from(io::ErrorKind::NotFound).into())
+            },
+        );
+        assert!(found.is_empty());
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,84 @@
+use std::io::Write;
+
+use crate::protocol::decode::hex;
+use crate::transport::trace::describe_packet;
+use crate::transport::{BraviaLink, TransportError};
+
+/// Link that prints each packet instead of sending it.
+///
+/// Controls succeed without a TV; queries fail with `DryRun` since there is
+/// nothing to answer them.
+pub struct DryRun<W> {
+    out: W,
+    sent: Vec<Vec<u8>>,
+}
+
+impl<W: Write> DryRun<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            sent: Vec::new(),
+        }
+    }
+
+    /// Every packet printed so far, in order.
+    pub fn sent(&self) -> &[Vec<u8>] {
+        &self.sent
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn print(&mut self, packet: &[u8]) -> Result<(), TransportError> {
+        writeln!(
+            self.out,
+            "dry-run: {}  # {}",
+            hex(packet),
+            describe_packet(packet)
+        )?;
+        self.out.flush()?;
+        self.sent.push(packet.to_vec());
+        Ok(())
+    }
+}
+
+impl<W: Write> BraviaLink for DryRun<W> {
+    fn send_control(&mut self, packet: &[u8]) -> Result<(), TransportError> {
+        self.print(packet)
+    }
+
+    fn send_query(&mut self, packet: &[u8]) -> Result<Vec<u8>, TransportError> {
+        self.print(packet)?;
+        Err(TransportError::DryRun)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::*;
+
+    #[test]
+    fn test_prints_controls_and_refuses_queries() {
+        let mut link = DryRun::new(Vec::new());
+        link.execute::<Power>(&PowerAction::On).unwrap();
+        assert!(matches!(
+            link.query::<Volume>(),
+            Err(TransportError::DryRun)
+        ));
+        assert_eq!(
+            link.sent(),
+            &[
+                build_control_packet(0x00, &[0x01]),
+                build_query_packet(0x05)
+            ]
+        );
+        let out = String::from_utf8(link.into_inner()).unwrap();
+        assert_eq!(
+            out,
+            "dry-run: 8C 00 00 02 01 8F  # control Power\n\
+             dry-run: 83 00 05 FF FF 86  # query Volume\n"
+        );
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::thread;
+use std::time::Duration;
+
+use crate::protocol::frame::{Parsed, parse_frame};
+use crate::protocol::{RESPONSE_HEADER, ResponseKind};
+use crate::transport::Port;
+
+/// Which faults to inject and how often.
+///
+/// Each probability is checked once per response frame, except `drop_byte`,
+/// which is checked for every byte. The same seed always injects the same
+/// faults into the same traffic.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FaultConfig {
+    pub seed: u64,
+    /// Chance that a received byte is lost.
+    pub drop_byte: f64,
+    /// Chance that a frame's checksum byte is altered.
+    pub corrupt_checksum: f64,
+    /// Chance that a frame's header is replaced by another byte.
+    pub wrong_header: f64,
+    /// Chance that a frame is held back for `delay_by` before it is readable.
+    pub delay: f64,
+    pub delay_by: Duration,
+    /// Chance that a frame is cut short and the rest never arrives.
+    pub partial_frame: f64,
+    /// Chance that an ack arrives again, after the next packet is sent.
+    pub duplicate_ack: f64,
+}
+
+/// How many faults of each kind have been injected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FaultCounts {
+    pub dropped_bytes: u32,
+    pub corrupted_checksums: u32,
+    pub wrong_headers: u32,
+    pub delayed: u32,
+    pub partial_frames: u32,
+    pub duplicated_acks: u32,
+}
+
+/// Port wrapper that damages response frames on their way to the reader.
+///
+/// Incoming bytes are split into frames using the kind expected for the last
+/// packet written; bytes outside a frame, and a frame still incomplete when
+/// the inner port times out, pass through untouched.
+pub struct FaultyPort<P> {
+    inner: P,
+    config: FaultConfig,
+    rng: u64,
+    kind: ResponseKind,
+    /// Received bytes not yet split into frames.
+    raw: Vec<u8>,
+    /// Bytes ready to be read.
+    ready: VecDeque<u8>,
+    /// Duplicated acks, readable once the next packet has been written.
+    late: Vec<u8>,
+    counts: FaultCounts,
+}
+
+impl<P: Port> FaultyPort<P> {
+    pub fn new(inner: P, config: FaultConfig) -> Self {
+        Self {
+            inner,
+            config,
+            rng: config.seed | 1,
+            kind: ResponseKind::Ack,
+            raw: Vec::new(),
+            ready: VecDeque::new(),
+            late: Vec::new(),
+            counts: FaultCounts::default(),
+        }
+    }
+
+    pub fn config(&self) -> &FaultConfig {
+        &self.config
+    }
+
+    pub fn counts(&self) -> &FaultCounts {
+        &self.counts
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut P {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+
+    fn random(&mut self) -> u64 {
+        // xorshift64, as in `Retrying`: reproducible, not unpredictable.
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        // The top 53 bits give a uniform float in [0, 1).
+        let sample = (self.random() >> 11) as f64 / (1u64 << 53) as f64;
+        probability > 0.0 && sample < probability
+    }
+
+    /// Moves complete frames from `raw` to `ready`, damaging some.
+    fn split_frames(&mut self) {
+        loop {
+            let start = self
+                .raw
+                .iter()
+                .position(|&b| b == RESPONSE_HEADER)
+                .unwrap_or(self.raw.len());
+            self.ready.extend(self.raw.drain(..start));
+            if self.raw.is_empty() {
+                return;
+            }
+            let used = match parse_frame(&self.raw, self.kind) {
+                Parsed::Incomplete => return,
+                Parsed::Frame(_, used) | Parsed::Error(_, used) => used,
+            };
+            let frame: Vec<u8> = self.raw.drain(..used).collect();
+            self.inject(frame);
+        }
+    }
+
+    fn inject(&mut self, mut frame: Vec<u8>) {
+        let config = self.config;
+        if frame.len() == 3 && self.chance(config.duplicate_ack) {
+            self.late.extend_from_slice(&frame);
+            self.counts.duplicated_acks += 1;
+        }
+        if self.chance(config.wrong_header) {
+            frame[0] = RESPONSE_HEADER ^ (1 + (self.random() % 0x7F) as u8);
+            self.counts.wrong_headers += 1;
+        }
+        if self.chance(config.corrupt_checksum) {
+            let last = frame.len() - 1;
+            frame[last] ^= 1 + (self.random() % 0xFF) as u8;
+            self.counts.corrupted_checksums += 1;
+        }
+        if frame.len() > 1 && self.chance(config.partial_frame) {
+            let keep = 1 + (self.random() % (frame.len() as u64 - 1)) as usize;
+            frame.truncate(keep);
+            self.counts.partial_frames += 1;
+        }
+        if self.chance(config.delay) {
+            thread::sleep(config.delay_by);
+            self.counts.delayed += 1;
+        }
+        for byte in frame {
+            if self.chance(config.drop_byte) {
+                self.counts.dropped_bytes += 1;
+            } else {
+                self.ready.push_back(byte);
+            }
+        }
+    }
+}
+
+impl<P: Port> Read for FaultyPort<P> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut chunk = [0u8; 64];
+        while self.ready.is_empty() {
+            match self.inner.read(&mut chunk) {
+                Ok(0) if self.raw.is_empty() => return Ok(0),
+                Ok(n) if n > 0 => {
+                    self.raw.extend_from_slice(&chunk[..n]);
+                    self.split_frames();
+                }
+                // Nothing more is coming; release what is left as it is.
+                Ok(_) => self.ready.extend(self.raw.drain(..)),
+                Err(e) if self.raw.is_empty() => return Err(e),
+                Err(_) => self.ready.extend(self.raw.drain(..)),
+            }
+        }
+        let n = buf.len().min(self.ready.len());
+        for (dst, src) in buf.iter_mut().zip(self.ready.drain(..n)) {
+            *dst = src;
+        }
+        Ok(n)
+    }
+}
+
+impl<P: Port> Write for FaultyPort<P> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.kind = ResponseKind::for_packet(buf);
+        self.ready.extend(self.late.drain(..));
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<P: Port> Port for FaultyPort<P> {
+    fn discard_input(&mut self) -> io::Result<()> {
+        self.raw.clear();
+        self.ready.clear();
+        self.inner.discard_input()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::*;
+    use crate::transport::mock::MockPort;
+    use crate::transport::{BraviaLink, Transport, TransportError};
+
+    fn faulty(config: FaultConfig) -> Transport<FaultyPort<MockPort>> {
+        Transport::from_port(FaultyPort::new(MockPort::new(), config))
+    }
+
+    #[test]
+    fn test_no_faults_passes_through() {
+        let mut link = faulty(FaultConfig::default());
+        link.port_mut().inner_mut().push_data(&[0x01]);
+        assert_eq!(link.query::<Power>().unwrap(), PowerState::On);
+        assert_eq!(link.port().counts(), &FaultCounts::default());
+    }
+
+    #[test]
+    fn test_corrupt_checksum() {
+        let mut link = faulty(FaultConfig {
+            corrupt_checksum: 1.0,
+            ..FaultConfig::default()
+        });
+        link.port_mut().inner_mut().push_ack(ResponseCode::Success);
+        assert!(matches!(
+            link.execute::<Power>(&PowerAction::On),
+            Err(TransportError::InvalidChecksum)
+        ));
+    }
+
+    #[test]
+    fn test_wrong_header_and_partial_frame_time_out() {
+        for config in [
+            FaultConfig {
+                wrong_header: 1.0,
+                ..FaultConfig::default()
+            },
+            FaultConfig {
+                partial_frame: 1.0,
+                ..FaultConfig::default()
+            },
+        ] {
+            let mut link = faulty(config);
+            link.port_mut().inner_mut().push_data(&[0x01]);
+            assert!(matches!(
+                link.query::<Power>(),
+                Err(TransportError::Timeout)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_duplicate_ack_arrives_with_next_response() {
+        let mut link = faulty(FaultConfig {
+            duplicate_ack: 1.0,
+            ..FaultConfig::default()
+        });
+        let port = link.port_mut().inner_mut();
+        port.push_ack(ResponseCode::Success);
+        port.push_data(&[0x01]);
+        link.execute::<Power>(&PowerAction::On).unwrap();
+        // The stale ack is read as the start of a data frame.
+        assert!(link.query::<Power>().is_err());
+        assert_eq!(link.port().counts().duplicated_acks, 1);
+    }
+
+    #[test]
+    fn test_delay_holds_frame_back() {
+        let mut link = faulty(FaultConfig {
+            delay: 1.0,
+            delay_by: Duration::from_millis(30),
+            ..FaultConfig::default()
+        });
+        link.port_mut().inner_mut().push_ack(ResponseCode::Success);
+        let started = std::time::Instant::now();
+        link.execute::<Power>(&PowerAction::On).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(30));
+    }
+
+    #[test]
+    fn test_same_seed_same_faults() {
+        let run = |seed| {
+            let mut link = faulty(FaultConfig {
+                seed,
+                drop_byte: 0.1,
+                corrupt_checksum: 0.2,
+                ..FaultConfig::default()
+            });
+            let results: Vec<bool> = (0..50)
+                .map(|_| {
+                    link.port_mut().inner_mut().push_data(&[0x01]);
+                    link.query::<Power>().is_ok()
+                })
+                .collect();
+            (results, *link.port().counts())
+        };
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7).0, run(8).0);
+    }
+}
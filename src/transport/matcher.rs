@@ -0,0 +1,246 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serialport::{SerialPortInfo, SerialPortType};
+
+use crate::transport::TransportError;
+
+/// Picks a serial port by what is plugged in rather than by path.
+///
+/// `usb:vid=067b,pid=2303,serial=XYZ` matches USB adapter attributes (any
+/// subset of the keys; ids are hex). `by-id:<text>` matches ports whose
+/// `/dev/serial/by-id` link name, or USB manufacturer, product or serial
+/// number, contains `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceMatcher {
+    Usb {
+        vid: Option<u16>,
+        pid: Option<u16>,
+        serial: Option<String>,
+    },
+    ById(String),
+}
+
+impl DeviceMatcher {
+    /// Whether `dev` is a matcher rather than a path or URL.
+    pub fn is_matcher(dev: &str) -> bool {
+        dev.starts_with("usb:") || dev.starts_with("by-id:")
+    }
+
+    pub fn matches(&self, port: &SerialPortInfo, by_id: &[(String, PathBuf)]) -> bool {
+        let usb = match &port.port_type {
+            SerialPortType::UsbPort(usb) => Some(usb),
+            _ => None,
+        };
+        match self {
+            DeviceMatcher::Usb { vid, pid, serial } => usb.is_some_and(|usb| {
+                vid.is_none_or(|v| v == usb.vid)
+                    && pid.is_none_or(|p| p == usb.pid)
+                    && serial
+                        .as_ref()
+                        .is_none_or(|s| usb.serial_number.as_ref() == Some(s))
+            }),
+            DeviceMatcher::ById(text) => {
+                let linked = by_id.iter().any(|(name, target)| {
+                    name.contains(text.as_str()) && Path::new(&port.port_name) == target
+                });
+                let described = usb.is_some_and(|usb| {
+                    [&usb.manufacturer, &usb.product, &usb.serial_number]
+                        .into_iter()
+                        .flatten()
+                        .any(|field| field.contains(text.as_str()))
+                });
+                linked || described
+            }
+        }
+    }
+
+    /// Returns the path of the one port in `ports` that matches.
+    pub fn select(
+        &self,
+        ports: &[SerialPortInfo],
+        by_id: &[(String, PathBuf)],
+    ) -> Result<String, TransportError> {
+        let matching: Vec<&SerialPortInfo> =
+            ports.iter().filter(|p| self.matches(p, by_id)).collect();
+        match matching.as_slice() {
+            [port] => Ok(port.port_name.clone()),
+            [] => Err(TransportError::DeviceMatch(format!(
+                "no port matches; candidates: {}",
+                list(ports.iter())
+            ))),
+            several => Err(TransportError::DeviceMatch(format!(
+                "{} ports match: {}",
+                several.len(),
+                list(several.iter().copied())
+            ))),
+        }
+    }
+
+    /// Resolves the matcher against the ports present now.
+    pub fn resolve(&self) -> Result<String, TransportError> {
+        self.select(&serialport::available_ports()?, &by_id_links())
+    }
+}
+
+impl FromStr for DeviceMatcher {
+    type Err = TransportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: String| TransportError::DeviceMatch(format!("{}: {}", s, reason));
+        if let Some(text) = s.strip_prefix("by-id:") {
+            if text.is_empty() {
+                return Err(invalid("empty by-id text".to_string()));
+            }
+            return Ok(DeviceMatcher::ById(text.to_string()));
+        }
+        let Some(keys) = s.strip_prefix("usb:") else {
+            return Err(invalid("expected usb: or by-id:".to_string()));
+        };
+        let (mut vid, mut pid, mut serial) = (None, None, None);
+        for pair in keys.split(',').filter(|p| !p.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("expected key=value, got {}", pair)))?;
+            let hex = |v: &str| {
+                u16::from_str_radix(v.trim_start_matches("0x"), 16)
+                    .map_err(|_| invalid(format!("invalid {}: {}", key, v)))
+            };
+            match key {
+                "vid" => vid = Some(hex(value)?),
+                "pid" => pid = Some(hex(value)?),
+                "serial" => serial = Some(value.to_string()),
+                _ => return Err(invalid(format!("unknown key {}", key))),
+            }
+        }
+        if vid.is_none() && pid.is_none() && serial.is_none() {
+            return Err(invalid("give at least one of vid, pid, serial".to_string()));
+        }
+        Ok(DeviceMatcher::Usb { vid, pid, serial })
+    }
+}
+
+/// One-line description of a port, with its USB attributes if it has any.
+pub fn describe(port: &SerialPortInfo) -> String {
+    match &port.port_type {
+        SerialPortType::UsbPort(usb) => {
+            let mut s = format!(
+                "{} (usb vid={:04x} pid={:04x}",
+                port.port_name, usb.vid, usb.pid
+            );
+            if let Some(serial) = &usb.serial_number {
+                s.push_str(&format!(" serial={}", serial));
+            }
+            for name in [&usb.manufacturer, &usb.product].into_iter().flatten() {
+                s.push_str(&format!(" {}", name));
+            }
+            s.push(')');
+            s
+        }
+        _ => port.port_name.clone(),
+    }
+}
+
+fn list<'a>(ports: impl Iterator<Item = &'a SerialPortInfo>) -> String {
+    let names: Vec<String> = ports.map(describe).collect();
+    if names.is_empty() {
+        "none".to_string()
+    } else {
+        names.join(", ")
+    }
+}
+
+/// `/dev/serial/by-id` link names and the device each points to. Empty where
+/// the directory does not exist.
+pub fn by_id_links() -> Vec<(String, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir("/dev/serial/by-id") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let target = std::fs::canonicalize(entry.path()).ok()?;
+            Some((entry.file_name().to_string_lossy().into_owned(), target))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serialport::UsbPortInfo;
+
+    fn usb_port(name: &str, vid: u16, pid: u16, serial: &str) -> SerialPortInfo {
+        SerialPortInfo {
+            port_name: name.to_string(),
+            port_type: SerialPortType::UsbPort(UsbPortInfo {
+                vid,
+                pid,
+                serial_number: Some(serial.to_string()),
+                manufacturer: Some("Prolific".to_string()),
+                product: None,
+            }),
+        }
+    }
+
+    fn ports() -> Vec<SerialPortInfo> {
+        vec![
+            usb_port("/dev/ttyUSB0", 0x067b, 0x2303, "A1"),
+            usb_port("/dev/ttyUSB1", 0x067b, 0x2303, "B2"),
+            SerialPortInfo {
+                port_name: "/dev/ttyS0".to_string(),
+                port_type: SerialPortType::Unknown,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "usb:vid=067b,pid=0x2303,serial=XYZ"
+                .parse::<DeviceMatcher>()
+                .unwrap(),
+            DeviceMatcher::Usb {
+                vid: Some(0x067b),
+                pid: Some(0x2303),
+                serial: Some("XYZ".to_string()),
+            }
+        );
+        assert_eq!(
+            "by-id:FTDI".parse::<DeviceMatcher>().unwrap(),
+            DeviceMatcher::ById("FTDI".to_string())
+        );
+        assert!("usb:".parse::<DeviceMatcher>().is_err());
+        assert!("usb:vid=zz".parse::<DeviceMatcher>().is_err());
+        assert!("usb:bus=1".parse::<DeviceMatcher>().is_err());
+    }
+
+    #[test]
+    fn test_select_by_usb_attributes() {
+        let matcher: DeviceMatcher = "usb:vid=067b,serial=B2".parse().unwrap();
+        assert_eq!(matcher.select(&ports(), &[]).unwrap(), "/dev/ttyUSB1");
+    }
+
+    #[test]
+    fn test_select_by_id_link() {
+        let links = vec![(
+            "usb-Prolific_USB-Serial_Controller_B2-if00-port0".to_string(),
+            PathBuf::from("/dev/ttyUSB1"),
+        )];
+        let matcher: DeviceMatcher = "by-id:Controller_B2".parse().unwrap();
+        assert_eq!(matcher.select(&ports(), &links).unwrap(), "/dev/ttyUSB1");
+    }
+
+    #[test]
+    fn test_zero_or_several_matches_list_candidates() {
+        let matcher: DeviceMatcher = "usb:pid=2303".parse().unwrap();
+        let err = matcher.select(&ports(), &[]).unwrap_err().to_string();
+        assert!(err.contains("2 ports match"), "{}", err);
+        assert!(err.contains("/dev/ttyUSB0 (usb vid=067b pid=2303 serial=A1 Prolific)"));
+
+        let matcher: DeviceMatcher = "usb:vid=0403".parse().unwrap();
+        let err = matcher.select(&ports(), &[]).unwrap_err().to_string();
+        assert!(err.contains("no port matches"), "{}", err);
+        assert!(err.contains("/dev/ttyS0"));
+    }
+}
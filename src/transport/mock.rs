@@ -0,0 +1,171 @@
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+
+use crate::protocol::{RESPONSE_HEADER, ResponseCode, checksum};
+use crate::transport::{Port, Transport};
+
+/// In-memory port that records every packet written and plays back scripted
+/// responses, one per packet. Reading past the end of the script behaves like
+/// a silent TV and times out.
+#[derive(Debug, Default)]
+pub struct MockPort {
+    sent: Vec<Vec<u8>>,
+    responses: VecDeque<Vec<u8>>,
+    rx: VecDeque<u8>,
+}
+
+pub type MockTransport = Transport<MockPort>;
+
+impl MockPort {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Packets written so far, one entry per write.
+    pub fn sent(&self) -> &[Vec<u8>] {
+        &self.sent
+    }
+
+    /// Queues raw bytes to be sent in reply to the next unanswered packet.
+    pub fn push_raw(&mut self, bytes: &[u8]) {
+        self.responses.push_back(bytes.to_vec());
+    }
+
+    /// Queues a 3-byte acknowledgement with the given response code.
+    pub fn push_ack(&mut self, code: ResponseCode) {
+        let mut frame = vec![RESPONSE_HEADER, code as u8];
+        frame.push(checksum(&frame));
+        self.push_raw(&frame);
+    }
+
+    /// Queues a successful query response carrying `data`.
+    pub fn push_data(&mut self, data: &[u8]) {
+        let mut frame = vec![
+            RESPONSE_HEADER,
+            ResponseCode::Success as u8,
+            (data.len() + 1) as u8,
+        ];
+        frame.extend_from_slice(data);
+        frame.push(checksum(&frame));
+        self.push_raw(&frame);
+    }
+
+    /// Lets the next packet go unanswered, so reading it times out.
+    pub fn push_silence(&mut self) {
+        self.push_raw(&[]);
+    }
+
+    /// Makes bytes readable right away, as if they had arrived unprompted.
+    pub fn push_stale(&mut self, bytes: &[u8]) {
+        self.rx.extend(bytes);
+    }
+
+    /// Number of bytes readable now plus scripted responses not yet released.
+    pub fn pending(&self) -> usize {
+        self.rx.len() + self.responses.iter().map(Vec::len).sum::<usize>()
+    }
+}
+
+impl Read for MockPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.rx.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "no scripted response",
+            ));
+        }
+        let n = buf.len().min(self.rx.len());
+        for (dst, src) in buf.iter_mut().zip(self.rx.drain(..n)) {
+            *dst = src;
+        }
+        Ok(n)
+    }
+}
+
+impl Write for MockPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.sent.push(buf.to_vec());
+        if let Some(response) = self.responses.pop_front() {
+            self.rx.extend(response);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Port for MockPort {
+    fn discard_input(&mut self) -> io::Result<()> {
+        self.rx.clear();
+        Ok(())
+    }
+}
+
+impl MockTransport {
+    pub fn mock() -> Self {
+        Self::from_port(MockPort::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::*;
+    use crate::transport::{BraviaLink, TransportError};
+
+    #[test]
+    fn test_execute_records_packet() {
+        let mut transport = MockTransport::mock();
+        transport.port_mut().push_ack(ResponseCode::Success);
+        transport.execute::<Power>(&PowerAction::On).unwrap();
+        assert_eq!(
+            transport.port().sent(),
+            &[vec![0x8C, 0x00, 0x00, 0x02, 0x01, 0x8F]]
+        );
+    }
+
+    #[test]
+    fn test_query_parses_scripted_response() {
+        let mut transport = MockTransport::mock();
+        transport.port_mut().push_data(&[0x04, 0x02]);
+        let input = transport.query::<InputSelect>().unwrap();
+        assert_eq!(input, InputType::Hdmi(2));
+        assert_eq!(transport.port().sent(), &[build_query_packet(0x02)]);
+    }
+
+    #[test]
+    fn test_rejected_and_silent() {
+        let mut transport = MockTransport::mock();
+        transport.port_mut().push_ack(ResponseCode::LimitOverMax);
+        assert!(matches!(
+            transport.execute::<Volume>(&VolumeAction::Up),
+            Err(TransportError::CommandRejected(ResponseCode::LimitOverMax))
+        ));
+        assert!(matches!(
+            transport.execute::<Volume>(&VolumeAction::Up),
+            Err(TransportError::Timeout)
+        ));
+    }
+
+    #[test]
+    fn test_stale_bytes_are_drained() {
+        let mut transport = MockTransport::mock();
+        transport
+            .port_mut()
+            .push_stale(&[0x70, 0x00, 0x02, 0x00, 0x72]);
+        transport.port_mut().push_data(&[0x01]);
+        assert_eq!(transport.query::<Power>().unwrap(), PowerState::On);
+    }
+
+    #[test]
+    fn test_zero_length_response_is_an_error() {
+        let mut transport = MockTransport::mock();
+        transport.port_mut().push_raw(&[0x70, 0x00, 0x00]);
+        assert!(matches!(
+            transport.query::<Volume>(),
+            Err(TransportError::Protocol(ProtocolError::InvalidResponse))
+        ));
+    }
+}
@@ -0,0 +1,288 @@
+use std::io::{self, Read, Write};
+use std::time::Duration;
+use thiserror::Error;
+
+pub use serial::SerialSettings;
+
+use crate::protocol::{
+    ControlCommand, FrameError, FrameParser, ProtocolError, QueryCommand, RangeError, ResponseCode,
+    ResponseKind,
+};
+
+#[cfg(feature = "async")]
+pub mod async_io;
+#[cfg(feature = "async")]
+pub mod codec;
+pub mod discover;
+pub mod dry_run;
+#[cfg(any(test, feature = "testing"))]
+pub mod fault;
+pub mod matcher;
+// Scripted port behind trace replay and the tests; not part of the API.
+#[doc(hidden)]
+pub mod mock;
+pub mod pacing;
+pub mod raw;
+pub mod reconnect;
+pub mod retry;
+pub mod rfc2217;
+pub mod serial;
+pub mod tcp;
+pub mod trace;
+pub mod warmup;
+#[cfg(feature = "async")]
+pub mod worker;
+
+#[derive(Debug, Error)]
+pub enum TransportError {
+    #[error("serial port error: {0}")]
+    Serial(#[from] serialport::Error),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("timeout reading response")]
+    Timeout,
+    #[error("protocol error: {0}")]
+    Protocol(#[from] ProtocolError),
+    #[error("value out of range: {0}")]
+    Range(#[from] RangeError),
+    #[error("invalid checksum in response")]
+    InvalidChecksum,
+    #[error("unexpected response header: {0:#04x}")]
+    UnexpectedHeader(u8),
+    #[error("command rejected: {0:?}")]
+    CommandRejected(ResponseCode),
+    #[error("TV not ready {0:?} after power-on")]
+    NotReady(Duration),
+    #[error("device unavailable: {0}")]
+    Disconnected(String),
+    #[error("cannot resolve device: {0}")]
+    DeviceMatch(String),
+    #[error("discovery failed: {0}")]
+    Discovery(String),
+    #[error("not available in dry-run: nothing answers queries")]
+    DryRun,
+    #[error("serial setting {setting} rejected: {reason}")]
+    Setting {
+        setting: &'static str,
+        reason: String,
+    },
+}
+
+impl From<FrameError> for TransportError {
+    fn from(e: FrameError) -> Self {
+        match e {
+            FrameError::UnexpectedHeader(b) => TransportError::UnexpectedHeader(b),
+            FrameError::InvalidResponseCode(b) => ProtocolError::InvalidResponseCode(b).into(),
+            FrameError::InvalidLength(_) => ProtocolError::InvalidResponse.into(),
+            FrameError::InvalidChecksum => TransportError::InvalidChecksum,
+        }
+    }
+}
+
+/// A link to a TV that can carry control and query packets.
+///
+/// Backends only implement the two packet-level methods; `execute` and
+/// `query` build the packets from a `Command` and are shared by all of them.
+pub trait BraviaLink {
+    /// Sends a control packet and waits for the TV's acknowledgement.
+    fn send_control(&mut self, packet: &[u8]) -> Result<(), TransportError>;
+
+    /// Sends a query packet and returns the response data (without checksum).
+    fn send_query(&mut self, packet: &[u8]) -> Result<Vec<u8>, TransportError>;
+
+    /// Reopens the device if it was lost. Links that cannot lose their
+    /// device have nothing to do.
+    fn ensure_connected(&mut self) -> Result<(), TransportError> {
+        Ok(())
+    }
+
+    /// This link with resends turned off, for packets whose first answer the
+    /// caller wants to see. `Retrying` hands out the link it wraps; every
+    /// other link is returned as is.
+    fn without_retries(&mut self) -> &mut dyn BraviaLink
+    where
+        Self: Sized,
+    {
+        self
+    }
+
+    fn execute<C: ControlCommand>(&mut self, action: &C::Action) -> Result<(), TransportError>
+    where
+        Self: Sized,
+    {
+        let packet = C::control_packet(action);
+        self.send_control(&packet)
+    }
+
+    fn query<C: QueryCommand>(&mut self) -> Result<C::Response, TransportError>
+    where
+        Self: Sized,
+    {
+        let packet = C::query_packet();
+        let response_data = self.send_query(&packet)?;
+        let response = C::parse_response(&response_data)?;
+        Ok(response)
+    }
+}
+
+/// Serial line framing used by backends that configure the port themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineSettings {
+    pub baud_rate: u32,
+    pub data_bits: serialport::DataBits,
+    pub parity: serialport::Parity,
+    pub stop_bits: serialport::StopBits,
+    pub flow_control: serialport::FlowControl,
+    /// DTR/RTS level to assert after opening; `None` leaves the driver default.
+    pub dtr: Option<bool>,
+    pub rts: Option<bool>,
+}
+
+impl Default for LineSettings {
+    /// 9600 8N1 without flow control, as required by the Bravia RS-232C
+    /// interface.
+    fn default() -> Self {
+        Self {
+            baud_rate: 9600,
+            data_bits: serialport::DataBits::Eight,
+            parity: serialport::Parity::None,
+            stop_bits: serialport::StopBits::One,
+            flow_control: serialport::FlowControl::None,
+            dtr: None,
+            rts: None,
+        }
+    }
+}
+
+/// A byte stream that carries RS-232C frames to the TV.
+///
+/// Read timeouts must surface as `io::ErrorKind::TimedOut`.
+pub trait Port: Read + Write + Send {
+    /// Throws away bytes already received but not yet read, such as the tail
+    /// of a response that arrived after its request timed out.
+    fn discard_input(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Port for Box<dyn serialport::SerialPort + Send> {
+    fn discard_input(&mut self) -> io::Result<()> {
+        self.clear(serialport::ClearBuffer::Input)?;
+        Ok(())
+    }
+}
+
+impl Port for Box<dyn Port> {
+    fn discard_input(&mut self) -> io::Result<()> {
+        (**self).discard_input()
+    }
+}
+
+/// Byte-stream transport speaking the RS-232C framing over any `Port`.
+pub struct Transport<P> {
+    port: P,
+    parser: FrameParser,
+}
+
+pub type SerialTransport = Transport<Box<dyn serialport::SerialPort + Send>>;
+
+pub type DynTransport = Transport<Box<dyn Port>>;
+
+/// Opens the transport named by a `--dev` value: `tcp://host:port` for a raw
+/// serial-over-TCP bridge, `rfc2217://host:port` for a Telnet COM-port server,
+/// `usb:...` or `by-id:...` for a local serial port found by its adapter (see
+/// `matcher::DeviceMatcher`), anything else is a local serial device path.
+///
+/// Line settings apply to serial devices and RFC 2217 servers; a raw TCP
+/// bridge is configured on the bridge itself and only uses the read timeout.
+pub fn open(dev: &str, settings: &SerialSettings) -> Result<DynTransport, TransportError> {
+    settings.line.validate()?;
+    let port: Box<dyn Port> = if let Some(addr) = dev.strip_prefix("tcp://") {
+        let mut port = tcp::TcpPort::new(addr);
+        if let Some(timeout) = settings.read_timeout {
+            port = port.read_timeout(timeout);
+        }
+        Box::new(tcp::TcpTransport::connect_port(port)?.into_port())
+    } else if let Some(addr) = dev.strip_prefix("rfc2217://") {
+        Box::new(rfc2217::Rfc2217Transport::connect(addr, settings)?.into_port())
+    } else if matcher::DeviceMatcher::is_matcher(dev) {
+        let path = dev.parse::<matcher::DeviceMatcher>()?.resolve()?;
+        Box::new(serial::open_port(&path, settings)?)
+    } else {
+        Box::new(serial::open_port(dev, settings)?)
+    };
+    Ok(Transport::from_port(port))
+}
+
+impl SerialTransport {
+    pub fn new(device_path: &str) -> Result<Self, TransportError> {
+        Ok(Self::from_port(serial::open_port(
+            device_path,
+            &SerialSettings::default(),
+        )?))
+    }
+}
+
+impl<P: Port> Transport<P> {
+    pub fn from_port(port: P) -> Self {
+        Self {
+            port,
+            parser: FrameParser::default(),
+        }
+    }
+
+    pub fn port(&self) -> &P {
+        &self.port
+    }
+
+    pub fn port_mut(&mut self) -> &mut P {
+        &mut self.port
+    }
+
+    pub fn into_port(self) -> P {
+        self.port
+    }
+
+    /// Writes `packet` and reads until a complete response frame arrives.
+    ///
+    /// Stale input is dropped first so a late answer to an earlier request
+    /// cannot be mistaken for this one.
+    fn exchange(&mut self, packet: &[u8]) -> Result<Vec<u8>, TransportError> {
+        self.port.discard_input()?;
+        self.parser.reset(ResponseKind::for_packet(packet));
+        self.port.write_all(packet)?;
+
+        let mut buf = [0u8; 64];
+        loop {
+            if let Some(frame) = self.parser.next_frame() {
+                let frame = frame?;
+                if frame.code != ResponseCode::Success {
+                    return Err(TransportError::CommandRejected(frame.code));
+                }
+                return Ok(frame.data.to_vec());
+            }
+            let n = self.port.read(&mut buf).map_err(|e| {
+                if e.kind() == io::ErrorKind::TimedOut {
+                    TransportError::Timeout
+                } else {
+                    TransportError::Io(e)
+                }
+            })?;
+            if n == 0 {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            self.parser.push(&buf[..n]);
+        }
+    }
+}
+
+impl<P: Port> BraviaLink for Transport<P> {
+    fn send_control(&mut self, packet: &[u8]) -> Result<(), TransportError> {
+        self.exchange(packet)?;
+        Ok(())
+    }
+
+    fn send_query(&mut self, packet: &[u8]) -> Result<Vec<u8>, TransportError> {
+        self.exchange(packet)
+    }
+}
@@ -0,0 +1,162 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::protocol::{CONTROL_HEADER, Command, InputSelect, PictureMode, Power};
+use crate::transport::{BraviaLink, TransportError};
+
+pub const DEFAULT_MIN_GAP: Duration = Duration::from_millis(100);
+pub const DEFAULT_SLOW_GAP: Duration = Duration::from_secs(1);
+
+/// Quiet time the TV needs between packets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PacingPolicy {
+    /// Gap after every response before the next packet goes out.
+    pub min_gap: Duration,
+    /// Gap after a control packet for one of `slow_functions`.
+    pub slow_gap: Duration,
+    /// Function codes whose control commands make the TV busy for a while.
+    pub slow_functions: Vec<u8>,
+}
+
+impl Default for PacingPolicy {
+    fn default() -> Self {
+        Self {
+            min_gap: DEFAULT_MIN_GAP,
+            slow_gap: DEFAULT_SLOW_GAP,
+            slow_functions: vec![
+                Power::FUNCTION_CODE,
+                InputSelect::FUNCTION_CODE,
+                PictureMode::FUNCTION_CODE,
+            ],
+        }
+    }
+}
+
+impl PacingPolicy {
+    /// Gap to leave after `packet` has been answered.
+    pub fn gap_after(&self, packet: &[u8]) -> Duration {
+        match packet {
+            [CONTROL_HEADER, _, function, ..] if self.slow_functions.contains(function) => {
+                self.slow_gap.max(self.min_gap)
+            }
+            _ => self.min_gap,
+        }
+    }
+}
+
+/// Link wrapper that spaces packets out so the TV does not drop them.
+///
+/// The gap is measured from the end of one exchange, successful or not, to
+/// the start of the next.
+pub struct Paced<L> {
+    inner: L,
+    policy: PacingPolicy,
+    next_send: Option<Instant>,
+}
+
+impl<L: BraviaLink> Paced<L> {
+    pub fn new(inner: L, policy: PacingPolicy) -> Self {
+        Self {
+            inner,
+            policy,
+            next_send: None,
+        }
+    }
+
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut L {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> L {
+        self.inner
+    }
+
+    fn paced<R>(
+        &mut self,
+        packet: &[u8],
+        send: impl FnOnce(&mut L, &[u8]) -> Result<R, TransportError>,
+    ) -> Result<R, TransportError> {
+        if let Some(wait) = self
+            .next_send
+            .and_then(|at| at.checked_duration_since(Instant::now()))
+        {
+            thread::sleep(wait);
+        }
+        let result = send(&mut self.inner, packet);
+        self.next_send = Some(Instant::now() + self.policy.gap_after(packet));
+        result
+    }
+}
+
+impl<L: BraviaLink> BraviaLink for Paced<L> {
+    fn send_control(&mut self, packet: &[u8]) -> Result<(), TransportError> {
+        self.paced(packet, L::send_control)
+    }
+
+    fn send_query(&mut self, packet: &[u8]) -> Result<Vec<u8>, TransportError> {
+        self.paced(packet, L::send_query)
+    }
+
+    fn ensure_connected(&mut self) -> Result<(), TransportError> {
+        self.inner.ensure_connected()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::*;
+    use crate::transport::mock::MockTransport;
+
+    fn paced(min_ms: u64, slow_ms: u64) -> Paced<MockTransport> {
+        let policy = PacingPolicy {
+            min_gap: Duration::from_millis(min_ms),
+            slow_gap: Duration::from_millis(slow_ms),
+            ..PacingPolicy::default()
+        };
+        Paced::new(MockTransport::mock(), policy)
+    }
+
+    #[test]
+    fn test_gap_after_function() {
+        let policy = PacingPolicy::default();
+        let input = build_control_packet(0x02, &[0x04, 0x01]);
+        assert_eq!(policy.gap_after(&input), DEFAULT_SLOW_GAP);
+        assert_eq!(
+            policy.gap_after(&build_control_packet(0x05, &[0x00, 0x00])),
+            DEFAULT_MIN_GAP
+        );
+        assert_eq!(policy.gap_after(&build_query_packet(0x00)), DEFAULT_MIN_GAP);
+    }
+
+    #[test]
+    fn test_waits_between_packets() {
+        let mut link = paced(20, 80);
+        let port = link.inner_mut().port_mut();
+        port.push_ack(ResponseCode::Success);
+        port.push_ack(ResponseCode::Success);
+        port.push_ack(ResponseCode::Success);
+
+        let started = Instant::now();
+        link.execute::<Volume>(&VolumeAction::Up).unwrap();
+        assert!(started.elapsed() < Duration::from_millis(20));
+        link.execute::<InputSelect>(&InputType::Hdmi(1)).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(20));
+        let after_input = Instant::now();
+        link.execute::<Volume>(&VolumeAction::Up).unwrap();
+        assert!(after_input.elapsed() >= Duration::from_millis(80));
+    }
+
+    #[test]
+    fn test_failed_exchange_still_paced() {
+        let mut link = paced(30, 30);
+        let _ = link.execute::<Volume>(&VolumeAction::Up);
+        let started = Instant::now();
+        let _ = link.execute::<Volume>(&VolumeAction::Up);
+        assert!(started.elapsed() >= Duration::from_millis(30));
+    }
+}
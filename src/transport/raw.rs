@@ -0,0 +1,126 @@
+use crate::protocol::{
+    MAX_DATA_LEN, ProtocolError, ResponseCode, build_control_packet, build_query_packet,
+};
+use crate::transport::{BraviaLink, TransportError};
+
+/// A packet for an arbitrary function code, for functions that have no
+/// typed `Command` yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawCommand(Raw);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Raw {
+    Control { function: u8, data: Vec<u8> },
+    Query { function: u8 },
+}
+
+/// What the TV answered to a raw packet. Rejections are reported as their
+/// response code rather than as an error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawResponse {
+    pub code: ResponseCode,
+    pub data: Vec<u8>,
+}
+
+impl RawCommand {
+    /// A control packet, checking that `data` fits in one packet.
+    pub fn control(function: u8, data: Vec<u8>) -> Result<Self, ProtocolError> {
+        if data.len() > MAX_DATA_LEN {
+            return Err(ProtocolError::DataTooLong(data.len()));
+        }
+        Ok(Self(Raw::Control { function, data }))
+    }
+
+    pub fn query(function: u8) -> Self {
+        Self(Raw::Query { function })
+    }
+
+    pub fn packet(&self) -> Vec<u8> {
+        match &self.0 {
+            Raw::Control { function, data } => build_control_packet(*function, data),
+            Raw::Query { function } => build_query_packet(*function),
+        }
+    }
+
+    /// Sends the packet once, past any `Retrying` layer, so the response is
+    /// the TV's answer to this packet and not to a resend.
+    pub fn send<L: BraviaLink>(&self, link: &mut L) -> Result<RawResponse, TransportError> {
+        let packet = self.packet();
+        let link = link.without_retries();
+        let result = match self.0 {
+            Raw::Control { .. } => link.send_control(&packet).map(|()| Vec::new()),
+            Raw::Query { .. } => link.send_query(&packet),
+        };
+        match result {
+            Ok(data) => Ok(RawResponse {
+                code: ResponseCode::Success,
+                data,
+            }),
+            Err(TransportError::CommandRejected(code)) => Ok(RawResponse {
+                code,
+                data: Vec::new(),
+            }),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+    use crate::transport::retry::{RetryConfig, RetryPolicy, Retrying};
+    use std::time::Duration;
+
+    #[test]
+    fn test_raw_query_and_rejection() {
+        let mut link = MockTransport::mock();
+        link.port_mut().push_data(&[0x12, 0x34]);
+        link.port_mut().push_ack(ResponseCode::ParseError);
+
+        let response = RawCommand::query(0x99).send(&mut link).unwrap();
+        assert_eq!(response.code, ResponseCode::Success);
+        assert_eq!(response.data, vec![0x12, 0x34]);
+
+        let control = RawCommand::control(0x99, vec![0x01, 0x02]).unwrap();
+        let response = control.send(&mut link).unwrap();
+        assert_eq!(response.code, ResponseCode::ParseError);
+
+        assert_eq!(
+            link.port().sent(),
+            &[
+                build_query_packet(0x99),
+                build_control_packet(0x99, &[0x01, 0x02])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_oversized_data_is_refused() {
+        assert!(RawCommand::control(0x00, vec![0; MAX_DATA_LEN]).is_ok());
+        assert_eq!(
+            RawCommand::control(0x00, vec![0; MAX_DATA_LEN + 1]),
+            Err(ProtocolError::DataTooLong(MAX_DATA_LEN + 1))
+        );
+    }
+
+    #[test]
+    fn test_raw_packets_are_not_resent() {
+        let policy = RetryPolicy {
+            canceled_delay: Duration::ZERO,
+            ..RetryPolicy::default()
+        };
+        let config = RetryConfig {
+            control: policy,
+            query: policy,
+        };
+        let mut link = Retrying::new(MockTransport::mock(), config);
+        link.inner_mut().port_mut().push_ack(ResponseCode::Canceled);
+        link.inner_mut().port_mut().push_ack(ResponseCode::Success);
+
+        let control = RawCommand::control(0x00, vec![0x01]).unwrap();
+        let response = control.send(&mut link).unwrap();
+        assert_eq!(response.code, ResponseCode::Canceled);
+        assert_eq!(link.inner().port().sent().len(), 1);
+    }
+}
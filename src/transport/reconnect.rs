@@ -0,0 +1,301 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::transport::{BraviaLink, TransportError};
+
+pub const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+pub const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Connection state shared between a `Reconnecting` link and whoever
+/// reports on it, such as the HTTP health endpoint.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkStatus {
+    pub connected: bool,
+    /// Why the device was lost or could not be opened; cleared once it is
+    /// open again.
+    pub last_error: Option<String>,
+    /// Successful reopenings after a working device was lost.
+    pub reconnects: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Health(Arc<Mutex<LinkStatus>>);
+
+impl Health {
+    /// Health of a link that cannot lose its device.
+    pub fn always_connected() -> Self {
+        let health = Self::default();
+        health.update(|s| s.connected = true);
+        health
+    }
+
+    pub fn status(&self) -> LinkStatus {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut LinkStatus)) {
+        f(&mut self.0.lock().unwrap_or_else(|e| e.into_inner()));
+    }
+}
+
+/// Whether `e` means the device itself went away, as opposed to the TV not
+/// answering.
+pub fn is_device_lost(e: &TransportError) -> bool {
+    match e {
+        TransportError::Serial(e) => e.kind() == serialport::ErrorKind::NoDevice,
+        TransportError::Io(e) => {
+            matches!(
+                e.kind(),
+                io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::NotConnected
+                    | io::ErrorKind::UnexpectedEof
+                    | io::ErrorKind::NotFound
+                    | io::ErrorKind::PermissionDenied
+            ) || is_lost_errno(e)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(unix)]
+fn is_lost_errno(e: &io::Error) -> bool {
+    matches!(
+        e.raw_os_error(),
+        Some(libc::EIO | libc::ENXIO | libc::ENODEV)
+    )
+}
+
+#[cfg(not(unix))]
+fn is_lost_errno(_e: &io::Error) -> bool {
+    false
+}
+
+type Opener<L> = Box<dyn FnMut() -> Result<L, TransportError> + Send>;
+
+/// Link wrapper that reopens its device after it is lost.
+///
+/// When an exchange fails with an error that means the device is gone, the
+/// link is closed and `open` is called again on the next request, then with
+/// exponential backoff until it succeeds. Requests in between fail fast
+/// with `Disconnected`.
+pub struct Reconnecting<L> {
+    open: Opener<L>,
+    link: Option<L>,
+    health: Health,
+    backoff: Duration,
+    next_attempt: Option<Instant>,
+    /// Whether the device has been open before, so the next open is a reopen.
+    opened: bool,
+}
+
+impl<L: BraviaLink> Reconnecting<L> {
+    /// Wraps `open` without calling it; the device is opened on first use.
+    pub fn new(open: impl FnMut() -> Result<L, TransportError> + Send + 'static) -> Self {
+        Self {
+            open: Box::new(open),
+            link: None,
+            health: Health::default(),
+            backoff: INITIAL_BACKOFF,
+            next_attempt: None,
+            opened: false,
+        }
+    }
+
+    /// Opens the device now, failing if it is not there.
+    pub fn connect(
+        mut open: impl FnMut() -> Result<L, TransportError> + Send + 'static,
+    ) -> Result<Self, TransportError> {
+        let link = open()?;
+        let mut this = Self::new(open);
+        this.link = Some(link);
+        this.opened = true;
+        this.health.update(|s| s.connected = true);
+        Ok(this)
+    }
+
+    pub fn health(&self) -> Health {
+        self.health.clone()
+    }
+
+    pub fn inner(&self) -> Option<&L> {
+        self.link.as_ref()
+    }
+
+    pub fn inner_mut(&mut self) -> Option<&mut L> {
+        self.link.as_mut()
+    }
+
+    fn open_link(&mut self) -> Result<&mut L, TransportError> {
+        if self.link.is_none() {
+            if let Some(at) = self.next_attempt
+                && Instant::now() < at
+            {
+                let reason = self.health.status().last_error.unwrap_or_default();
+                return Err(TransportError::Disconnected(reason));
+            }
+            match (self.open)() {
+                Ok(link) => {
+                    self.link = Some(link);
+                    self.backoff = INITIAL_BACKOFF;
+                    self.next_attempt = None;
+                    let reopened = std::mem::replace(&mut self.opened, true);
+                    self.health.update(|s| {
+                        if reopened {
+                            s.reconnects += 1;
+                        }
+                        s.connected = true;
+                        s.last_error = None;
+                    });
+                }
+                Err(e) => {
+                    self.next_attempt = Some(Instant::now() + self.backoff);
+                    self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+                    let reason = e.to_string();
+                    self.health.update(|s| s.last_error = Some(reason.clone()));
+                    return Err(TransportError::Disconnected(reason));
+                }
+            }
+        }
+        Ok(self.link.as_mut().expect("link was just opened"))
+    }
+
+    fn send<R>(
+        &mut self,
+        packet: &[u8],
+        send: impl FnOnce(&mut L, &[u8]) -> Result<R, TransportError>,
+    ) -> Result<R, TransportError> {
+        let link = self.open_link()?;
+        let result = send(link, packet);
+        if let Err(e) = &result
+            && is_device_lost(e)
+        {
+            self.link = None;
+            self.next_attempt = None;
+            let reason = e.to_string();
+            self.health.update(|s| {
+                s.connected = false;
+                s.last_error = Some(reason);
+            });
+        }
+        result
+    }
+}
+
+impl<L: BraviaLink> BraviaLink for Reconnecting<L> {
+    fn send_control(&mut self, packet: &[u8]) -> Result<(), TransportError> {
+        self.send(packet, L::send_control)
+    }
+
+    fn send_query(&mut self, packet: &[u8]) -> Result<Vec<u8>, TransportError> {
+        self.send(packet, L::send_query)
+    }
+
+    fn ensure_connected(&mut self) -> Result<(), TransportError> {
+        self.open_link().map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::*;
+    use crate::transport::mock::MockTransport;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Opener that fails `failures` times before handing out mocks that
+    /// answer one power query each.
+    fn flaky(
+        failures: usize,
+    ) -> (
+        Arc<AtomicUsize>,
+        impl FnMut() -> Result<MockTransport, TransportError> + Send,
+    ) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let open = move || {
+            if counter.fetch_add(1, Ordering::SeqCst) < failures {
+                return Err(io::Error::from(io::ErrorKind::NotFound).into());
+            }
+            let mut mock = MockTransport::mock();
+            mock.port_mut().push_data(&[0x01]);
+            mock.port_mut().push_raw(&[0x70, 0x00]);
+            Ok(mock)
+        };
+        (calls, open)
+    }
+
+    #[test]
+    fn test_connect_fails_without_device() {
+        let (_, open) = flaky(1);
+        assert!(Reconnecting::connect(open).is_err());
+    }
+
+    #[test]
+    fn test_opens_lazily_with_backoff() {
+        let (calls, open) = flaky(1);
+        let mut link = Reconnecting::new(open);
+        let health = link.health();
+        assert!(!health.status().connected);
+
+        assert!(matches!(
+            link.query::<Power>(),
+            Err(TransportError::Disconnected(_))
+        ));
+        // Within the backoff window the opener is not called again.
+        assert!(link.ensure_connected().is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        std::thread::sleep(INITIAL_BACKOFF);
+        assert_eq!(link.query::<Power>().unwrap(), PowerState::On);
+        assert!(health.status().connected);
+        assert_eq!(health.status().last_error, None);
+        // A device that was missing at startup has not been reconnected.
+        assert_eq!(health.status().reconnects, 0);
+    }
+
+    #[test]
+    fn test_reopens_after_device_loss() {
+        let (calls, open) = flaky(0);
+        let mut link = Reconnecting::connect(open).unwrap();
+        let health = link.health();
+        assert_eq!(link.query::<Power>().unwrap(), PowerState::On);
+
+        // Unplugging shows up as an I/O error from the port.
+        let e = link.send(&build_query_packet(0x00), |_, _| -> Result<(), _> {
+            Err(io::Error::from(io::ErrorKind::BrokenPipe).into())
+        });
+        assert!(e.is_err());
+        assert!(!health.status().connected);
+        assert!(health.status().last_error.is_some());
+
+        assert_eq!(link.query::<Power>().unwrap(), PowerState::On);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(health.status().reconnects, 1);
+        assert_eq!(health.status().last_error, None);
+    }
+
+    #[test]
+    fn test_timeouts_keep_the_device() {
+        let (calls, open) = flaky(0);
+        let mut link = Reconnecting::connect(open).unwrap();
+        link.query::<Power>().unwrap();
+        assert!(matches!(
+            link.query::<Power>(),
+            Err(TransportError::Timeout)
+        ));
+        assert!(link.health().status().connected);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_device_loss_errors() {
+        assert!(is_device_lost(
+            &io::Error::from(io::ErrorKind::UnexpectedEof).into()
+        ));
+        assert!(!is_device_lost(&TransportError::Timeout));
+        assert!(!is_device_lost(&TransportError::CommandRejected(
+            ResponseCode::Canceled
+        )));
+    }
+}
@@ -0,0 +1,371 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::protocol::{QUERY_HEADER, ResponseCode};
+use crate::transport::{BraviaLink, TransportError};
+
+/// Which retry policy applies to a packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandClass {
+    Control,
+    Query,
+}
+
+impl CommandClass {
+    pub fn for_packet(packet: &[u8]) -> Self {
+        if packet.first() == Some(&QUERY_HEADER) {
+            CommandClass::Query
+        } else {
+            CommandClass::Control
+        }
+    }
+}
+
+/// When and how often a failed exchange is sent again.
+///
+/// Timeouts and garbled responses are retried with exponential backoff plus
+/// up to `jitter` of random delay. `Canceled` means the TV was busy, so it is
+/// retried only after `canceled_delay`. Out-of-range values and errors that
+/// a resend cannot fix are returned at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Resends after the first attempt; 0 disables retrying.
+    pub retries: u32,
+    /// Delay before the first resend, doubled for each one after.
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+    pub jitter: Duration,
+    pub canceled_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 2,
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+            jitter: Duration::from_millis(50),
+            canceled_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Base delay before resend number `retry` (0-based) after `err`, or
+    /// `None` if `err` should not be retried. Jitter is added by the caller.
+    pub fn delay_for(&self, err: &TransportError, retry: u32) -> Option<Duration> {
+        if retry >= self.retries {
+            return None;
+        }
+        match err {
+            TransportError::Timeout
+            | TransportError::InvalidChecksum
+            | TransportError::UnexpectedHeader(_)
+            | TransportError::Protocol(_)
+            | TransportError::Io(_) => Some(
+                self.backoff
+                    .saturating_mul(1 << retry.min(16))
+                    .min(self.max_backoff),
+            ),
+            TransportError::CommandRejected(ResponseCode::Canceled) => Some(self.canceled_delay),
+            TransportError::CommandRejected(_)
+            | TransportError::Range(_)
+            | TransportError::NotReady(_)
+            | TransportError::Setting { .. }
+            | TransportError::Discovery(_)
+            | TransportError::DeviceMatch(_)
+            | TransportError::Disconnected(_)
+            | TransportError::DryRun
+            | TransportError::Serial(_) => None,
+        }
+    }
+}
+
+/// Retry policies for each command class.
+///
+/// Control commands get their own policy because toggles such as
+/// `--volume up` are not idempotent: a timed-out toggle may already have
+/// taken effect on the TV. By default only queries are resent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryConfig {
+    pub control: RetryPolicy,
+    pub query: RetryPolicy,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            control: RetryPolicy::none(),
+            query: RetryPolicy::default(),
+        }
+    }
+}
+
+impl RetryConfig {
+    pub fn none() -> Self {
+        Self {
+            control: RetryPolicy::none(),
+            query: RetryPolicy::none(),
+        }
+    }
+
+    pub fn policy(&self, class: CommandClass) -> &RetryPolicy {
+        match class {
+            CommandClass::Control => &self.control,
+            CommandClass::Query => &self.query,
+        }
+    }
+}
+
+/// Link wrapper that resends packets according to a `RetryConfig`.
+pub struct Retrying<L> {
+    inner: L,
+    config: RetryConfig,
+    rng: u64,
+}
+
+impl<L: BraviaLink> Retrying<L> {
+    pub fn new(inner: L, config: RetryConfig) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        Self {
+            inner,
+            config,
+            rng: seed | 1,
+        }
+    }
+
+    pub fn config(&self) -> &RetryConfig {
+        &self.config
+    }
+
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut L {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> L {
+        self.inner
+    }
+
+    fn jitter(&mut self, max: Duration) -> Duration {
+        if max.is_zero() {
+            return Duration::ZERO;
+        }
+        // xorshift64; jitter only needs to spread retries, not be unpredictable.
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        Duration::from_nanos(self.rng % max.as_nanos().max(1) as u64)
+    }
+
+    fn with_retries<R>(
+        &mut self,
+        packet: &[u8],
+        mut send: impl FnMut(&mut L, &[u8]) -> Result<R, TransportError>,
+    ) -> Result<R, TransportError> {
+        let policy = *self.config.policy(CommandClass::for_packet(packet));
+        let mut retry = 0;
+        loop {
+            let err = match send(&mut self.inner, packet) {
+                Ok(r) => return Ok(r),
+                Err(e) => e,
+            };
+            let Some(delay) = policy.delay_for(&err, retry) else {
+                return Err(err);
+            };
+            std::thread::sleep(delay + self.jitter(policy.jitter));
+            retry += 1;
+        }
+    }
+}
+
+impl<L: BraviaLink> BraviaLink for Retrying<L> {
+    fn send_control(&mut self, packet: &[u8]) -> Result<(), TransportError> {
+        self.with_retries(packet, L::send_control)
+    }
+
+    fn send_query(&mut self, packet: &[u8]) -> Result<Vec<u8>, TransportError> {
+        self.with_retries(packet, L::send_query)
+    }
+
+    fn ensure_connected(&mut self) -> Result<(), TransportError> {
+        self.inner.ensure_connected()
+    }
+
+    fn without_retries(&mut self) -> &mut dyn BraviaLink {
+        &mut self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::*;
+    use crate::transport::Transport;
+    use crate::transport::fault::{FaultConfig, FaultyPort};
+    use crate::transport::mock::{MockPort, MockTransport};
+    use std::time::Instant;
+
+    fn quick(retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            retries,
+            backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+            jitter: Duration::ZERO,
+            canceled_delay: Duration::ZERO,
+        }
+    }
+
+    fn retrying(control: RetryPolicy, query: RetryPolicy) -> Retrying<MockTransport> {
+        Retrying::new(MockTransport::mock(), RetryConfig { control, query })
+    }
+
+    #[test]
+    fn test_retries_timeout_and_checksum() {
+        let mut link = retrying(quick(0), quick(2));
+        let port = link.inner_mut().port_mut();
+        port.push_silence();
+        port.push_raw(&[0x70, 0x00, 0x02, 0x01, 0x00]);
+        port.push_data(&[0x01]);
+        assert_eq!(link.query::<Power>().unwrap(), PowerState::On);
+        assert_eq!(link.inner().port().sent().len(), 3);
+    }
+
+    #[test]
+    fn test_gives_up_after_retries() {
+        let mut link = retrying(quick(1), quick(1));
+        assert!(matches!(
+            link.execute::<Power>(&PowerAction::On),
+            Err(TransportError::Timeout)
+        ));
+        assert_eq!(link.inner().port().sent().len(), 2);
+    }
+
+    #[test]
+    fn test_limit_errors_are_not_retried() {
+        let mut link = retrying(quick(3), quick(3));
+        link.inner_mut()
+            .port_mut()
+            .push_ack(ResponseCode::LimitOverMax);
+        assert!(matches!(
+            link.execute::<Volume>(&VolumeAction::Up),
+            Err(TransportError::CommandRejected(ResponseCode::LimitOverMax))
+        ));
+        assert_eq!(link.inner().port().sent().len(), 1);
+    }
+
+    #[test]
+    fn test_canceled_is_retried_after_delay() {
+        let policy = RetryPolicy {
+            canceled_delay: Duration::from_millis(30),
+            ..quick(1)
+        };
+        let mut link = retrying(policy, policy);
+        let port = link.inner_mut().port_mut();
+        port.push_ack(ResponseCode::Canceled);
+        port.push_ack(ResponseCode::Success);
+        let started = Instant::now();
+        link.execute::<Power>(&PowerAction::On).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(30));
+        assert_eq!(link.inner().port().sent().len(), 2);
+    }
+
+    #[test]
+    fn test_policy_per_class() {
+        let mut link = retrying(quick(0), quick(1));
+        assert!(matches!(
+            link.execute::<Power>(&PowerAction::On),
+            Err(TransportError::Timeout)
+        ));
+        assert_eq!(link.inner().port().sent().len(), 1);
+        link.inner_mut().port_mut().push_silence();
+        link.inner_mut().port_mut().push_data(&[0x00]);
+        assert_eq!(link.query::<Power>().unwrap(), PowerState::Off);
+    }
+
+    #[test]
+    fn test_controls_are_not_resent_by_default() {
+        let mut link = Retrying::new(MockTransport::mock(), RetryConfig::default());
+        link.inner_mut().port_mut().push_silence();
+        assert!(matches!(
+            link.execute::<Volume>(&VolumeAction::Up),
+            Err(TransportError::Timeout)
+        ));
+        assert_eq!(link.inner().port().sent().len(), 1);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let policy = RetryPolicy {
+            retries: 5,
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(300),
+            ..RetryPolicy::default()
+        };
+        let delays: Vec<_> = (0..5)
+            .map(|n| policy.delay_for(&TransportError::Timeout, n).unwrap())
+            .collect();
+        assert_eq!(delays, [100, 200, 300, 300, 300].map(Duration::from_millis));
+        assert_eq!(policy.delay_for(&TransportError::Timeout, 5), None);
+    }
+
+    #[test]
+    fn test_recovers_from_injected_faults() {
+        let config = FaultConfig {
+            seed: 42,
+            drop_byte: 0.05,
+            corrupt_checksum: 0.2,
+            partial_frame: 0.1,
+            ..FaultConfig::default()
+        };
+        let mut port = MockPort::new();
+        for _ in 0..200 {
+            port.push_data(&[0x01]);
+        }
+        let transport = Transport::from_port(FaultyPort::new(port, config));
+        let retry = RetryConfig {
+            control: quick(0),
+            query: quick(10),
+        };
+        let mut link = Retrying::new(transport, retry);
+        for _ in 0..20 {
+            assert_eq!(link.query::<Power>().unwrap(), PowerState::On);
+        }
+        let counts = link.inner().port().counts();
+        assert!(counts.corrupted_checksums > 0 && counts.partial_frames > 0);
+    }
+
+    #[test]
+    fn test_resyncs_after_duplicated_ack() {
+        let config = FaultConfig {
+            duplicate_ack: 1.0,
+            ..FaultConfig::default()
+        };
+        let mut port = MockPort::new();
+        port.push_ack(ResponseCode::Success);
+        port.push_data(&[0x01]);
+        port.push_data(&[0x01]);
+        let transport = Transport::from_port(FaultyPort::new(port, config));
+        let retry = RetryConfig {
+            control: quick(0),
+            query: quick(1),
+        };
+        let mut link = Retrying::new(transport, retry);
+        link.execute::<Power>(&PowerAction::On).unwrap();
+        // The stale ack garbles the first answer; the resend starts clean.
+        assert_eq!(link.query::<Power>().unwrap(), PowerState::On);
+        assert_eq!(link.inner().port().inner().sent().len(), 3);
+    }
+}
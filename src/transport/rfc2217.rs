@@ -0,0 +1,560 @@
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+
+use serialport::{DataBits, FlowControl, Parity, StopBits};
+
+use crate::transport::tcp::TcpPort;
+use crate::transport::{LineSettings, Port, SerialSettings, Transport, TransportError};
+
+// Telnet (RFC 854) commands
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+
+// Telnet options
+const BINARY: u8 = 0;
+const SUPPRESS_GO_AHEAD: u8 = 3;
+const COM_PORT_OPTION: u8 = 44;
+
+// COM-PORT-OPTION (RFC 2217) client commands; server replies add 100.
+const SET_BAUDRATE: u8 = 1;
+const SET_DATASIZE: u8 = 2;
+const SET_PARITY: u8 = 3;
+const SET_STOPSIZE: u8 = 4;
+const SET_CONTROL: u8 = 5;
+const SERVER_OFFSET: u8 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TelnetState {
+    Data,
+    Iac,
+    Option(u8),
+    Sub,
+    SubIac,
+}
+
+/// A line setting the server answered with another value than the one asked
+/// for, such as a rounded baud rate. Values are as sent on the wire.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingMismatch {
+    pub setting: &'static str,
+    pub asked: Vec<u8>,
+    pub got: Vec<u8>,
+}
+
+/// Serial server speaking Telnet with the RFC 2217 COM-PORT-OPTION.
+///
+/// Line settings are negotiated on every (re)connection before any frame is
+/// sent. Data bytes are IAC-escaped on the way out and Telnet commands are
+/// stripped from the stream on the way in.
+pub struct Rfc2217Port {
+    inner: TcpPort,
+    settings: LineSettings,
+    negotiated_generation: Option<u64>,
+    state: TelnetState,
+    sub: Vec<u8>,
+    replies: VecDeque<Vec<u8>>,
+    rx: VecDeque<u8>,
+    server_will_com_port: Option<bool>,
+    mismatches: Vec<SettingMismatch>,
+}
+
+pub type Rfc2217Transport = Transport<Rfc2217Port>;
+
+impl Rfc2217Port {
+    pub fn new(inner: TcpPort, settings: LineSettings) -> Self {
+        Self {
+            inner,
+            settings,
+            negotiated_generation: None,
+            state: TelnetState::Data,
+            sub: Vec::new(),
+            replies: VecDeque::new(),
+            rx: VecDeque::new(),
+            server_will_com_port: None,
+            mismatches: Vec::new(),
+        }
+    }
+
+    pub fn settings(&self) -> &LineSettings {
+        &self.settings
+    }
+
+    /// Settings the server did not take as asked in the last negotiation.
+    pub fn mismatches(&self) -> &[SettingMismatch] {
+        &self.mismatches
+    }
+
+    fn ensure_negotiated(&mut self) -> io::Result<()> {
+        self.inner.ensure_connected()?;
+        if self.negotiated_generation != Some(self.inner.generation()) {
+            self.negotiate()?;
+            self.negotiated_generation = Some(self.inner.generation());
+        }
+        Ok(())
+    }
+
+    fn negotiate(&mut self) -> io::Result<()> {
+        self.state = TelnetState::Data;
+        self.sub.clear();
+        self.replies.clear();
+        self.rx.clear();
+        self.server_will_com_port = None;
+        self.mismatches.clear();
+
+        self.inner.write_all(&[
+            IAC,
+            WILL,
+            BINARY,
+            IAC,
+            DO,
+            BINARY,
+            IAC,
+            WILL,
+            COM_PORT_OPTION,
+        ])?;
+
+        while self.server_will_com_port.is_none() {
+            self.fill()?;
+        }
+        if self.server_will_com_port == Some(false) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "server refused RFC 2217 COM-PORT-OPTION",
+            ));
+        }
+
+        let settings = self.settings;
+        let mut commands = vec![
+            (
+                SET_BAUDRATE,
+                "baud",
+                settings.baud_rate.to_be_bytes().to_vec(),
+            ),
+            (
+                SET_DATASIZE,
+                "data-bits",
+                vec![data_bits_code(settings.data_bits)],
+            ),
+            (SET_PARITY, "parity", vec![parity_code(settings.parity)]),
+            (
+                SET_STOPSIZE,
+                "stop-bits",
+                vec![stop_bits_code(settings.stop_bits)],
+            ),
+        ];
+        // Control lines are left to the server unless asked for.
+        if settings.flow_control != FlowControl::None {
+            commands.push((
+                SET_CONTROL,
+                "flow-control",
+                vec![flow_control_code(settings.flow_control)],
+            ));
+        }
+        if let Some(dtr) = settings.dtr {
+            commands.push((SET_CONTROL, "dtr", vec![if dtr { 8 } else { 9 }]));
+        }
+        if let Some(rts) = settings.rts {
+            commands.push((SET_CONTROL, "rts", vec![if rts { 11 } else { 12 }]));
+        }
+        for (command, _, value) in &commands {
+            let mut frame = vec![IAC, SB, COM_PORT_OPTION, *command];
+            frame.extend(escape(value));
+            frame.extend_from_slice(&[IAC, SE]);
+            self.inner.write_all(&frame)?;
+        }
+
+        for (command, setting, value) in &commands {
+            let reply = self.wait_for_reply(command + SERVER_OFFSET)?;
+            let got = &reply[1..];
+            if got.len() != value.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "malformed reply to COM-PORT-OPTION command {}: {:02x?}",
+                        command, got
+                    ),
+                ));
+            }
+            // Servers may round the baud rate or answer with the current
+            // state, so a different value is not a refusal.
+            if got != &value[..] {
+                self.mismatches.push(SettingMismatch {
+                    setting,
+                    asked: value.clone(),
+                    got: got.to_vec(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn wait_for_reply(&mut self, code: u8) -> io::Result<Vec<u8>> {
+        loop {
+            if let Some(pos) = self.replies.iter().position(|r| r.first() == Some(&code)) {
+                return Ok(self.replies.remove(pos).unwrap());
+            }
+            self.fill()?;
+        }
+    }
+
+    /// Reads one chunk from the socket and feeds it through the Telnet decoder.
+    fn fill(&mut self) -> io::Result<()> {
+        let mut buf = [0u8; 256];
+        let n = self.inner.read(&mut buf)?;
+        for &byte in &buf[..n] {
+            self.decode(byte)?;
+        }
+        Ok(())
+    }
+
+    fn decode(&mut self, byte: u8) -> io::Result<()> {
+        self.state = match (self.state, byte) {
+            (TelnetState::Data, IAC) => TelnetState::Iac,
+            (TelnetState::Data, b) => {
+                self.rx.push_back(b);
+                TelnetState::Data
+            }
+            (TelnetState::Iac, IAC) => {
+                self.rx.push_back(IAC);
+                TelnetState::Data
+            }
+            (TelnetState::Iac, cmd @ (DO | DONT | WILL | WONT)) => TelnetState::Option(cmd),
+            (TelnetState::Iac, SB) => {
+                self.sub.clear();
+                TelnetState::Sub
+            }
+            (TelnetState::Iac, _) => TelnetState::Data,
+            (TelnetState::Option(cmd), option) => {
+                self.answer_option(cmd, option)?;
+                TelnetState::Data
+            }
+            (TelnetState::Sub, IAC) => TelnetState::SubIac,
+            (TelnetState::Sub, b) => {
+                self.sub.push(b);
+                TelnetState::Sub
+            }
+            (TelnetState::SubIac, IAC) => {
+                self.sub.push(IAC);
+                TelnetState::Sub
+            }
+            (TelnetState::SubIac, SE) => {
+                if self.sub.first() == Some(&COM_PORT_OPTION) && self.sub.len() > 1 {
+                    self.replies.push_back(self.sub[1..].to_vec());
+                }
+                TelnetState::Data
+            }
+            (TelnetState::SubIac, _) => TelnetState::Data,
+        };
+        Ok(())
+    }
+
+    fn answer_option(&mut self, cmd: u8, option: u8) -> io::Result<()> {
+        match (cmd, option) {
+            (DO, COM_PORT_OPTION) => self.server_will_com_port = Some(true),
+            (DONT, COM_PORT_OPTION) => self.server_will_com_port = Some(false),
+            (DO, BINARY) | (WILL, BINARY) | (WILL, SUPPRESS_GO_AHEAD) => {}
+            (DO, other) => self.inner.write_all(&[IAC, WONT, other])?,
+            (WILL, other) => self.inner.write_all(&[IAC, DONT, other])?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl Read for Rfc2217Port {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.ensure_negotiated()?;
+        while self.rx.is_empty() {
+            self.fill()?;
+        }
+        let n = buf.len().min(self.rx.len());
+        for (dst, src) in buf.iter_mut().zip(self.rx.drain(..n)) {
+            *dst = src;
+        }
+        Ok(n)
+    }
+}
+
+impl Write for Rfc2217Port {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.ensure_negotiated()?;
+        self.inner.write_all(&escape(buf))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Port for Rfc2217Port {
+    fn discard_input(&mut self) -> io::Result<()> {
+        self.inner.discard_input()?;
+        self.state = TelnetState::Data;
+        self.rx.clear();
+        Ok(())
+    }
+}
+
+impl Rfc2217Transport {
+    /// Connects to `host:port` and negotiates the line settings up front.
+    pub fn connect(addr: &str, settings: &SerialSettings) -> Result<Self, TransportError> {
+        let mut tcp = TcpPort::new(addr);
+        if let Some(timeout) = settings.read_timeout {
+            tcp = tcp.read_timeout(timeout);
+        }
+        let mut port = Rfc2217Port::new(tcp, settings.line);
+        port.ensure_negotiated()?;
+        Ok(Self::from_port(port))
+    }
+}
+
+/// Doubles every 0xFF so data bytes are not mistaken for Telnet commands.
+pub fn escape(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    for &b in data {
+        out.push(b);
+        if b == IAC {
+            out.push(IAC);
+        }
+    }
+    out
+}
+
+fn data_bits_code(bits: DataBits) -> u8 {
+    match bits {
+        DataBits::Five => 5,
+        DataBits::Six => 6,
+        DataBits::Seven => 7,
+        DataBits::Eight => 8,
+    }
+}
+
+fn parity_code(parity: Parity) -> u8 {
+    match parity {
+        Parity::None => 1,
+        Parity::Odd => 2,
+        Parity::Even => 3,
+    }
+}
+
+fn stop_bits_code(stop_bits: StopBits) -> u8 {
+    match stop_bits {
+        StopBits::One => 1,
+        StopBits::Two => 2,
+    }
+}
+
+fn flow_control_code(flow_control: FlowControl) -> u8 {
+    match flow_control {
+        FlowControl::None => 1,
+        FlowControl::Software => 2,
+        FlowControl::Hardware => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::*;
+    use crate::transport::BraviaLink;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    /// Minimal RFC 2217 access server: agrees to COM-PORT-OPTION, echoes the
+    /// line settings back unless told to answer otherwise, and unescapes the
+    /// data stream.
+    struct StandIn {
+        conn: TcpStream,
+        settings: Vec<(u8, Vec<u8>)>,
+        answers: Vec<(u8, Vec<u8>)>,
+    }
+
+    impl StandIn {
+        fn accept(listener: &TcpListener) -> Self {
+            let (conn, _) = listener.accept().unwrap();
+            Self {
+                conn,
+                settings: Vec::new(),
+                answers: Vec::new(),
+            }
+        }
+
+        fn byte(&mut self) -> u8 {
+            let mut b = [0u8; 1];
+            self.conn.read_exact(&mut b).unwrap();
+            b[0]
+        }
+
+        /// Reads `n` unescaped data bytes, answering Telnet commands on the way.
+        fn read_data(&mut self, n: usize) -> Vec<u8> {
+            let mut data = Vec::new();
+            while data.len() < n {
+                match self.byte() {
+                    IAC => match self.byte() {
+                        IAC => data.push(IAC),
+                        WILL => {
+                            let option = self.byte();
+                            let answer = if option == COM_PORT_OPTION || option == BINARY {
+                                DO
+                            } else {
+                                DONT
+                            };
+                            self.conn.write_all(&[IAC, answer, option]).unwrap();
+                        }
+                        DO | DONT | WONT => {
+                            self.byte();
+                        }
+                        SB => {
+                            let mut sub = Vec::new();
+                            loop {
+                                match self.byte() {
+                                    IAC => match self.byte() {
+                                        SE => break,
+                                        b => sub.push(b),
+                                    },
+                                    b => sub.push(b),
+                                }
+                            }
+                            assert_eq!(sub[0], COM_PORT_OPTION);
+                            let value = match self.answers.iter().find(|(c, _)| *c == sub[1]) {
+                                Some((_, value)) => value.clone(),
+                                None => sub[2..].to_vec(),
+                            };
+                            let mut reply = vec![IAC, SB, COM_PORT_OPTION, sub[1] + SERVER_OFFSET];
+                            reply.extend(escape(&value));
+                            reply.extend_from_slice(&[IAC, SE]);
+                            self.conn.write_all(&reply).unwrap();
+                            self.settings.push((sub[1], sub[2..].to_vec()));
+                        }
+                        _ => {}
+                    },
+                    b => data.push(b),
+                }
+            }
+            data
+        }
+    }
+
+    #[test]
+    fn test_negotiates_line_settings() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let mut server = StandIn::accept(&listener);
+            let packet = server.read_data(6);
+            server.conn.write_all(&[0x70, 0x00, 0x70]).unwrap();
+            (server.settings, packet)
+        });
+
+        let mut transport = Rfc2217Transport::connect(&addr, &SerialSettings::default()).unwrap();
+        transport.execute::<Power>(&PowerAction::On).unwrap();
+
+        let (settings, packet) = server.join().unwrap();
+        assert_eq!(
+            settings,
+            vec![
+                (SET_BAUDRATE, 9600u32.to_be_bytes().to_vec()),
+                (SET_DATASIZE, vec![8]),
+                (SET_PARITY, vec![1]),
+                (SET_STOPSIZE, vec![1]),
+            ]
+        );
+        assert_eq!(packet, build_control_packet(0x00, &[0x01]));
+    }
+
+    #[test]
+    fn test_accepts_a_different_setting() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let mut server = StandIn::accept(&listener);
+            server
+                .answers
+                .push((SET_BAUDRATE, 19200u32.to_be_bytes().to_vec()));
+            server.read_data(6);
+            server.conn.write_all(&[0x70, 0x00, 0x70]).unwrap();
+        });
+
+        let mut transport = Rfc2217Transport::connect(&addr, &SerialSettings::default()).unwrap();
+        assert_eq!(
+            transport.port().mismatches(),
+            [SettingMismatch {
+                setting: "baud",
+                asked: 9600u32.to_be_bytes().to_vec(),
+                got: 19200u32.to_be_bytes().to_vec(),
+            }]
+        );
+        transport.execute::<Power>(&PowerAction::On).unwrap();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_negotiates_control_lines() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let mut server = StandIn::accept(&listener);
+            server.read_data(6);
+            server.conn.write_all(&[0x70, 0x00, 0x70]).unwrap();
+            server.settings
+        });
+
+        let settings = SerialSettings {
+            line: LineSettings {
+                baud_rate: 19200,
+                flow_control: FlowControl::Hardware,
+                dtr: Some(true),
+                rts: Some(false),
+                ..LineSettings::default()
+            },
+            read_timeout: None,
+        };
+        let mut transport = Rfc2217Transport::connect(&addr, &settings).unwrap();
+        transport.execute::<Power>(&PowerAction::On).unwrap();
+
+        let settings = server.join().unwrap();
+        assert_eq!(settings[0], (SET_BAUDRATE, 19200u32.to_be_bytes().to_vec()));
+        assert_eq!(
+            settings[4..],
+            [
+                (SET_CONTROL, vec![3]),
+                (SET_CONTROL, vec![8]),
+                (SET_CONTROL, vec![12]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_escapes_query_and_unescapes_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let mut server = StandIn::accept(&listener);
+            let packet = server.read_data(6);
+            // Response data containing 0xFF must be escaped on the wire too.
+            let mut frame = vec![0x70, 0x00, 0x03, 0xFF, 0x01];
+            frame.push(checksum(&frame));
+            server.conn.write_all(&escape(&frame)).unwrap();
+            packet
+        });
+
+        let mut transport = Rfc2217Transport::connect(&addr, &SerialSettings::default()).unwrap();
+        let info = transport.query::<ProductInfo1>().unwrap();
+        assert_eq!(info, [0xFF, 0x01]);
+        assert_eq!(server.join().unwrap(), build_query_packet(0x6E));
+    }
+
+    #[test]
+    fn test_escape_doubles_iac() {
+        assert_eq!(
+            escape(&[0x83, 0x00, 0x00, 0xFF, 0xFF, 0x81]),
+            vec![0x83, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x81]
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use std::time::Duration;
+
+use serialport::{DataBits, FlowControl, Parity, SerialPort, StopBits};
+
+use crate::transport::{LineSettings, TransportError};
+
+pub const READ_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Everything configurable about how a device is opened.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SerialSettings {
+    pub line: LineSettings,
+    /// Response timeout; `None` uses the backend's own default.
+    pub read_timeout: Option<Duration>,
+}
+
+impl LineSettings {
+    pub fn validate(&self) -> Result<(), TransportError> {
+        if self.baud_rate == 0 {
+            return Err(TransportError::Setting {
+                setting: "baud",
+                reason: "must be greater than zero".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn rejected(setting: &'static str) -> impl Fn(serialport::Error) -> TransportError {
+    move |e| TransportError::Setting {
+        setting,
+        reason: e.to_string(),
+    }
+}
+
+/// Opens a local serial port and applies `settings` one at a time, so a
+/// setting the driver refuses is named in the error.
+pub fn open_port(
+    path: &str,
+    settings: &SerialSettings,
+) -> Result<Box<dyn SerialPort + Send>, TransportError> {
+    let line = settings.line;
+    line.validate()?;
+    let mut port = serialport::new(path, line.baud_rate)
+        .timeout(settings.read_timeout.unwrap_or(READ_TIMEOUT))
+        .open()
+        .map_err(|e| match e.kind() {
+            serialport::ErrorKind::InvalidInput => rejected("baud")(e),
+            _ => e.into(),
+        })?;
+
+    // Some drivers round unsupported rates instead of failing.
+    if let Ok(actual) = port.baud_rate()
+        && actual != line.baud_rate
+    {
+        return Err(TransportError::Setting {
+            setting: "baud",
+            reason: format!(
+                "asked for {}, port is running at {}",
+                line.baud_rate, actual
+            ),
+        });
+    }
+    port.set_data_bits(line.data_bits)
+        .map_err(rejected("data-bits"))?;
+    port.set_parity(line.parity).map_err(rejected("parity"))?;
+    port.set_stop_bits(line.stop_bits)
+        .map_err(rejected("stop-bits"))?;
+    port.set_flow_control(line.flow_control)
+        .map_err(rejected("flow-control"))?;
+    if let Some(dtr) = line.dtr {
+        port.write_data_terminal_ready(dtr)
+            .map_err(rejected("dtr"))?;
+    }
+    if let Some(rts) = line.rts {
+        port.write_request_to_send(rts).map_err(rejected("rts"))?;
+    }
+    Ok(port)
+}
+
+pub fn parse_data_bits(s: &str) -> Result<DataBits, String> {
+    match s {
+        "5" => Ok(DataBits::Five),
+        "6" => Ok(DataBits::Six),
+        "7" => Ok(DataBits::Seven),
+        "8" => Ok(DataBits::Eight),
+        _ => Err(format!(
+            "Invalid data bits: {}. Valid values: 5, 6, 7, 8",
+            s
+        )),
+    }
+}
+
+pub fn parse_parity(s: &str) -> Result<Parity, String> {
+    match s {
+        "none" => Ok(Parity::None),
+        "odd" => Ok(Parity::Odd),
+        "even" => Ok(Parity::Even),
+        _ => Err(format!(
+            "Invalid parity: {}. Valid values: none, odd, even",
+            s
+        )),
+    }
+}
+
+pub fn parse_stop_bits(s: &str) -> Result<StopBits, String> {
+    match s {
+        "1" => Ok(StopBits::One),
+        "2" => Ok(StopBits::Two),
+        _ => Err(format!("Invalid stop bits: {}. Valid values: 1, 2", s)),
+    }
+}
+
+pub fn parse_flow_control(s: &str) -> Result<FlowControl, String> {
+    match s {
+        "none" => Ok(FlowControl::None),
+        "software" => Ok(FlowControl::Software),
+        "hardware" => Ok(FlowControl::Hardware),
+        _ => Err(format!(
+            "Invalid flow control: {}. Valid values: none, software, hardware",
+            s
+        )),
+    }
+}
+
+pub fn parse_switch(s: &str) -> Result<bool, String> {
+    match s {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(format!("Invalid value: {}. Valid values: on, off", s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_settings() {
+        assert_eq!(parse_data_bits("7"), Ok(DataBits::Seven));
+        assert_eq!(parse_parity("even"), Ok(Parity::Even));
+        assert_eq!(parse_stop_bits("2"), Ok(StopBits::Two));
+        assert_eq!(parse_flow_control("hardware"), Ok(FlowControl::Hardware));
+        assert_eq!(parse_switch("off"), Ok(false));
+        assert!(parse_data_bits("9").is_err());
+        assert!(parse_parity("mark").is_err());
+    }
+
+    #[test]
+    fn test_zero_baud_is_invalid() {
+        let line = LineSettings {
+            baud_rate: 0,
+            ..LineSettings::default()
+        };
+        assert!(matches!(
+            line.validate(),
+            Err(TransportError::Setting {
+                setting: "baud",
+                ..
+            })
+        ));
+        assert!(matches!(
+            open_port(
+                "/dev/null",
+                &SerialSettings {
+                    line,
+                    read_timeout: None
+                }
+            ),
+            Err(TransportError::Setting {
+                setting: "baud",
+                ..
+            })
+        ));
+    }
+}
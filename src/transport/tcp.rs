@@ -0,0 +1,258 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::transport::{Port, Transport, TransportError};
+
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+pub const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Raw TCP connection to an Ethernet-to-RS232 converter (Moxa, ser2net, ...).
+///
+/// The socket is opened lazily and dropped on any I/O error other than a
+/// read timeout, so the next packet transparently reconnects.
+pub struct TcpPort {
+    addr: String,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    stream: Option<TcpStream>,
+    generation: u64,
+}
+
+pub type TcpTransport = Transport<TcpPort>;
+
+impl TcpPort {
+    pub fn new(addr: &str) -> Self {
+        Self {
+            addr: addr.to_string(),
+            connect_timeout: CONNECT_TIMEOUT,
+            read_timeout: READ_TIMEOUT,
+            stream: None,
+            generation: 0,
+        }
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Number of connections opened so far; changes on every reconnect.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub(crate) fn ensure_connected(&mut self) -> io::Result<()> {
+        self.connect().map(|_| ())
+    }
+
+    fn connect(&mut self) -> io::Result<&mut TcpStream> {
+        if self.stream.is_none() {
+            let mut last_err = None;
+            for addr in self.addr.to_socket_addrs()? {
+                match TcpStream::connect_timeout(&addr, self.connect_timeout) {
+                    Ok(stream) => {
+                        stream.set_read_timeout(Some(self.read_timeout))?;
+                        stream.set_nodelay(true)?;
+                        self.stream = Some(stream);
+                        self.generation += 1;
+                        break;
+                    }
+                    Err(e) => last_err = Some(e),
+                }
+            }
+            if self.stream.is_none() {
+                return Err(last_err.unwrap_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, "address resolved to nothing")
+                }));
+            }
+        }
+        Ok(self.stream.as_mut().unwrap())
+    }
+
+    fn disconnect(&mut self) {
+        self.stream = None;
+    }
+}
+
+impl Read for TcpPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let stream = self.connect()?;
+        match stream.read(buf) {
+            Ok(0) if !buf.is_empty() => {
+                self.disconnect();
+                Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed by peer",
+                ))
+            }
+            Ok(n) => Ok(n),
+            // Socket read timeouts surface as WouldBlock on unix.
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                Err(io::Error::new(io::ErrorKind::TimedOut, e))
+            }
+            Err(e) => {
+                self.disconnect();
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Write for TcpPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // A connection the peer closed while idle only fails on first use, so
+        // retry once on a fresh socket before giving up.
+        let was_connected = self.is_connected();
+        match self.connect()?.write(buf) {
+            Ok(n) => Ok(n),
+            Err(_) if was_connected => {
+                self.disconnect();
+                let result = self.connect()?.write(buf);
+                if result.is_err() {
+                    self.disconnect();
+                }
+                result
+            }
+            Err(e) => {
+                self.disconnect();
+                Err(e)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.stream {
+            Some(stream) => stream.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Port for TcpPort {
+    fn discard_input(&mut self) -> io::Result<()> {
+        let Some(stream) = &mut self.stream else {
+            return Ok(());
+        };
+        stream.set_nonblocking(true)?;
+        let mut buf = [0u8; 256];
+        let result = loop {
+            match stream.read(&mut buf) {
+                Ok(0) => {
+                    self.stream = None;
+                    return Ok(());
+                }
+                Ok(_) => continue,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+        stream.set_nonblocking(false)?;
+        result
+    }
+}
+
+impl TcpTransport {
+    /// Connects to `host:port`, failing early if the converter is unreachable.
+    pub fn connect(addr: &str) -> Result<Self, TransportError> {
+        Self::connect_port(TcpPort::new(addr))
+    }
+
+    /// Like `connect`, for a port with its own timeouts.
+    pub fn connect_port(mut port: TcpPort) -> Result<Self, TransportError> {
+        port.connect()?;
+        Ok(Self::from_port(port))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::*;
+    use crate::transport::BraviaLink;
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn ack() -> [u8; 3] {
+        [0x70, 0x00, 0x70]
+    }
+
+    #[test]
+    fn test_tcp_control_round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let tv = thread::spawn(move || {
+            let (mut conn, _) = listener.accept().unwrap();
+            let mut packet = [0u8; 6];
+            conn.read_exact(&mut packet).unwrap();
+            conn.write_all(&ack()).unwrap();
+            packet
+        });
+
+        let mut transport = TcpTransport::connect(&addr).unwrap();
+        transport.execute::<Power>(&PowerAction::On).unwrap();
+        assert_eq!(
+            tv.join().unwrap().to_vec(),
+            build_control_packet(0x00, &[0x01])
+        );
+    }
+
+    #[test]
+    fn test_tcp_read_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let tv = thread::spawn(move || {
+            let (mut conn, _) = listener.accept().unwrap();
+            let mut packet = [0u8; 6];
+            conn.read_exact(&mut packet).unwrap();
+            thread::sleep(Duration::from_millis(300));
+        });
+
+        let port = TcpPort::new(&addr).read_timeout(Duration::from_millis(100));
+        let mut transport = Transport::from_port(port);
+        assert!(matches!(
+            transport.query::<Power>(),
+            Err(TransportError::Timeout)
+        ));
+        assert!(transport.port().is_connected());
+        tv.join().unwrap();
+    }
+
+    #[test]
+    fn test_tcp_reconnects_after_peer_close() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let tv = thread::spawn(move || {
+            for _ in 0..2 {
+                let (mut conn, _) = listener.accept().unwrap();
+                let mut packet = [0u8; 6];
+                conn.read_exact(&mut packet).unwrap();
+                conn.write_all(&[0x70, 0x00, 0x02, 0x01, 0x73]).unwrap();
+            }
+        });
+
+        let mut transport = TcpTransport::connect(&addr).unwrap();
+        assert_eq!(transport.query::<Power>().unwrap(), PowerState::On);
+        // The first connection is closed by the server thread; the next query
+        // either fails once or reconnects straight away, but must recover.
+        let state = transport
+            .query::<Power>()
+            .or_else(|_| transport.query::<Power>());
+        assert_eq!(state.unwrap(), PowerState::On);
+        tv.join().unwrap();
+    }
+}
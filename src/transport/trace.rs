@@ -0,0 +1,325 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, LineWriter, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::protocol::decode::hex;
+use crate::protocol::{CONTROL_HEADER, FrameParser, QUERY_HEADER, ResponseKind, function_name};
+use crate::transport::mock::{MockPort, MockTransport};
+use crate::transport::{BraviaLink, Port, TransportError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Tx,
+    Rx,
+}
+
+/// One write to, or read from, the port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    /// Time since the trace started.
+    pub at: Duration,
+    pub direction: Direction,
+    pub bytes: Vec<u8>,
+}
+
+/// Names the command a request packet carries, e.g. "query Power".
+pub fn describe_packet(packet: &[u8]) -> String {
+    let kind = match packet.first() {
+        Some(&CONTROL_HEADER) => "control",
+        Some(&QUERY_HEADER) => "query",
+        _ => return "unknown packet".to_string(),
+    };
+    match packet.get(2) {
+        Some(&function) => match function_name(function) {
+            Some(name) => format!("{} {}", kind, name),
+            None => format!("{} {:#04x}", kind, function),
+        },
+        None => format!("{} (truncated)", kind),
+    }
+}
+
+impl TraceEvent {
+    /// Formats the event as a trace line, with `note` as a trailing comment.
+    pub fn to_line(&self, note: &str) -> String {
+        let direction = match self.direction {
+            Direction::Tx => "TX",
+            Direction::Rx => "RX",
+        };
+        let mut line = format!(
+            "{:.6} {} {}",
+            self.at.as_secs_f64(),
+            direction,
+            hex(&self.bytes)
+        );
+        if !note.is_empty() {
+            line.push_str("  # ");
+            line.push_str(note);
+        }
+        line
+    }
+
+    /// Parses a line written by `to_line`. Blank and comment-only lines
+    /// yield `Ok(None)`.
+    pub fn parse_line(line: &str) -> Result<Option<Self>, String> {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            return Ok(None);
+        }
+        let mut fields = line.split_whitespace();
+        let at = fields
+            .next()
+            .and_then(|s| s.parse::<f64>().ok())
+            .and_then(|s| Duration::try_from_secs_f64(s).ok())
+            .ok_or("invalid timestamp")?;
+        let direction = match fields.next() {
+            Some("TX") => Direction::Tx,
+            Some("RX") => Direction::Rx,
+            _ => return Err("expected TX or RX".to_string()),
+        };
+        let bytes = fields
+            .map(|s| u8::from_str_radix(s, 16).map_err(|_| format!("invalid byte: {}", s)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Some(Self {
+            at,
+            direction,
+            bytes,
+        }))
+    }
+}
+
+pub fn read_trace(reader: impl BufRead) -> io::Result<Vec<TraceEvent>> {
+    let mut events = Vec::new();
+    for (n, line) in reader.lines().enumerate() {
+        match TraceEvent::parse_line(&line?) {
+            Ok(Some(event)) => events.push(event),
+            Ok(None) => {}
+            Err(e) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("trace line {}: {}", n + 1, e),
+                ));
+            }
+        }
+    }
+    Ok(events)
+}
+
+/// Port wrapper that logs every write and read to a trace sink.
+///
+/// Received bytes are logged as they arrive, annotated with any response
+/// frames they complete. Input thrown away by `discard_input` without being
+/// read is not logged.
+pub struct TracePort<P, W> {
+    inner: P,
+    sink: W,
+    started: Instant,
+    parser: FrameParser,
+}
+
+impl<P: Port> TracePort<P, LineWriter<File>> {
+    /// Traces to the end of `path`, creating it if needed, so a device that
+    /// is reopened keeps logging to the same file. Pass the same `started`
+    /// on every reopen to keep the file's timestamps in order.
+    pub fn append(inner: P, path: impl AsRef<Path>, started: Instant) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::started_at(inner, LineWriter::new(file), started))
+    }
+}
+
+impl<P: Port, W: Write + Send> TracePort<P, W> {
+    pub fn new(inner: P, sink: W) -> Self {
+        Self::started_at(inner, sink, Instant::now())
+    }
+
+    /// Logs times relative to `started` rather than to now.
+    pub fn started_at(inner: P, sink: W, started: Instant) -> Self {
+        Self {
+            inner,
+            sink,
+            started,
+            parser: FrameParser::default(),
+        }
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    pub fn sink(&self) -> &W {
+        &self.sink
+    }
+
+    fn log(&mut self, direction: Direction, bytes: &[u8], note: &str) -> io::Result<()> {
+        let event = TraceEvent {
+            at: self.started.elapsed(),
+            direction,
+            bytes: bytes.to_vec(),
+        };
+        writeln!(self.sink, "{}", event.to_line(note))
+    }
+}
+
+impl<P: Port, W: Write + Send> Read for TracePort<P, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n > 0 {
+            self.parser.push(&buf[..n]);
+            let frames: Vec<String> = std::iter::from_fn(|| self.parser.next_frame())
+                .map(|frame| match frame {
+                    Ok(frame) if frame.data.is_empty() => format!("{:?}", frame.code),
+                    Ok(frame) => format!("{:?} data {}", frame.code, hex(&frame.data)),
+                    Err(e) => e.to_string(),
+                })
+                .collect();
+            self.log(Direction::Rx, &buf[..n], &frames.join("; "))?;
+        }
+        Ok(n)
+    }
+}
+
+impl<P: Port, W: Write + Send> Write for TracePort<P, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.parser.reset(ResponseKind::for_packet(buf));
+        self.log(Direction::Tx, &buf[..n], &describe_packet(buf))?;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()?;
+        self.sink.flush()
+    }
+}
+
+impl<P: Port, W: Write + Send> Port for TracePort<P, W> {
+    fn discard_input(&mut self) -> io::Result<()> {
+        self.inner.discard_input()
+    }
+}
+
+/// Result of resending one recorded request during a replay.
+pub struct Replayed {
+    pub packet: Vec<u8>,
+    pub result: Result<Vec<u8>, TransportError>,
+}
+
+/// Sends every recorded TX packet again through a mock transport that answers
+/// with the bytes recorded after it, so the parser sees exactly what the TV
+/// sent. Bytes received before the first packet are replayed as stale input.
+pub fn replay(events: &[TraceEvent]) -> Vec<Replayed> {
+    let mut port = MockPort::new();
+    let mut packets = Vec::new();
+    let mut response: Option<Vec<u8>> = None;
+    for event in events {
+        match (event.direction, &mut response) {
+            (Direction::Tx, _) => {
+                if let Some(bytes) = response.replace(Vec::new()) {
+                    port.push_raw(&bytes);
+                }
+                packets.push(event.bytes.clone());
+            }
+            (Direction::Rx, Some(bytes)) => bytes.extend_from_slice(&event.bytes),
+            (Direction::Rx, None) => port.push_stale(&event.bytes),
+        }
+    }
+    if let Some(bytes) = response {
+        port.push_raw(&bytes);
+    }
+
+    let mut transport = MockTransport::from_port(port);
+    packets
+        .into_iter()
+        .map(|packet| {
+            let result = if packet.first() == Some(&QUERY_HEADER) {
+                transport.send_query(&packet)
+            } else {
+                transport.send_control(&packet).map(|()| Vec::new())
+            };
+            Replayed { packet, result }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::*;
+    use crate::transport::Transport;
+
+    fn traced() -> Transport<TracePort<MockPort, Vec<u8>>> {
+        Transport::from_port(TracePort::new(MockPort::new(), Vec::new()))
+    }
+
+    #[test]
+    fn test_trace_lines() {
+        let mut transport = traced();
+        transport.port_mut().inner.push_data(&[0x01]);
+        assert_eq!(transport.query::<Power>().unwrap(), PowerState::On);
+
+        let log = String::from_utf8(transport.port().sink().clone()).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(" TX 83 00 00 FF FF 81  # query Power"));
+        assert!(lines[1].ends_with(" RX 70 00 02 01 73  # Success data 01"));
+    }
+
+    #[test]
+    fn test_reopened_port_keeps_the_clock() {
+        let path = std::env::temp_dir().join(format!("bravia-trace-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let started = Instant::now() - Duration::from_secs(5);
+        for _ in 0..2 {
+            let port = TracePort::append(MockPort::new(), &path, started).unwrap();
+            let mut transport = Transport::from_port(port);
+            transport.port_mut().inner.push_data(&[0x01]);
+            transport.query::<Power>().unwrap();
+        }
+        let events = read_trace(io::BufReader::new(File::open(&path).unwrap())).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(events.len(), 4);
+        assert!(events[0].at >= Duration::from_secs(5));
+        assert!(events.windows(2).all(|pair| pair[0].at <= pair[1].at));
+    }
+
+    #[test]
+    fn test_parse_line_round_trip() {
+        let event = TraceEvent {
+            at: Duration::from_micros(1_500_250),
+            direction: Direction::Rx,
+            bytes: vec![0x70, 0x00, 0x70],
+        };
+        let line = event.to_line("Success");
+        assert_eq!(line, "1.500250 RX 70 00 70  # Success");
+        assert_eq!(TraceEvent::parse_line(&line).unwrap(), Some(event));
+        assert_eq!(TraceEvent::parse_line("  # comment").unwrap(), None);
+        assert!(TraceEvent::parse_line("0.1 XX 00").is_err());
+    }
+
+    #[test]
+    fn test_replay_reproduces_exchanges() {
+        let trace = "\
+0.000000 RX 70 00
+0.100000 TX 83 00 00 FF FF 81
+0.150000 RX 55 70 00
+0.160000 RX 02 01 73
+0.300000 TX 8C 00 05 03 00 00 94
+";
+        let events = read_trace(trace.as_bytes()).unwrap();
+        let replayed = replay(&events);
+        assert_eq!(replayed.len(), 2);
+        assert_eq!(replayed[0].packet, build_query_packet(0x00));
+        assert_eq!(replayed[0].result.as_ref().unwrap(), &vec![0x01]);
+        assert!(matches!(replayed[1].result, Err(TransportError::Timeout)));
+    }
+
+    #[test]
+    fn test_describe_packet() {
+        assert_eq!(
+            describe_packet(&build_control_packet(0x02, &[0x04, 0x01])),
+            "control InputSelect"
+        );
+        assert_eq!(describe_packet(&build_query_packet(0x99)), "query 0x99");
+        assert_eq!(describe_packet(&[0x12]), "unknown packet");
+    }
+}
@@ -0,0 +1,178 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::protocol::{ControlCommand, Power, PowerAction, PowerState, QueryCommand};
+use crate::transport::{BraviaLink, TransportError};
+
+pub const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(15);
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WarmUpPolicy {
+    /// How long after power-on to wait for the TV; zero disables waiting.
+    pub max_wait: Duration,
+    /// Delay between `Power` queries while waiting.
+    pub poll_interval: Duration,
+}
+
+impl Default for WarmUpPolicy {
+    fn default() -> Self {
+        Self {
+            max_wait: DEFAULT_MAX_WAIT,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+}
+
+/// Link wrapper that holds commands back while the TV warms up.
+///
+/// For several seconds after power-on the TV ignores or cancels commands.
+/// Once a power-on is acknowledged, the next packet is not sent until a
+/// `Power` query answers `On`, or `NotReady` is returned if that takes longer
+/// than `max_wait` from the power-on.
+pub struct WarmUp<L> {
+    inner: L,
+    policy: WarmUpPolicy,
+    warming_since: Option<Instant>,
+}
+
+impl<L: BraviaLink> WarmUp<L> {
+    pub fn new(inner: L, policy: WarmUpPolicy) -> Self {
+        Self {
+            inner,
+            policy,
+            warming_since: None,
+        }
+    }
+
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut L {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> L {
+        self.inner
+    }
+
+    /// Whether commands are being held for a recent power-on.
+    pub fn is_warming_up(&self) -> bool {
+        self.warming_since.is_some()
+    }
+
+    fn wait_ready(&mut self) -> Result<(), TransportError> {
+        let Some(since) = self.warming_since else {
+            return Ok(());
+        };
+        let packet = Power::query_packet();
+        loop {
+            // Timeouts and cancellations are expected until the TV is up.
+            if let Ok(data) = self.inner.send_query(&packet)
+                && Power::parse_response(&data).ok() == Some(PowerState::On)
+            {
+                self.warming_since = None;
+                return Ok(());
+            }
+            let waited = since.elapsed();
+            if waited >= self.policy.max_wait {
+                self.warming_since = None;
+                return Err(TransportError::NotReady(waited));
+            }
+            thread::sleep(self.policy.poll_interval.min(self.policy.max_wait - waited));
+        }
+    }
+}
+
+fn is_power_on(packet: &[u8]) -> bool {
+    Power::control_packet(&PowerAction::On) == *packet
+}
+
+impl<L: BraviaLink> BraviaLink for WarmUp<L> {
+    fn send_control(&mut self, packet: &[u8]) -> Result<(), TransportError> {
+        self.wait_ready()?;
+        self.inner.send_control(packet)?;
+        if is_power_on(packet) && !self.policy.max_wait.is_zero() {
+            self.warming_since = Some(Instant::now());
+        }
+        Ok(())
+    }
+
+    fn send_query(&mut self, packet: &[u8]) -> Result<Vec<u8>, TransportError> {
+        self.wait_ready()?;
+        self.inner.send_query(packet)
+    }
+
+    fn ensure_connected(&mut self) -> Result<(), TransportError> {
+        self.inner.ensure_connected()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::*;
+    use crate::transport::mock::MockTransport;
+
+    fn warm_up(max_wait_ms: u64) -> WarmUp<MockTransport> {
+        let policy = WarmUpPolicy {
+            max_wait: Duration::from_millis(max_wait_ms),
+            poll_interval: Duration::from_millis(5),
+        };
+        WarmUp::new(MockTransport::mock(), policy)
+    }
+
+    #[test]
+    fn test_holds_commands_until_power_is_on() {
+        let mut link = warm_up(1000);
+        let port = link.inner_mut().port_mut();
+        port.push_ack(ResponseCode::Success);
+        port.push_silence();
+        port.push_ack(ResponseCode::Canceled);
+        port.push_data(&[0x00]);
+        port.push_data(&[0x01]);
+        port.push_ack(ResponseCode::Success);
+
+        link.execute::<Power>(&PowerAction::On).unwrap();
+        assert!(link.is_warming_up());
+        link.execute::<InputSelect>(&InputType::Hdmi(2)).unwrap();
+        assert!(!link.is_warming_up());
+
+        let sent = link.inner().port().sent();
+        assert_eq!(sent.len(), 6);
+        assert!(sent[1..5].iter().all(|p| *p == build_query_packet(0x00)));
+        assert_eq!(sent[5], build_control_packet(0x02, &[0x04, 0x02]));
+    }
+
+    #[test]
+    fn test_gives_up_after_max_wait() {
+        let mut link = warm_up(30);
+        link.inner_mut().port_mut().push_ack(ResponseCode::Success);
+        link.execute::<Power>(&PowerAction::On).unwrap();
+        assert!(matches!(
+            link.query::<Volume>(),
+            Err(TransportError::NotReady(_))
+        ));
+        assert!(!link.is_warming_up());
+    }
+
+    #[test]
+    fn test_other_commands_do_not_wait() {
+        let mut link = warm_up(1000);
+        let port = link.inner_mut().port_mut();
+        port.push_ack(ResponseCode::Success);
+        port.push_ack(ResponseCode::Success);
+        link.execute::<Power>(&PowerAction::Off).unwrap();
+        link.execute::<Volume>(&VolumeAction::Up).unwrap();
+        assert_eq!(link.inner().port().sent().len(), 2);
+    }
+
+    #[test]
+    fn test_zero_max_wait_disables() {
+        let mut link = warm_up(0);
+        link.inner_mut().port_mut().push_ack(ResponseCode::Success);
+        link.execute::<Power>(&PowerAction::On).unwrap();
+        assert!(!link.is_warming_up());
+    }
+}
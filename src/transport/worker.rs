@@ -0,0 +1,197 @@
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::thread;
+use std::time::Duration;
+
+use thiserror::Error;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+
+use crate::transport::TransportError;
+use crate::transport::warmup::DEFAULT_MAX_WAIT;
+
+pub const DEFAULT_QUEUE_SIZE: usize = 16;
+/// Outlasts a full warm-up wait after power-on, so requests queued behind it
+/// are not timed out while the TV comes up.
+pub const DEFAULT_DEADLINE: Duration = DEFAULT_MAX_WAIT.saturating_add(Duration::from_secs(5));
+
+#[derive(Debug, Error)]
+pub enum WorkerError {
+    #[error("request queue is full")]
+    Busy,
+    #[error("request deadline exceeded")]
+    DeadlineExceeded,
+    #[error("transport worker stopped")]
+    Stopped,
+    #[error("request aborted by transport worker")]
+    Aborted,
+    #[error(transparent)]
+    Transport(#[from] TransportError),
+}
+
+type Job<L> = Box<dyn FnOnce(&mut L) + Send>;
+
+/// Handle to a dedicated thread that owns a link and runs requests against
+/// it one at a time, in arrival order.
+///
+/// Requests wait in a bounded queue. A request is dropped without touching
+/// the TV if its deadline passes or its caller goes away before the worker
+/// gets to it.
+pub struct LinkWorker<L> {
+    tx: mpsc::Sender<Job<L>>,
+    deadline: Duration,
+}
+
+impl<L> Clone for LinkWorker<L> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            deadline: self.deadline,
+        }
+    }
+}
+
+impl<L: Send + 'static> LinkWorker<L> {
+    pub fn spawn(mut link: L, queue_size: usize, deadline: Duration) -> Self {
+        let (tx, mut rx) = mpsc::channel::<Job<L>>(queue_size.max(1));
+        thread::Builder::new()
+            .name("bravia-link".to_string())
+            .spawn(move || {
+                while let Some(job) = rx.blocking_recv() {
+                    // A panicking request only fails itself; the worker and
+                    // the link stay available for the next one.
+                    let _ = catch_unwind(AssertUnwindSafe(|| job(&mut link)));
+                }
+            })
+            .expect("failed to spawn transport worker thread");
+        Self { tx, deadline }
+    }
+
+    /// Queues `f` and waits for its result, up to the request deadline.
+    pub async fn run<R, F>(&self, f: F) -> Result<R, WorkerError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut L) -> Result<R, TransportError> + Send + 'static,
+    {
+        let deadline = Instant::now() + self.deadline;
+        let (result_tx, result_rx) = oneshot::channel();
+        let job: Job<L> = Box::new(move |link| {
+            if result_tx.is_closed() || Instant::now() >= deadline {
+                return;
+            }
+            let _ = result_tx.send(f(link));
+        });
+
+        self.tx.try_send(job).map_err(|e| match e {
+            mpsc::error::TrySendError::Full(_) => WorkerError::Busy,
+            mpsc::error::TrySendError::Closed(_) => WorkerError::Stopped,
+        })?;
+
+        match tokio::time::timeout_at(deadline, result_rx).await {
+            Ok(Ok(result)) => Ok(result?),
+            Ok(Err(_)) if Instant::now() >= deadline => Err(WorkerError::DeadlineExceeded),
+            Ok(Err(_)) => Err(WorkerError::Aborted),
+            Err(_) => Err(WorkerError::DeadlineExceeded),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_runs_jobs_in_order() {
+        let worker = LinkWorker::spawn(Vec::new(), 4, DEFAULT_DEADLINE);
+        for n in 1..=2 {
+            worker
+                .run(move |log: &mut Vec<u8>| {
+                    log.push(n);
+                    Ok(())
+                })
+                .await
+                .unwrap();
+        }
+        let log = worker.run(|log| Ok(log.clone())).await.unwrap();
+        assert_eq!(log, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_queue_full_is_busy() {
+        let worker = LinkWorker::spawn((), 1, DEFAULT_DEADLINE);
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let blocker = {
+            let worker = worker.clone();
+            tokio::spawn(async move {
+                worker
+                    .run(move |_| {
+                        release_rx.recv().unwrap();
+                        Ok(())
+                    })
+                    .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // One request fits in the queue behind the blocked one; the next does not.
+        let queued = {
+            let worker = worker.clone();
+            tokio::spawn(async move { worker.run(|_| Ok(())).await })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(matches!(
+            worker.run(|_| Ok(())).await,
+            Err(WorkerError::Busy)
+        ));
+
+        release_tx.send(()).unwrap();
+        blocker.await.unwrap().unwrap();
+        queued.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_expired_and_cancelled_jobs_are_skipped() {
+        let worker = LinkWorker::spawn((), 4, Duration::from_millis(50));
+        let ran = Arc::new(AtomicUsize::new(0));
+
+        let blocker = {
+            let worker = worker.clone();
+            tokio::spawn(async move {
+                worker
+                    .run(|_| {
+                        thread::sleep(Duration::from_millis(100));
+                        Ok(())
+                    })
+                    .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let counter = Arc::clone(&ran);
+        let late = worker
+            .run(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            })
+            .await;
+        assert!(matches!(late, Err(WorkerError::DeadlineExceeded)));
+        assert!(matches!(
+            blocker.await.unwrap(),
+            Err(WorkerError::DeadlineExceeded)
+        ));
+
+        worker.run(|_| Ok(())).await.unwrap();
+        assert_eq!(ran.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_panicking_job_does_not_kill_worker() {
+        let worker = LinkWorker::spawn(0u8, 4, DEFAULT_DEADLINE);
+        let result = worker
+            .run(|_: &mut u8| -> Result<(), _> { panic!("boom") })
+            .await;
+        assert!(matches!(result, Err(WorkerError::Aborted)));
+        assert_eq!(worker.run(|n: &mut u8| Ok(*n + 1)).await.unwrap(), 1);
+    }
+}
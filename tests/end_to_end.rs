@@ -0,0 +1,626 @@
+//! Runs the compiled CLI and HTTP server against `sony-bravia-emulator` on a
+//! pty, checking the packets on the wire (read back from `--trace`) and the
+//! printed output or JSON.
+#![cfg(unix)]
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const CLI: &str = env!("CARGO_BIN_EXE_sony-bravia-cli");
+const EMULATOR: &str = env!("CARGO_BIN_EXE_sony-bravia-emulator");
+
+fn temp_path(name: &str) -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let n = NEXT.fetch_add(1, Ordering::SeqCst);
+    std::env::temp_dir().join(format!("bravia-e2e-{}-{}-{}", std::process::id(), n, name))
+}
+
+/// The packets a trace file records as sent, as hex strings.
+fn sent_packets(trace: &Path) -> Vec<String> {
+    fs::read_to_string(trace)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once(" TX "))
+        .map(|(_, rest)| rest.split("  #").next().unwrap().to_string())
+        .collect()
+}
+
+struct Emulator {
+    child: Child,
+    dev: String,
+}
+
+impl Emulator {
+    fn start(args: &[&str]) -> Self {
+        let mut child = Command::new(EMULATOR)
+            .args(args)
+            .arg("--quiet")
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("failed to start emulator");
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let dev = line
+            .trim()
+            .strip_prefix("pty: ")
+            .expect("emulator did not report its pty")
+            .to_string();
+        Self { child, dev }
+    }
+}
+
+impl Drop for Emulator {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+struct Run {
+    output: Output,
+    packets: Vec<String>,
+}
+
+impl Run {
+    fn stdout(&self) -> String {
+        String::from_utf8_lossy(&self.output.stdout).into_owned()
+    }
+
+    fn stderr(&self) -> String {
+        String::from_utf8_lossy(&self.output.stderr).into_owned()
+    }
+}
+
+/// Runs the CLI with `args`, tracing the wire.
+fn cli(args: &[&str]) -> Run {
+    let trace = temp_path("trace.log");
+    let output = Command::new(CLI)
+        .arg("--trace")
+        .arg(&trace)
+        .args(args)
+        .output()
+        .expect("failed to run CLI");
+    let packets = sent_packets(&trace);
+    let _ = fs::remove_file(&trace);
+    Run { output, packets }
+}
+
+fn run(tv: &Emulator, args: &[&str]) -> Run {
+    let mut full = vec!["--dev", &tv.dev];
+    full.extend_from_slice(args);
+    cli(&full)
+}
+
+#[test]
+fn test_every_command_flag() {
+    let tv = Emulator::start(&["--warm-up", "0"]);
+    #[rustfmt::skip]
+    let cases: &[(&[&str], &[&str], &str)] = &[
+        (&["--power", "on"], &["8C 00 00 02 01 8F"], "Power: on"),
+        (&["--power-query"], &["83 00 00 FF FF 81"], "Power: On"),
+        (&["--standby", "enable"], &["8C 00 01 02 01 90"], "Standby: enable"),
+        (&["--input", "hdmi3"], &["8C 00 02 03 04 03 98"], "Input: hdmi3"),
+        (&["--input-query"], &["83 00 02 FF FF 83"], "Input: hdmi3"),
+        (&["--input-toggle"], &["8C 00 02 02 00 90"], "Input toggled"),
+        (&["--volume-set", "55"], &["8C 00 05 03 01 37 CC"], "Volume set: 55"),
+        (&["--volume", "up"], &["8C 00 05 03 00 00 94"], "Volume: up"),
+        (&["--volume", "down"], &["8C 00 05 03 00 01 95"], "Volume: down"),
+        (&["--volume-query"], &["83 00 05 FF FF 86"], "Volume: 55"),
+        (&["--mute", "toggle"], &["8C 00 06 02 00 94"], "Mute: toggle"),
+        (&["--mute", "off"], &["8C 00 06 03 01 00 96"], "Mute: off"),
+        (&["--mute", "on"], &["8C 00 06 03 01 01 97"], "Mute: on"),
+        (&["--mute-set", "off"], &["8C 00 06 03 01 00 96"], "Mute: off"),
+        (&["--mute-set", "on"], &["8C 00 06 03 01 01 97"], "Mute: on"),
+        (&["--mute-query"], &["83 00 06 FF FF 87"], "Mute: Muted"),
+        (&["--sleep", "30"], &["8C 00 0C 03 01 1E BA"], "Sleep: 30"),
+        (&["--sleep-toggle"], &["8C 00 0C 02 00 9A"], "Sleep toggled"),
+        (&["--display"], &["8C 00 0F 02 00 9D"], "Display toggled"),
+        (&["--picture", "off"], &["8C 00 0D 03 01 00 9D"], "Picture: off"),
+        (&["--picture", "toggle"], &["8C 00 0D 02 00 9B"], "Picture: toggle"),
+        (&["--picture-mode", "cinema"], &["8C 00 20 03 01 02 B2"], "Picture mode: cinema"),
+        (&["--picture-mode-toggle"], &["8C 00 20 02 00 AE"], "Picture mode toggled"),
+        (&["--brightness", "up"], &["8C 00 24 03 00 00 B3"], "Brightness: up"),
+        (&["--brightness-set", "20"], &["8C 00 24 03 01 14 C8"], "Brightness set: 20"),
+        (&["--contrast", "down"], &["8C 00 23 03 00 01 B3"], "Contrast: down"),
+        (&["--contrast-set", "40"], &["8C 00 23 03 01 28 DB"], "Contrast set: 40"),
+        (&["--color", "up"], &["8C 00 25 03 00 00 B4"], "Color: up"),
+        (&["--color-set", "30"], &["8C 00 25 03 01 1E D3"], "Color set: 30"),
+        (&["--sharpness", "down"], &["8C 00 28 03 00 01 B8"], "Sharpness: down"),
+        (&["--sharpness-set", "10"], &["8C 00 28 03 01 0A C2"], "Sharpness set: 10"),
+        (&["--cine-motion", "auto"], &["8C 00 2A 02 01 B9"], "Cine motion: auto"),
+        (&["--hue-red", "up"], &["8C 00 26 04 00 00 00 B6"], "Hue red: up"),
+        (&["--hue-red-set", "60"], &["8C 00 26 04 01 00 3C F3"], "Hue red set: 60"),
+        (&["--hue-green", "down"], &["8C 00 26 04 00 01 01 B8"], "Hue green: down"),
+        (&["--hue-green-set", "40"], &["8C 00 26 04 01 01 28 E0"], "Hue green set: 40"),
+        (&["--wide", "zoom"], &["8C 00 44 03 01 02 D6"], "Wide: zoom"),
+        (&["--auto-wide", "on"], &["8C 00 45 03 01 01 D6"], "Auto wide: on"),
+        (&["--four-three-mode", "normal"], &["8C 00 46 03 01 04 DA"], "4:3 mode: normal"),
+        (&["--h-shift", "up"], &["8C 00 41 03 00 00 D0"], "H shift: up"),
+        (&["--h-shift-set", "10"], &["8C 00 41 04 01 01 39 0C"], "H shift set: 10"),
+        (&["--v-size", "down"], &["8C 00 42 03 00 01 D2"], "V size: down"),
+        (&["--v-size-set", "5"], &["8C 00 42 04 01 01 2C 00"], "V size set: 5"),
+        (&["--v-shift", "up"], &["8C 00 43 03 00 00 D2"], "V shift: up"),
+        (&["--v-shift-set", "7"], &["8C 00 43 04 01 01 2A FF"], "V shift set: 7"),
+        (&["--sound-mode", "music"], &["8C 00 30 03 01 06 C6"], "Sound mode: music"),
+        (&["--speaker", "off"], &["8C 00 36 03 01 01 C7"], "Speaker: off"),
+        (&["--sircs", "home"], &["8C 00 67 03 01 60 57"], "SIRCS: home"),
+        (&["--language", "eng"], &["8C 00 07 05 00 65 6E 67 D2"], "Language: eng"),
+        (&["--product-info", "query"], &["83 00 6E FF FF EF", "83 00 6D FF FF EE", "83 00 6C FF FF ED"], "Product Info 1: [4b, 44, 4c, 2d, 45, 4d, 55]"),
+        (&["--device-id", "query"], &["83 00 6F FF FF F0"], "Device ID: [45, 4d, 55, 2d, 30, 30, 30, 31]"),
+        (&["--raw-control", "05", "01", "0a"], &["8C 00 05 03 01 0A 9F"], "Response: Success"),
+        (&["--raw-query", "0x05"], &["83 00 05 FF FF 86"], "Data: [0a]"),
+        (&["--status"], &["83 00 00 FF FF 81", "83 00 05 FF FF 86", "83 00 06 FF FF 87", "83 00 02 FF FF 83"], "Volume: 10\nMute: Muted\nInput: hdmi4"),
+    ];
+    for (args, packets, output) in cases {
+        let result = run(&tv, args);
+        assert!(
+            result.output.status.success(),
+            "{:?}: {}",
+            args,
+            result.stderr()
+        );
+        assert_eq!(&result.packets, packets, "{:?}", args);
+        assert!(
+            result.stdout().contains(output),
+            "{:?}: {}",
+            args,
+            result.stdout()
+        );
+    }
+}
+
+#[test]
+fn test_rejections_are_reported() {
+    let tv = Emulator::start(&["--warm-up", "0"]);
+    assert!(run(&tv, &["--volume-set", "100"]).output.status.success());
+
+    let result = run(&tv, &["--volume", "up"]);
+    assert_eq!(result.output.status.code(), Some(1));
+    assert!(result.stderr().contains("command rejected: LimitOverMax"));
+    assert_eq!(result.packets.len(), 1, "limits are not retried");
+
+    let result = run(&tv, &["--raw-control", "05", "01", "C8"]);
+    assert!(result.output.status.success());
+    assert!(result.stdout().contains("Response: LimitOverMax"));
+}
+
+#[test]
+fn test_serial_flags_and_config() {
+    let tv = Emulator::start(&["--warm-up", "0"]);
+    let result = run(
+        &tv,
+        &[
+            "--baud",
+            "19200",
+            "--data-bits",
+            "7",
+            "--parity",
+            "even",
+            "--stop-bits",
+            "2",
+            "--flow-control",
+            "hardware",
+            "--read-timeout",
+            "200",
+            "--power-query",
+        ],
+    );
+    assert!(result.output.status.success(), "{}", result.stderr());
+    assert_eq!(result.packets, ["83 00 00 FF FF 81"]);
+
+    // A pty has no modem control lines to set.
+    for flag in ["--dtr", "--rts"] {
+        let result = run(&tv, &[flag, "on", "--power-query"]);
+        assert_eq!(result.output.status.code(), Some(1));
+        let setting = flag.trim_start_matches("--");
+        assert!(
+            result
+                .stderr()
+                .contains(&format!("serial setting {} rejected", setting))
+        );
+        assert!(result.packets.is_empty());
+    }
+
+    let config = temp_path("config.toml");
+    fs::write(
+        &config,
+        format!("dev = \"{}\"\n\n[serial]\nbaud = 9600\n", tv.dev),
+    )
+    .unwrap();
+    let result = cli(&["--config", config.to_str().unwrap(), "--volume-query"]);
+    let _ = fs::remove_file(&config);
+    assert!(result.output.status.success(), "{}", result.stderr());
+    assert_eq!(result.stdout(), "Volume: 20\n");
+}
+
+#[test]
+fn test_retries_and_warm_up() {
+    let tv = Emulator::start(&["--standby", "--warm-up", "300"]);
+
+    // Everything but power is canceled in standby.
+    let result = run(
+        &tv,
+        &[
+            "--mute",
+            "on",
+            "--retries",
+            "0",
+            "--control-retries",
+            "2",
+            "--retry-backoff",
+            "0",
+            "--retry-jitter",
+            "0",
+            "--canceled-retry-delay",
+            "10",
+            "--min-gap",
+            "0",
+        ],
+    );
+    assert_eq!(result.output.status.code(), Some(1));
+    assert!(result.stderr().contains("Canceled"));
+    assert_eq!(result.packets, ["8C 00 06 03 01 01 97"; 3]);
+
+    let result = run(
+        &tv,
+        &[
+            "--power",
+            "on",
+            "--volume-query",
+            "--warm-up-poll",
+            "20",
+            "--min-gap",
+            "0",
+            "--slow-gap",
+            "0",
+        ],
+    );
+    assert!(result.output.status.success(), "{}", result.stderr());
+    assert!(result.stdout().contains("Volume: 20"));
+    let (first, rest) = result.packets.split_first().unwrap();
+    assert_eq!(first, "8C 00 00 02 01 8F");
+    assert_eq!(rest.last().unwrap(), "83 00 05 FF FF 86");
+    let polls = &rest[..rest.len() - 1];
+    assert!(
+        polls.len() >= 2 && polls.iter().all(|p| p == "83 00 00 FF FF 81"),
+        "{:?}",
+        polls
+    );
+
+    let tv = Emulator::start(&["--standby", "--warm-up", "10000"]);
+    let result = run(
+        &tv,
+        &[
+            "--power",
+            "on",
+            "--volume-query",
+            "--warm-up-timeout",
+            "100",
+            "--warm-up-poll",
+            "20",
+            "--slow-gap",
+            "0",
+        ],
+    );
+    assert_eq!(result.output.status.code(), Some(1));
+    assert!(result.stderr().contains("not ready"), "{}", result.stderr());
+}
+
+#[test]
+fn test_pacing_gaps() {
+    let tv = Emulator::start(&["--warm-up", "0"]);
+    let started = Instant::now();
+    let result = run(
+        &tv,
+        &["--volume", "up", "--mute", "toggle", "--min-gap", "200"],
+    );
+    assert!(result.output.status.success());
+    assert!(started.elapsed() >= Duration::from_millis(200));
+
+    // Power is a slow command; the warm-up check after it waits too.
+    let started = Instant::now();
+    let result = run(
+        &tv,
+        &[
+            "--power",
+            "on",
+            "--mute",
+            "toggle",
+            "--min-gap",
+            "0",
+            "--slow-gap",
+            "300",
+        ],
+    );
+    assert!(result.output.status.success());
+    assert!(started.elapsed() >= Duration::from_millis(300));
+    assert_eq!(
+        result.packets,
+        [
+            "8C 00 00 02 01 8F",
+            "83 00 00 FF FF 81",
+            "8C 00 06 02 00 94"
+        ]
+    );
+}
+
+#[test]
+fn test_trace_replay_dry_run_and_decode() {
+    let tv = Emulator::start(&["--warm-up", "0"]);
+    let trace = temp_path("replay.log");
+    let status = Command::new(CLI)
+        .args([
+            "--dev",
+            &tv.dev,
+            "--trace",
+            trace.to_str().unwrap(),
+            "--volume-query",
+        ])
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success());
+    let replay = Command::new(CLI)
+        .args(["--replay", trace.to_str().unwrap()])
+        .output()
+        .unwrap();
+    let _ = fs::remove_file(&trace);
+    assert_eq!(
+        String::from_utf8_lossy(&replay.stdout),
+        "83 00 05 FF FF 86 (query Volume): data 14\n"
+    );
+
+    let dry_run = Command::new(CLI)
+        .args(["--dry-run", "--power", "on", "--input", "hdmi2"])
+        .output()
+        .unwrap();
+    assert!(dry_run.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&dry_run.stdout),
+        "dry-run: 8C 00 00 02 01 8F  # control Power\nPower: on\n\
+         dry-run: 8C 00 02 03 04 02 97  # control InputSelect\nInput: hdmi2\n"
+    );
+    let dry_run = Command::new(CLI)
+        .args(["--dry-run", "--power-query"])
+        .output()
+        .unwrap();
+    assert_eq!(dry_run.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&dry_run.stderr).contains("not available in dry-run"));
+
+    let decoded = Command::new(CLI)
+        .args(["decode", "8c", "00", "00", "02", "01", "8f"])
+        .output()
+        .unwrap();
+    let decoded = String::from_utf8_lossy(&decoded.stdout);
+    assert!(decoded.contains("function: 0x00 Power"), "{}", decoded);
+    assert!(decoded.contains("data:     01 = On"), "{}", decoded);
+}
+
+#[test]
+fn test_discover_skips_unlisted_pty() {
+    let tv = Emulator::start(&["--warm-up", "0"]);
+    let result = cli(&["--discover", "--probe-baud", "9600"]);
+    // The pty is not a system serial port, so discovery never probes it.
+    assert!(!result.stdout().contains(&tv.dev));
+    assert!(result.stdout().contains("serial port") || result.stdout().contains("baud"));
+}
+
+// === HTTP Server ===
+
+struct Server {
+    child: Child,
+    port: u16,
+    trace: PathBuf,
+}
+
+impl Server {
+    fn start(dev: &str, args: &[&str]) -> Self {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let trace = temp_path("http-trace.log");
+        let child = Command::new(CLI)
+            .args(["--dev", dev, "--http-server", "--http-host", "127.0.0.1"])
+            .args([
+                "--http-port",
+                &port.to_string(),
+                "--trace",
+                trace.to_str().unwrap(),
+            ])
+            .args([
+                "--warm-up-timeout",
+                "0",
+                "--min-gap",
+                "0",
+                "--slow-gap",
+                "0",
+            ])
+            .args(args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("failed to start server");
+        let started = Instant::now();
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            assert!(
+                started.elapsed() < Duration::from_secs(10),
+                "server did not start"
+            );
+            thread::sleep(Duration::from_millis(50));
+        }
+        Self { child, port, trace }
+    }
+
+    /// Sends one request and returns the status, the body and the packets
+    /// it put on the wire.
+    fn request(&self, method: &str, path: &str, body: &str) -> (u16, String, Vec<String>) {
+        let before = sent_packets(&self.trace).len();
+        let mut stream = TcpStream::connect(("127.0.0.1", self.port)).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+             Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = response.split_once("\r\n\r\n").unwrap().1.to_string();
+        (status, body, sent_packets(&self.trace).split_off(before))
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = fs::remove_file(&self.trace);
+    }
+}
+
+#[test]
+fn test_every_http_route() {
+    let tv = Emulator::start(&["--warm-up", "0"]);
+    let server = Server::start(
+        &tv.dev,
+        &["--http-queue-size", "4", "--http-request-timeout", "2000"],
+    );
+    #[rustfmt::skip]
+    let cases: &[(&str, &str, &[&str], &str)] = &[
+        ("GET", "/health", &[], r#"{"status":"ok","connected":true,"last_error":null,"reconnects":0}"#),
+        ("GET", "/status", &["83 00 00 FF FF 81"], r#"{"power":"on"}"#),
+        ("GET", "/power", &["83 00 00 FF FF 81"], r#"{"power":"on"}"#),
+        ("GET", "/volume", &["83 00 05 FF FF 86"], r#"{"level":20}"#),
+        ("GET", "/input", &["83 00 02 FF FF 83"], r#"{"input":"hdmi1"}"#),
+        ("GET", "/mute", &["83 00 06 FF FF 87"], r#"{"muted":false}"#),
+        ("GET", "/product-info", &["83 00 6E FF FF EF", "83 00 6D FF FF EE", "83 00 6C FF FF ED"], r#"{"info1":"KDL-EMU","info2":"EMULATOR","info3":"0000001"}"#),
+        ("GET", "/device-id", &["83 00 6F FF FF F0"], r#"{"id":"EMU-0001"}"#),
+        ("POST", "/power/on", &["8C 00 00 02 01 8F"], "Power on"),
+        ("POST", "/standby/enable", &["8C 00 01 02 01 90"], "Standby enabled"),
+        ("POST", "/standby/disable", &["8C 00 01 02 00 8F"], "Standby disabled"),
+        ("POST", "/input/hdmi2", &["8C 00 02 03 04 02 97"], "Input set to hdmi2"),
+        ("POST", "/input/toggle", &["8C 00 02 02 00 90"], "Input toggled"),
+        ("POST", "/volume/up", &["8C 00 05 03 00 00 94"], "Volume up"),
+        ("POST", "/volume/down", &["8C 00 05 03 00 01 95"], "Volume down"),
+        ("POST", "/volume/set/40", &["8C 00 05 03 01 28 BD"], "Volume set to 40"),
+        ("POST", "/mute/toggle", &["8C 00 06 02 00 94"], "Mute toggled"),
+        ("POST", "/mute/on", &["8C 00 06 03 01 01 97"], "Mute on"),
+        ("POST", "/mute/off", &["8C 00 06 03 01 00 96"], "Mute off"),
+        ("POST", "/sleep/30", &["8C 00 0C 03 01 1E BA"], "Sleep timer set to 30 minutes"),
+        ("POST", "/sleep/toggle", &["8C 00 0C 02 00 9A"], "Sleep timer toggled"),
+        ("POST", "/picture/mode/game", &["8C 00 20 03 01 08 B8"], "Picture mode set to game"),
+        ("POST", "/picture/mode/toggle", &["8C 00 20 02 00 AE"], "Picture mode toggled"),
+        ("POST", "/picture/brightness/up", &["8C 00 24 03 00 00 B3"], "Brightness up"),
+        ("POST", "/picture/brightness/set/20", &["8C 00 24 03 01 14 C8"], "Brightness set to 20"),
+        ("POST", "/picture/contrast/down", &["8C 00 23 03 00 01 B3"], "Contrast down"),
+        ("POST", "/picture/contrast/set/30", &["8C 00 23 03 01 1E D1"], "Contrast set to 30"),
+        ("POST", "/picture/color/up", &["8C 00 25 03 00 00 B4"], "Color up"),
+        ("POST", "/picture/color/set/25", &["8C 00 25 03 01 19 CE"], "Color set to 25"),
+        ("POST", "/picture/hue/red/up", &["8C 00 26 04 00 00 00 B6"], "Hue red up"),
+        ("POST", "/picture/hue/red/set/60", &["8C 00 26 04 01 00 3C F3"], "Hue red set to 60"),
+        ("POST", "/picture/hue/green/down", &["8C 00 26 04 00 01 01 B8"], "Hue green down"),
+        ("POST", "/picture/hue/green/set/40", &["8C 00 26 04 01 01 28 E0"], "Hue green set to 40"),
+        ("POST", "/picture/sharpness/down", &["8C 00 28 03 00 01 B8"], "Sharpness down"),
+        ("POST", "/picture/sharpness/set/10", &["8C 00 28 03 01 0A C2"], "Sharpness set to 10"),
+        ("POST", "/picture/off", &["8C 00 0D 03 01 00 9D"], "Picture off"),
+        ("POST", "/picture/on", &["8C 00 0D 03 01 01 9E"], "Picture on"),
+        ("POST", "/picture/toggle", &["8C 00 0D 02 00 9B"], "Picture toggled"),
+        ("POST", "/picture/cine-motion/auto", &["8C 00 2A 02 01 B9"], "Cine motion on"),
+        ("POST", "/display/toggle", &["8C 00 0F 02 00 9D"], "Display toggled"),
+        ("POST", "/language/eng", &["8C 00 07 05 00 65 6E 67 D2"], "Language set to eng"),
+        ("POST", "/screen/wide/full", &["8C 00 44 03 01 01 D5"], "Wide mode set to full"),
+        ("POST", "/screen/auto-wide/on", &["8C 00 45 03 01 01 D6"], "Auto wide control executed"),
+        ("POST", "/screen/4-3-mode/normal", &["8C 00 46 03 01 04 DA"], "4:3 mode set to normal"),
+        ("POST", "/screen/h-shift/up", &["8C 00 41 03 00 00 D0"], "H-shift up"),
+        ("POST", "/screen/h-shift/set/70", &["8C 00 41 04 01 00 03 D5"], "H-shift set to 70"),
+        ("POST", "/screen/v-size/down", &["8C 00 42 03 00 01 D2"], "V-size down"),
+        ("POST", "/screen/v-size/set/50", &["8C 00 42 04 01 00 00 D3"], "V-size set to 50"),
+        ("POST", "/screen/v-shift/up", &["8C 00 43 03 00 00 D2"], "V-shift up"),
+        ("POST", "/screen/v-shift/set/50", &["8C 00 43 04 01 00 00 D4"], "V-shift set to 50"),
+        ("POST", "/sound/mode/cinema", &["8C 00 30 03 01 04 C4"], "Sound mode set to cinema"),
+        ("POST", "/sound/speaker/on", &["8C 00 36 03 01 00 C6"], "Speaker control executed"),
+        ("POST", "/sircs/home", &["8C 00 67 03 01 60 57"], "SIRCS button pressed"),
+        ("POST", "/power/off", &["8C 00 00 02 00 8E"], "Power off"),
+    ];
+    for (method, path, packets, body) in cases {
+        let (status, response, sent) = server.request(method, path, "");
+        assert_eq!(status, 200, "{} {}: {}", method, path, response);
+        assert_eq!(&sent, packets, "{} {}", method, path);
+        if *method == "POST" {
+            let expected = format!(r#"{{"success":true,"message":"{}"}}"#, body);
+            assert_eq!(response, expected, "{} {}", method, path);
+        } else {
+            assert_eq!(&response, body, "{} {}", method, path);
+        }
+    }
+
+    // Standby cancels the query; bad paths never reach the TV.
+    let (status, _, sent) = server.request("GET", "/volume", "");
+    assert_eq!(
+        (status, sent.len()),
+        (500, 3),
+        "canceled queries are retried, then fail"
+    );
+    for path in ["/volume/set/101", "/power/bogus"] {
+        let (status, _, sent) = server.request("POST", path, "");
+        assert_eq!(status, 400, "{}", path);
+        assert!(sent.is_empty());
+    }
+}
+
+#[test]
+fn test_raw_route() {
+    let tv = Emulator::start(&["--warm-up", "0"]);
+    let server = Server::start(&tv.dev, &[]);
+    let (status, body, sent) = server.request("POST", "/raw", r#"{"kind":"query","function":5}"#);
+    assert_eq!(
+        (status, body.as_str()),
+        (200, r#"{"code":"Success","data":[20]}"#)
+    );
+    assert_eq!(sent, ["83 00 05 FF FF 86"]);
+    let (status, body, sent) = server.request(
+        "POST",
+        "/raw",
+        r#"{"kind":"control","function":5,"data":[1,200]}"#,
+    );
+    assert_eq!(
+        (status, body.as_str()),
+        (200, r#"{"code":"LimitOverMax","data":[]}"#)
+    );
+    assert_eq!(sent, ["8C 00 05 03 01 C8 5D"]);
+    drop(server);
+
+    let server = Server::start(&tv.dev, &["--http-disable-raw"]);
+    let (status, _, sent) = server.request("POST", "/raw", r#"{"kind":"query","function":5}"#);
+    assert_eq!(status, 403);
+    assert!(sent.is_empty());
+}
+
+#[test]
+fn test_health_while_device_missing() {
+    let dev = temp_path("missing-tty");
+    let server = Server::start(dev.to_str().unwrap(), &[]);
+    let (status, body, _) = server.request("GET", "/health", "");
+    assert_eq!(status, 503);
+    assert!(
+        body.contains(r#""status":"degraded","connected":false"#),
+        "{}",
+        body
+    );
+    let (status, _, _) = server.request("GET", "/power", "");
+    assert_eq!(status, 503);
+}